@@ -0,0 +1,29 @@
+//! Builds the seed corpus of both fuzz targets from `transactions.csv` in the repository root.
+//!
+//! Run from the `fuzz` directory with `cargo run --example seed_corpus`.
+
+use std::fs;
+
+use rtm_cli::csv_reader::CsvReader;
+use rtm_core::models::AmountPolicies;
+use rtm_fuzz::Operation;
+
+fn main() {
+    let content = fs::read("../transactions.csv").expect("transactions.csv should be readable");
+
+    fs::create_dir_all("corpus/csv_reader").unwrap();
+    fs::write("corpus/csv_reader/transactions.csv", &content).unwrap();
+
+    let mut encoded = Vec::new();
+    let mut reader = CsvReader::with_amount_policies(content.as_slice(), AmountPolicies::default());
+    for record in reader.read_iter().expect("transactions.csv should have valid headers") {
+        let Ok(record) = record else {
+            continue;
+        };
+        if let Some(operation) = Operation::from_accounting_operation(&record.operation) {
+            operation.encode(&mut encoded);
+        }
+    }
+    fs::create_dir_all("corpus/accounting_system").unwrap();
+    fs::write("corpus/accounting_system/transactions", encoded).unwrap();
+}
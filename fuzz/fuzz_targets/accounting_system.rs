@@ -0,0 +1,25 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtm_core::processor::{AccountingSystem, TransactionError};
+use rtm_fuzz::{Model, Operation, accounting_system_balances};
+
+fuzz_target!(|data: &[u8]| {
+    let mut accounting_system = AccountingSystem::new();
+    let mut model = Model::default();
+    for operation in Operation::decode_all(data) {
+        let expected = model.apply(operation);
+        let result = accounting_system
+            .run_operation(operation.to_accounting_operation())
+            .map_err(TransactionError::name);
+        assert_eq!(result, expected, "result of {operation:?}");
+        assert_eq!(
+            accounting_system_balances(&accounting_system),
+            model.balances(),
+            "balances after {operation:?}"
+        );
+        if let Err(violations) = accounting_system.verify_invariants() {
+            panic!("invariants violated after {operation:?}: {violations:?}");
+        }
+    }
+});
@@ -0,0 +1,28 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rtm_cli::csv_reader::{CsvReader, CsvReaderError};
+use rtm_core::models::AmountPolicies;
+
+fuzz_target!(|data: &[u8]| {
+    let mut reader = CsvReader::with_amount_policies(data, AmountPolicies::default());
+    let Ok(records) = reader.read_iter() else {
+        return;
+    };
+    let mut last_line = 0;
+    for record in records {
+        let line = match record {
+            Ok(record) => record.line,
+            Err(
+                CsvReaderError::UnreadableRow { line, .. }
+                | CsvReaderError::InvalidFieldCount { line, .. }
+                | CsvReaderError::InvalidField { line, .. },
+            ) => line,
+            Err(error @ (CsvReaderError::HeaderNotFound | CsvReaderError::InvalidHeaders)) => {
+                panic!("header error after reading headers: {error}")
+            }
+        };
+        assert!(line >= last_line, "line {line} reported after line {last_line}");
+        last_line = line;
+    }
+});
@@ -0,0 +1,8 @@
+//! Shared code of the fuzz targets: a compact binary encoding of operation sequences and
+//! a reference model of the accounting rules, used for differential fuzzing.
+
+mod model;
+pub use model::*;
+
+mod operation;
+pub use operation::*;
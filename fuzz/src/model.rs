@@ -0,0 +1,162 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use rtm_core::processor::AccountingSystem;
+
+use crate::{MAX_AMOUNT, Operation, to_fixed_point};
+
+/// Balances of a single client: available, held and whether the account is locked.
+pub type ModelBalances = (i128, i128, bool);
+
+#[derive(Debug, Default)]
+struct ModelAccount {
+    available: i128,
+    held: i128,
+    locked: bool,
+    transactions: HashMap<u32, i128>,
+    disputed: HashSet<u32>,
+}
+
+impl ModelAccount {
+    fn move_funds(&mut self, held_change: i128, available_change: i128) -> Result<(), &'static str> {
+        let (held, available) = (self.held + held_change, self.available + available_change);
+        if overflows(held) || overflows(available) || overflows(held + available) {
+            return Err("ArithmeticOverflow");
+        }
+        self.held = held;
+        self.available = available;
+        Ok(())
+    }
+}
+
+/// Straightforward reimplementation of the accounting rules with default configuration,
+/// on fixed point integers. Balances are kept within [`MAX_AMOUNT`], so that they can never
+/// overflow the integers themselves.
+#[derive(Debug, Default)]
+pub struct Model {
+    accounts: BTreeMap<u16, ModelAccount>,
+    seen: HashSet<u32>,
+}
+
+impl Model {
+    /// Applies the operation, returning the name of the expected
+    /// [`TransactionError`](rtm_core::processor::TransactionError) if it is rejected.
+    pub fn apply(&mut self, operation: Operation) -> Result<(), &'static str> {
+        let (client, tx) = match operation {
+            Operation::Deposit { client, tx, .. }
+            | Operation::Withdrawal { client, tx, .. }
+            | Operation::Dispute { client, tx }
+            | Operation::Resolve { client, tx }
+            | Operation::Chargeback { client, tx } => (client, tx),
+        };
+        // Rejected operations leave no account behind.
+        let created = !self.accounts.contains_key(&client);
+        let result = self.apply_to_account(client, tx, operation);
+        if created && result.is_err() {
+            self.accounts.remove(&client);
+        }
+        result
+    }
+
+    fn apply_to_account(&mut self, client: u16, tx: u32, operation: Operation) -> Result<(), &'static str> {
+        let account = self.accounts.entry(client).or_default();
+        // Disputes opened before a chargeback can still be settled on a locked account.
+        let settles_dispute = matches!(operation, Operation::Resolve { .. } | Operation::Chargeback { .. })
+            && account.disputed.contains(&tx);
+        if account.locked && !settles_dispute {
+            return Err("AccountLocked");
+        }
+
+        let amount = match operation {
+            Operation::Deposit { amount, .. } => signed(amount),
+            Operation::Withdrawal { amount, .. } => -signed(amount),
+            Operation::Dispute { .. } | Operation::Resolve { .. } | Operation::Chargeback { .. } => {
+                let Some(amount) = account.transactions.get(&tx).copied() else {
+                    return Err("TransactionDoesNotExist");
+                };
+                amount
+            }
+        };
+
+        match operation {
+            Operation::Deposit { .. } | Operation::Withdrawal { .. } => {
+                if amount == 0 {
+                    return Err("NonPositiveAmount");
+                }
+                if overflows(account.available + amount) || overflows(account.available + amount + account.held) {
+                    return Err("ArithmeticOverflow");
+                }
+                if account.available + amount < 0 {
+                    return Err("InsufficientFunds");
+                }
+                if !self.seen.insert(tx) {
+                    return Err("DuplicateTransaction");
+                }
+                account.available += amount;
+                account.transactions.insert(tx, amount);
+            }
+            Operation::Dispute { .. } => {
+                if account.disputed.contains(&tx) {
+                    return Err("TransactionAlreadyDisputed");
+                }
+                let (held_change, available_change) = dispute_changes(amount);
+                account.move_funds(held_change, available_change)?;
+                account.disputed.insert(tx);
+            }
+            Operation::Resolve { .. } => {
+                if !account.disputed.contains(&tx) {
+                    return Err("TransactionNotDisputed");
+                }
+                let (held_change, available_change) = dispute_changes(amount);
+                account.move_funds(-held_change, -available_change)?;
+                account.disputed.remove(&tx);
+            }
+            Operation::Chargeback { .. } => {
+                if !account.disputed.contains(&tx) {
+                    return Err("TransactionNotDisputed");
+                }
+                // Charged back withdrawal is returned to the client.
+                account.move_funds(-amount.abs(), (-amount).max(0))?;
+                account.disputed.remove(&tx);
+                account.locked = true;
+            }
+        }
+        Ok(())
+    }
+
+    /// Balances of all tracked clients, ordered by client id.
+    pub fn balances(&self) -> Vec<(u16, ModelBalances)> {
+        self.accounts
+            .iter()
+            .map(|(client, account)| (*client, (account.available, account.held, account.locked)))
+            .collect()
+    }
+}
+
+/// Held and available changes of disputing a transaction with the signed amount: a deposit is
+/// moved from available to held funds, a withdrawal is held on top of available funds.
+fn dispute_changes(amount: i128) -> (i128, i128) {
+    if amount > 0 { (amount, -amount) } else { (-amount, 0) }
+}
+
+fn signed(amount: u128) -> i128 {
+    i128::try_from(amount).expect("encoded amounts have at most 96 bits")
+}
+
+fn overflows(balance: i128) -> bool {
+    balance.unsigned_abs() > MAX_AMOUNT
+}
+
+/// Balances of all accounts of the accounting system, in the format of [`Model::balances`].
+pub fn accounting_system_balances(accounting_system: &AccountingSystem) -> Vec<(u16, ModelBalances)> {
+    accounting_system
+        .iter_accounts()
+        .map(|account| {
+            let balances = (
+                to_fixed_point(&account.available_balance),
+                to_fixed_point(&account.held_balance),
+                account.state == rtm_core::processor::ClientAccountState::Locked,
+            );
+            (account.client_id.as_u16(), balances)
+        })
+        .collect()
+}
@@ -0,0 +1,133 @@
+use rtm_core::models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind};
+
+/// Size of a single encoded operation: kind, client, transaction id and a 96 bit amount.
+pub const ENCODED_SIZE: usize = 1 + 2 + 4 + 12;
+
+/// Largest amount as a fixed point integer, i.e. the largest mantissa of a decimal.
+pub const MAX_AMOUNT: u128 = (1 << 96) - 1;
+
+/// Operation with the amount as a fixed point integer with 4 decimal places, up to [`MAX_AMOUNT`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operation {
+    Deposit { client: u16, tx: u32, amount: u128 },
+    Withdrawal { client: u16, tx: u32, amount: u128 },
+    Dispute { client: u16, tx: u32 },
+    Resolve { client: u16, tx: u32 },
+    Chargeback { client: u16, tx: u32 },
+}
+
+impl Operation {
+    /// Decodes all complete operations, ignoring trailing bytes. Every chunk of
+    /// [`ENCODED_SIZE`] bytes is a valid operation, so no fuzzer input is wasted.
+    pub fn decode_all(data: &[u8]) -> Vec<Operation> {
+        data.chunks_exact(ENCODED_SIZE).map(Operation::decode).collect()
+    }
+
+    fn decode(chunk: &[u8]) -> Operation {
+        let client = u16::from_le_bytes([chunk[1], chunk[2]]);
+        let tx = u32::from_le_bytes([chunk[3], chunk[4], chunk[5], chunk[6]]);
+        let mut amount = [0; 16];
+        amount[..12].copy_from_slice(&chunk[7..19]);
+        let amount = u128::from_le_bytes(amount);
+        match chunk[0] % 5 {
+            0 => Operation::Deposit { client, tx, amount },
+            1 => Operation::Withdrawal { client, tx, amount },
+            2 => Operation::Dispute { client, tx },
+            3 => Operation::Resolve { client, tx },
+            _ => Operation::Chargeback { client, tx },
+        }
+    }
+
+    pub fn encode(&self, output: &mut Vec<u8>) {
+        let (kind, client, tx, amount) = match *self {
+            Operation::Deposit { client, tx, amount } => (0u8, client, tx, amount),
+            Operation::Withdrawal { client, tx, amount } => (1, client, tx, amount),
+            Operation::Dispute { client, tx } => (2, client, tx, 0),
+            Operation::Resolve { client, tx } => (3, client, tx, 0),
+            Operation::Chargeback { client, tx } => (4, client, tx, 0),
+        };
+        output.push(kind);
+        output.extend_from_slice(&client.to_le_bytes());
+        output.extend_from_slice(&tx.to_le_bytes());
+        output.extend_from_slice(&amount.to_le_bytes()[..12]);
+    }
+
+    /// Converts an operation read from csv. Returns `None` for negative amounts, operations
+    /// with a currency and administrative operations, which do not fit the encoding.
+    pub fn from_accounting_operation(operation: &AccountingOperation) -> Option<Operation> {
+        if operation.currency().is_some_and(|currency| !currency.is_unspecified()) {
+            return None;
+        }
+        let operation = match operation {
+            AccountingOperation::Transaction { transaction } => {
+                let client = transaction.client_id().as_u16();
+                let tx = transaction.id().as_u32();
+                let amount = u128::try_from(to_fixed_point(transaction.amount())).ok()?;
+                match transaction.kind() {
+                    TransactionKind::Deposit => Operation::Deposit { client, tx, amount },
+                    TransactionKind::Withdrawal => Operation::Withdrawal { client, tx, amount },
+                }
+            }
+            AccountingOperation::Dispute { client_id, ref_id, .. } => Operation::Dispute {
+                client: client_id.as_u16(),
+                tx: ref_id.as_u32(),
+            },
+            AccountingOperation::Resolve { client_id, ref_id, .. } => Operation::Resolve {
+                client: client_id.as_u16(),
+                tx: ref_id.as_u32(),
+            },
+            AccountingOperation::Chargeback { client_id, ref_id, .. } => Operation::Chargeback {
+                client: client_id.as_u16(),
+                tx: ref_id.as_u32(),
+            },
+            AccountingOperation::Unlock { .. }
+            | AccountingOperation::Freeze { .. }
+            | AccountingOperation::Close { .. } => return None,
+        };
+        Some(operation)
+    }
+
+    pub fn to_accounting_operation(self) -> AccountingOperation {
+        let transaction = |client: u16, tx: u32, amount: u128, kind| AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(client),
+                TransactionId::from(tx),
+                from_fixed_point(amount),
+                kind,
+            ),
+        };
+        match self {
+            Operation::Deposit { client, tx, amount } => transaction(client, tx, amount, TransactionKind::Deposit),
+            Operation::Withdrawal { client, tx, amount } => {
+                transaction(client, tx, amount, TransactionKind::Withdrawal)
+            }
+            Operation::Dispute { client, tx } => AccountingOperation::Dispute {
+                client_id: ClientId::from(client),
+                ref_id: TransactionId::from(tx),
+                currency: None,
+                timestamp: None,
+            },
+            Operation::Resolve { client, tx } => AccountingOperation::Resolve {
+                client_id: ClientId::from(client),
+                ref_id: TransactionId::from(tx),
+                currency: None,
+                timestamp: None,
+            },
+            Operation::Chargeback { client, tx } => AccountingOperation::Chargeback {
+                client_id: ClientId::from(client),
+                ref_id: TransactionId::from(tx),
+                currency: None,
+                timestamp: None,
+            },
+        }
+    }
+}
+
+fn from_fixed_point(amount: u128) -> Amount {
+    Amount::try_from(format!("{}.{:04}", amount / 10_000, amount % 10_000).as_str()).unwrap()
+}
+
+/// Amount as a fixed point integer with 4 decimal places.
+pub fn to_fixed_point(amount: &Amount) -> i128 {
+    amount.to_string().replace('.', "").parse().unwrap()
+}
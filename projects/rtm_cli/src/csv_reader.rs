@@ -1,25 +1,91 @@
 use std::{collections::HashMap, io::Read};
 
-use rtm_core::models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind};
+use rtm_core::models::{AccountingOperation, AmountPolicies, InvalidFieldReason, OperationRecord};
 
-const HEADERS: &[&str] = &["type", "client", "tx", "amount"];
+const HEADERS: [&str; 4] = ["type", "client", "tx", "amount"];
 
-#[derive(Debug)]
+/// Headers of the columns that may follow [`HEADERS`]: the currency and the timestamp in
+/// seconds since the Unix epoch, both empty when unknown, and the reason of administrative
+/// operations.
+const OPTIONAL_HEADERS: [&str; 3] = ["currency", "timestamp", "reason"];
+
+#[derive(Debug, PartialEq, Eq)]
 pub enum CsvReaderError {
     HeaderNotFound,
     InvalidHeaders,
+
+    /// Row is not a valid csv record.
+    UnreadableRow {
+        line: u64,
+        reason: String,
+    },
+
+    /// Row does not have exactly one field per header.
+    InvalidFieldCount {
+        line: u64,
+        expected: usize,
+        found: usize,
+    },
+
+    /// Field could not be parsed into the value expected for its column.
+    InvalidField {
+        line: u64,
+        column: &'static str,
+        value: String,
+        reason: InvalidFieldReason,
+    },
+}
+
+impl std::fmt::Display for CsvReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CsvReaderError::HeaderNotFound => write!(f, "header not found"),
+            CsvReaderError::InvalidHeaders => write!(
+                f,
+                "invalid headers, expected: {} and optionally {}",
+                HEADERS.join(", "),
+                OPTIONAL_HEADERS.join(", ")
+            ),
+            CsvReaderError::UnreadableRow { line, reason } => write!(f, "line {line}: unreadable row: {reason}"),
+            CsvReaderError::InvalidFieldCount { line, expected, found } => {
+                write!(f, "line {line}: expected {expected} fields, found {found}")
+            }
+            CsvReaderError::InvalidField {
+                line,
+                column,
+                value,
+                reason,
+            } => {
+                write!(f, "line {line}, column `{column}`: invalid value `{value}`")?;
+                if *reason != InvalidFieldReason::Malformed {
+                    write!(f, ", {reason}")?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 pub struct CsvReader<T: Read> {
     reader: csv::Reader<T>,
+    amount_policies: AmountPolicies,
 }
 
 impl<T: Read> CsvReader<T> {
-    pub fn new(stream: T) -> Self {
+    /// Reader parsing amounts with `amount_policies`.
+    pub fn with_amount_policies(stream: T, amount_policies: AmountPolicies) -> Self {
         let reader = csv::Reader::from_reader(stream);
-        Self { reader }
+        Self {
+            reader,
+            amount_policies,
+        }
     }
 
+    /// Reads the header and iterates over the following rows.
+    ///
+    /// # Errors
+    ///
+    /// When the header is missing or lacks any of the required columns.
     pub fn read_iter(&mut self) -> Result<CsvReaderIterator<'_, T>, CsvReaderError> {
         let Ok(headers) = self.reader.headers() else {
             return Err(CsvReaderError::HeaderNotFound);
@@ -29,103 +95,374 @@ impl<T: Read> CsvReader<T> {
         for (i, header) in headers.iter().enumerate() {
             header_map.insert(header.trim().to_lowercase(), i);
         }
-        if header_map.len() != HEADERS.len() {
+        let optional_columns = OPTIONAL_HEADERS.map(|header| header_map.get(header).copied());
+        let field_count = HEADERS.len() + optional_columns.iter().flatten().count();
+        if header_map.len() != field_count || headers.len() != field_count {
             return Err(CsvReaderError::InvalidHeaders);
         }
-        for header in HEADERS {
-            if !header_map.contains_key(*header) {
+        let mut columns = [0; HEADERS.len()];
+        for (column, header) in columns.iter_mut().zip(HEADERS) {
+            let Some(index) = header_map.get(header) else {
                 return Err(CsvReaderError::InvalidHeaders);
-            }
+            };
+            *column = *index;
         }
 
         let records = self.reader.records();
-        return Ok(CsvReaderIterator::new(records, header_map));
+        return Ok(CsvReaderIterator::new(
+            records,
+            columns,
+            optional_columns,
+            &self.amount_policies,
+        ));
     }
 }
 
+/// Operation read from the csv file together with the line it was found at.
+#[derive(Debug)]
+pub struct CsvRecord {
+    pub line: u64,
+    pub operation: AccountingOperation,
+}
+
 pub struct CsvReaderIterator<'a, T: Read> {
     records: csv::StringRecordsIter<'a, T>,
-    header_map: HashMap<String, usize>,
+
+    /// Index of the field holding each of [`HEADERS`], in the same order.
+    columns: [usize; HEADERS.len()],
+
+    /// Index of the field holding each of [`OPTIONAL_HEADERS`], if present.
+    optional_columns: [Option<usize>; OPTIONAL_HEADERS.len()],
+    amount_policies: &'a AmountPolicies,
 }
 
 impl<'a, T: Read> CsvReaderIterator<'a, T> {
-    pub fn new(records: csv::StringRecordsIter<'a, T>, header_map: HashMap<String, usize>) -> Self {
-        Self { records, header_map }
+    fn new(
+        records: csv::StringRecordsIter<'a, T>,
+        columns: [usize; HEADERS.len()],
+        optional_columns: [Option<usize>; OPTIONAL_HEADERS.len()],
+        amount_policies: &'a AmountPolicies,
+    ) -> Self {
+        Self {
+            records,
+            columns,
+            optional_columns,
+            amount_policies,
+        }
+    }
+
+    fn field_count(&self) -> usize {
+        HEADERS.len() + self.optional_columns.iter().flatten().count()
     }
 }
 
 impl<T: Read> Iterator for CsvReaderIterator<'_, T> {
-    type Item = AccountingOperation;
+    type Item = Result<CsvRecord, CsvReaderError>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        macro_rules! read_field {
-            ($record:expr, $field:expr) => {
-                ($record.get(*self.header_map.get($field).unwrap()).unwrap())
-            };
-        }
+        let record = match self.records.next()? {
+            Ok(record) => record,
+            Err(err) => {
+                let line = err.position().map_or(0, csv::Position::line);
+                if let csv::ErrorKind::UnequalLengths { len, .. } = err.kind() {
+                    return Some(Err(CsvReaderError::InvalidFieldCount {
+                        line,
+                        expected: self.field_count(),
+                        found: usize::try_from(*len).unwrap_or(usize::MAX),
+                    }));
+                }
+                return Some(Err(CsvReaderError::UnreadableRow {
+                    line,
+                    reason: err.to_string(),
+                }));
+            }
+        };
 
-        loop {
-            let next = self.records.next()?;
+        let line = record.position().map_or(0, csv::Position::line);
 
-            let Ok(record) = next else {
-                continue;
-            };
+        if record.len() != self.field_count() {
+            return Some(Err(CsvReaderError::InvalidFieldCount {
+                line,
+                expected: self.field_count(),
+                found: record.len(),
+            }));
+        }
 
-            if record.len() != HEADERS.len() {
-                continue;
+        let [record_type, client, tx, amount] = self.columns.map(|column| record.get(column).unwrap_or_default());
+        let [currency, timestamp, reason] = self
+            .optional_columns
+            .map(|column| column.and_then(|column| record.get(column)).unwrap_or_default());
+        let fields = OperationRecord {
+            record_type,
+            client,
+            tx,
+            amount,
+            currency,
+            timestamp,
+            reason,
+        };
+        let operation = AccountingOperation::parse_record(&fields, self.amount_policies);
+        let operation = match operation {
+            Ok(operation) => operation,
+            Err(err) => {
+                return Some(Err(CsvReaderError::InvalidField {
+                    line,
+                    column: err.column,
+                    value: err.value,
+                    reason: err.reason,
+                }));
             }
+        };
 
-            let Ok(client) = ClientId::try_from(read_field!(&record, "client").trim()) else {
-                continue;
-            };
+        return Some(Ok(CsvRecord { line, operation }));
+    }
+}
 
-            let Ok(tx) = TransactionId::try_from(read_field!(&record, "tx").trim()) else {
-                continue;
-            };
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rtm_core::models::{AmountPolicy, Currency, Rounding, Timestamp, TransactionKind};
 
-            let amount_field = read_field!(&record, "amount").trim();
+    use super::*;
 
-            let record_type = read_field!(&record, "type").trim();
+    fn read(content: &str) -> Vec<Result<CsvRecord, CsvReaderError>> {
+        let mut reader = CsvReader::with_amount_policies(content.as_bytes(), AmountPolicies::default());
+        reader.read_iter().unwrap().collect()
+    }
 
-            match record_type {
-                "deposit" => {
-                    let Ok(amount) = Amount::try_from(amount_field) else {
-                        continue;
-                    };
-                    return Some(AccountingOperation::Transaction {
-                        transaction: Transaction::new(client, tx, amount, TransactionKind::Deposit),
-                    });
-                }
-                "withdrawal" => {
-                    let Ok(amount) = Amount::try_from(amount_field) else {
-                        continue;
-                    };
-                    return Some(AccountingOperation::Transaction {
-                        transaction: Transaction::new(client, tx, amount, TransactionKind::Withdrawal),
-                    });
-                }
-                "dispute" => {
-                    return Some(AccountingOperation::Dispute {
-                        client_id: client,
-                        ref_id: tx,
-                    });
-                }
-                "resolve" => {
-                    return Some(AccountingOperation::Resolve {
-                        client_id: client,
-                        ref_id: tx,
-                    });
-                }
-                "chargeback" => {
-                    return Some(AccountingOperation::Chargeback {
-                        client_id: client,
-                        ref_id: tx,
-                    });
-                }
-                _ => {
-                    return None;
-                }
-            }
-        }
+    #[test]
+    fn test_valid_rows() {
+        let records = read("type, client, tx, amount\ndeposit, 1, 1, 1.0\ndispute, 1, 1,\n");
+        assert_eq!(records.len(), 2);
+        let first = records[0].as_ref().unwrap();
+        assert_eq!(first.line, 2);
+        assert!(matches!(first.operation, AccountingOperation::Transaction { .. }));
+        let second = records[1].as_ref().unwrap();
+        assert_eq!(second.line, 3);
+        assert!(matches!(second.operation, AccountingOperation::Dispute { .. }));
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let records = read(
+            "type, client, tx, amount\n\
+             deposit, 1, 1\n\
+             deposit, x, 2, 1.0\n\
+             deposit, 1, -3, 1.0\n\
+             withdrawal, 1, 4, abc\n\
+             deposit, 1, 5, 1.0\n",
+        );
+        let errors = records
+            .into_iter()
+            .map(|record| record.map(|record| record.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            vec![
+                Err(CsvReaderError::InvalidFieldCount {
+                    line: 2,
+                    expected: 4,
+                    found: 3,
+                }),
+                Err(CsvReaderError::InvalidField {
+                    line: 3,
+                    column: "client",
+                    value: "x".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Err(CsvReaderError::InvalidField {
+                    line: 4,
+                    column: "tx",
+                    value: "-3".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Err(CsvReaderError::InvalidField {
+                    line: 5,
+                    column: "amount",
+                    value: "abc".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Ok(6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_type_does_not_stop_reading() {
+        let records = read(
+            "type, client, tx, amount\n\
+             deposit, 1, 1, 1.0\n\
+             adjustment, 1, 5, 1.0\n\
+             deposit, 1, 2, 2.0\n\
+             refund, 1, 6, 1.0\n\
+             withdrawal, 1, 3, 1.0\n",
+        );
+        let lines = records
+            .into_iter()
+            .map(|record| record.map(|record| record.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                Ok(2),
+                Err(CsvReaderError::InvalidField {
+                    line: 3,
+                    column: "type",
+                    value: "adjustment".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Ok(4),
+                Err(CsvReaderError::InvalidField {
+                    line: 5,
+                    column: "type",
+                    value: "refund".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Ok(6),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_amounts() {
+        let records = read(
+            "type, client, tx, amount\n\
+             deposit, 1, 1, -1.0\n\
+             withdrawal, 1, 2, 0\n\
+             deposit, 1, 3, 0.00001\n",
+        );
+        let errors = records
+            .into_iter()
+            .map(|record| record.unwrap_err().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            errors,
+            [
+                "line 2, column `amount`: invalid value `-1.0`, amount is not positive",
+                "line 3, column `amount`: invalid value `0`, amount is not positive",
+                "line 4, column `amount`: invalid value `0.00001`, amount is not positive",
+            ]
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("type, client, tx\n")]
+    #[case("type, client, tx, amount, fee\n")]
+    #[case("type, type, client, tx, amount\n")]
+    #[case("type, client, client, amount\n")]
+    #[case("type, client, tx, amount, currency, currency\n")]
+    #[case("type, client, tx, currency\n")]
+    #[case("type, client, tx, amount, timestamp, timestamp\n")]
+    fn test_invalid_headers(#[case] content: &str) {
+        let mut reader = CsvReader::with_amount_policies(content.as_bytes(), AmountPolicies::default());
+        assert_eq!(reader.read_iter().err(), Some(CsvReaderError::InvalidHeaders));
+    }
+
+    #[rstest]
+    #[case("Deposit")]
+    #[case("DEPOSIT")]
+    #[case("dEpOsIt")]
+    fn test_type_is_case_insensitive(#[case] record_type: &str) {
+        let records = read(&format!("type, client, tx, amount\n{record_type}, 1, 1, 1.0\n"));
+        assert_eq!(records.len(), 1);
+        let AccountingOperation::Transaction { transaction } = &records[0].as_ref().unwrap().operation else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(transaction.kind(), TransactionKind::Deposit);
+    }
+
+    #[test]
+    fn test_currency_column() {
+        let records = read(
+            "currency, type, client, tx, amount\n\
+             eur, deposit, 1, 1, 1.0\n\
+             , deposit, 1, 2, 1.0\n\
+             USD, dispute, 1, 1,\n\
+             EURO, deposit, 1, 3, 1.0\n\
+             deposit, 1, 4, 1.0\n",
+        );
+        let AccountingOperation::Transaction { transaction } = &records[0].as_ref().unwrap().operation else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(transaction.currency(), Currency::try_from("EUR").unwrap());
+        let AccountingOperation::Transaction { transaction } = &records[1].as_ref().unwrap().operation else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(transaction.currency(), Currency::UNSPECIFIED);
+        assert_eq!(
+            records[2].as_ref().unwrap().operation.currency(),
+            Some(Currency::try_from("USD").unwrap())
+        );
+        assert_eq!(
+            records[3].as_ref().unwrap_err().to_string(),
+            "line 5, column `currency`: invalid value `EURO`"
+        );
+        assert_eq!(
+            records[4].as_ref().unwrap_err().to_string(),
+            "line 6: expected 5 fields, found 4"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_column() {
+        let records = read(
+            "type, client, tx, amount, timestamp\n\
+             deposit, 1, 1, 1.0, 1700000000\n\
+             dispute, 1, 1, , 1700000060\n\
+             deposit, 1, 2, 1.0,\n\
+             deposit, 1, 3, 1.0, yesterday\n",
+        );
+        let AccountingOperation::Transaction { transaction } = &records[0].as_ref().unwrap().operation else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(transaction.timestamp(), Some(Timestamp::from(1_700_000_000)));
+        assert_eq!(
+            records[1].as_ref().unwrap().operation.timestamp(),
+            Some(Timestamp::from(1_700_000_060))
+        );
+        assert_eq!(records[2].as_ref().unwrap().operation.timestamp(), None);
+        assert_eq!(
+            records[3].as_ref().unwrap_err().to_string(),
+            "line 5, column `timestamp`: invalid value `yesterday`"
+        );
+    }
+
+    #[test]
+    fn test_reason_column() {
+        let records = read(
+            "type, client, tx, amount, reason\n\
+             freeze, 1, , ,\"suspected fraud, see ticket\"\n\
+             unlock, 1, , , reviewed\n\
+             close, 1, , ,\n\
+             deposit, 1, 1, 1.0, ignored\n",
+        );
+        assert!(matches!(
+            &records[0].as_ref().unwrap().operation,
+            AccountingOperation::Freeze { reason, .. } if reason == "suspected fraud, see ticket"
+        ));
+        assert_eq!(records[1].as_ref().unwrap().operation.reason(), Some("reviewed"));
+        assert_eq!(
+            records[2].as_ref().unwrap_err().to_string(),
+            "line 4, column `reason`: invalid value ``"
+        );
+        assert_eq!(records[3].as_ref().unwrap().operation.reason(), None);
+    }
+
+    #[test]
+    fn test_currency_precision() {
+        let mut amount_policies = AmountPolicies::default();
+        amount_policies.currencies.insert(
+            Currency::try_from("JPY").unwrap(),
+            AmountPolicy::new(0, Rounding::Reject),
+        );
+        let content = "type, client, tx, amount, currency\ndeposit, 1, 1, 1.5, EUR\ndeposit, 1, 2, 1.5, JPY\n";
+        let mut reader = CsvReader::with_amount_policies(content.as_bytes(), amount_policies);
+        let records = reader.read_iter().unwrap().collect::<Vec<_>>();
+        assert!(records[0].is_ok());
+        assert_eq!(
+            records[1].as_ref().unwrap_err().to_string(),
+            "line 3, column `amount`: invalid value `1.5`, amount has too many decimal places"
+        );
     }
 }
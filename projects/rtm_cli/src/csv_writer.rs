@@ -1,18 +1,29 @@
 use std::io::Write;
 
-use rtm_core::processor::{ClientAccount, ClientAccountState};
+use rtm_core::processor::ClientAccount;
+
+const HEADERS: [&str; 5] = ["client", "available", "held", "total", "locked"];
+
+/// Headers of the columns written after [`HEADERS`] by an extended [`CsvWriter`]: the currency,
+/// empty for accounts without one, whether the account is overdrawn, and its state, which
+/// distinguishes frozen and closed accounts from ones locked by a chargeback.
+const EXTENDED_HEADERS: [&str; 3] = ["currency", "overdrawn", "state"];
 
 pub struct CsvWriter<T: Write> {
     writer: csv::Writer<T>,
+    extended: bool,
 }
 
 impl<T: Write> CsvWriter<T> {
-    pub fn new(stream: T) -> Self {
+    /// Writer of accounts, with the [`EXTENDED_HEADERS`] columns if `extended`.
+    pub fn new(stream: T, extended: bool) -> Self {
         let mut writer = csv::Writer::from_writer(stream);
-        writer
-            .write_record(["client", "available", "held", "total", "locked"])
-            .unwrap();
-        Self { writer }
+        let mut headers = HEADERS.to_vec();
+        if extended {
+            headers.extend(EXTENDED_HEADERS);
+        }
+        writer.write_record(headers).unwrap();
+        Self { writer, extended }
     }
 
     pub fn write_client_account(&mut self, record: &ClientAccount) {
@@ -20,12 +31,67 @@ impl<T: Write> CsvWriter<T> {
         let available = record.available_balance.to_string();
         let held = record.held_balance.to_string();
         let total = (record.available_balance.clone() + record.held_balance.clone()).to_string();
-        let locked = match record.state {
-            ClientAccountState::Normal => "false",
-            ClientAccountState::Locked => "true",
+        let locked = if record.state.is_locked() { "true" } else { "false" };
+        let currency = if record.currency.is_unspecified() {
+            String::new()
+        } else {
+            record.currency.to_string()
         };
-        self.writer
-            .write_record([&client_id, &available, &held, &total, locked])
-            .unwrap();
+        let state = record.state.to_string();
+        let mut fields = vec![client_id.as_str(), &available, &held, &total, locked];
+        if self.extended {
+            let overdrawn = if record.is_overdrawn() { "true" } else { "false" };
+            fields.extend([currency.as_str(), overdrawn, &state]);
+        }
+        self.writer.write_record(fields).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rtm_core::{
+        models::{AccountingOperation, Amount, ClientId, Currency, Transaction, TransactionId, TransactionKind},
+        processor::AccountingSystem,
+    };
+
+    use super::*;
+
+    #[rstest]
+    #[case(
+        false,
+        "client,available,held,total,locked\n1,1.5000,0.0000,1.5000,false\n2,1.5000,0.0000,1.5000,false\n"
+    )]
+    #[case(
+        true,
+        concat!(
+            "client,available,held,total,locked,currency,overdrawn,state\n",
+            "1,1.5000,0.0000,1.5000,false,EUR,false,normal\n",
+            "2,1.5000,0.0000,1.5000,false,,false,normal\n"
+        )
+    )]
+    fn test_write_client_accounts(#[case] extended: bool, #[case] expected: &str) {
+        let mut accounting_system = AccountingSystem::new();
+        for (client, tx, currency) in [(2, 1, "XXX"), (1, 2, "EUR")] {
+            let transaction = Transaction::with_currency(
+                ClientId::from(client),
+                TransactionId::from(tx),
+                Amount::try_from("1.5").unwrap(),
+                Currency::try_from(currency).unwrap(),
+                TransactionKind::Deposit,
+            );
+            accounting_system
+                .run_operation(AccountingOperation::Transaction { transaction })
+                .unwrap();
+        }
+
+        let mut output = Vec::new();
+        {
+            let mut writer = CsvWriter::new(&mut output, extended);
+            for account in accounting_system.iter_accounts() {
+                writer.write_client_account(account);
+            }
+        }
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
     }
 }
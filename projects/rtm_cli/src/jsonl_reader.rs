@@ -0,0 +1,291 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, Lines, Read},
+};
+
+use rtm_core::models::{AccountingOperation, AmountPolicies, InvalidFieldReason, OperationRecord};
+use serde_json::value::RawValue;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonlReaderError {
+    /// Line is not a json object.
+    UnreadableRow { line: u64, reason: String },
+
+    /// Field could not be parsed into the value expected for its column.
+    InvalidField {
+        line: u64,
+        column: &'static str,
+        value: String,
+        reason: InvalidFieldReason,
+    },
+}
+
+impl std::fmt::Display for JsonlReaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonlReaderError::UnreadableRow { line, reason } => write!(f, "line {line}: unreadable row: {reason}"),
+            JsonlReaderError::InvalidField {
+                line,
+                column,
+                value,
+                reason,
+            } => {
+                write!(f, "line {line}, column `{column}`: invalid value `{value}`")?;
+                if *reason != InvalidFieldReason::Malformed {
+                    write!(f, ", {reason}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Reads operations from newline delimited json objects, e.g.
+/// `{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}`, optionally with `"currency"`,
+/// `"timestamp"` and `"reason"` fields.
+///
+/// Fields follow the same rules as csv columns: numbers and strings are both accepted,
+/// while a missing or `null` field is treated as an empty one. Blank lines are skipped.
+pub struct JsonlReader<T: Read> {
+    lines: Lines<BufReader<T>>,
+    line: u64,
+    failed: bool,
+    amount_policies: AmountPolicies,
+}
+
+impl<T: Read> JsonlReader<T> {
+    /// Reader parsing amounts with `amount_policies`.
+    pub fn with_amount_policies(stream: T, amount_policies: AmountPolicies) -> Self {
+        Self {
+            lines: BufReader::new(stream).lines(),
+            line: 0,
+            failed: false,
+            amount_policies,
+        }
+    }
+}
+
+/// Operation read from the jsonl file together with the line it was found at.
+#[derive(Debug)]
+pub struct JsonlRecord {
+    pub line: u64,
+    pub operation: AccountingOperation,
+}
+
+impl<T: Read> Iterator for JsonlReader<T> {
+    type Item = Result<JsonlRecord, JsonlReaderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let content = loop {
+            self.line += 1;
+            match self.lines.next()? {
+                Ok(content) if content.trim().is_empty() => {}
+                Ok(content) => break content,
+                Err(err) => {
+                    // Invalid utf-8 only affects a single line, any other io error is fatal.
+                    self.failed = err.kind() != std::io::ErrorKind::InvalidData;
+                    return Some(Err(JsonlReaderError::UnreadableRow {
+                        line: self.line,
+                        reason: err.to_string(),
+                    }));
+                }
+            }
+        };
+        let line = self.line;
+
+        let fields = match serde_json::from_str::<HashMap<String, &RawValue>>(&content) {
+            Ok(fields) => fields,
+            Err(err) => {
+                return Some(Err(JsonlReaderError::UnreadableRow {
+                    line,
+                    reason: err.to_string(),
+                }));
+            }
+        };
+        let read_field = |name: &str| match fields.get(name) {
+            None => String::new(),
+            Some(value) => match value.get() {
+                "null" => String::new(),
+                raw if raw.starts_with('"') => serde_json::from_str(raw).unwrap_or_default(),
+                raw => raw.to_string(),
+            },
+        };
+
+        let [record_type, client, tx, amount, currency, timestamp, reason] =
+            ["type", "client", "tx", "amount", "currency", "timestamp", "reason"].map(read_field);
+        let record = OperationRecord {
+            record_type: &record_type,
+            client: &client,
+            tx: &tx,
+            amount: &amount,
+            currency: &currency,
+            timestamp: &timestamp,
+            reason: &reason,
+        };
+        let operation = AccountingOperation::parse_record(&record, &self.amount_policies);
+        match operation {
+            Ok(operation) => Some(Ok(JsonlRecord { line, operation })),
+            Err(err) => Some(Err(JsonlReaderError::InvalidField {
+                line,
+                column: err.column,
+                value: err.value,
+                reason: err.reason,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+    use rtm_core::models::{Amount, Currency, Timestamp, TransactionKind};
+
+    use super::*;
+
+    fn read(content: &str) -> Vec<Result<JsonlRecord, JsonlReaderError>> {
+        JsonlReader::with_amount_policies(content.as_bytes(), AmountPolicies::default()).collect()
+    }
+
+    #[test]
+    fn test_valid_rows() {
+        let records = read(
+            "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1, \"amount\": \"1.0\"}\n\
+             \n\
+             {\"type\": \"dispute\", \"client\": 1, \"tx\": 1, \"amount\": null}\n\
+             {\"type\": \"resolve\", \"client\": \"1\", \"tx\": \"1\"}\n",
+        );
+        let lines = records
+            .iter()
+            .map(|record| record.as_ref().unwrap().line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, vec![1, 3, 4]);
+        assert!(matches!(
+            records[1].as_ref().unwrap().operation,
+            AccountingOperation::Dispute { .. }
+        ));
+        assert!(matches!(
+            records[2].as_ref().unwrap().operation,
+            AccountingOperation::Resolve { .. }
+        ));
+    }
+
+    #[rstest]
+    #[case("1.2345")]
+    #[case("\"1.2345\"")]
+    #[case("\" 1.2345 \"")]
+    fn test_amount_as_number_or_string(#[case] amount: &str) {
+        let records = read(&format!(
+            "{{\"type\": \"Deposit\", \"client\": 1, \"tx\": 1, \"amount\": {amount}}}"
+        ));
+        let AccountingOperation::Transaction { transaction } = &records[0].as_ref().unwrap().operation else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(transaction.kind(), TransactionKind::Deposit);
+        assert_eq!(transaction.amount(), &Amount::try_from("1.2345").unwrap());
+    }
+
+    #[test]
+    fn test_malformed_rows() {
+        let records = read(
+            "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1\n\
+             [1, 2, 3]\n\
+             {\"type\": \"deposit\", \"client\": \"x\", \"tx\": 2, \"amount\": 1.0}\n\
+             {\"type\": \"deposit\", \"client\": 1, \"tx\": -3, \"amount\": 1.0}\n\
+             {\"type\": \"withdrawal\", \"client\": 1, \"tx\": 4}\n\
+             {\"type\": \"adjustment\", \"client\": 1, \"tx\": 5, \"amount\": 1.0}\n\
+             {\"type\": \"deposit\", \"client\": 1, \"tx\": 6, \"amount\": 1.0}\n",
+        );
+        let lines = records
+            .into_iter()
+            .map(|record| record.map(|record| record.line))
+            .collect::<Vec<_>>();
+        assert!(matches!(lines[0], Err(JsonlReaderError::UnreadableRow { line: 1, .. })));
+        assert!(matches!(lines[1], Err(JsonlReaderError::UnreadableRow { line: 2, .. })));
+        assert_eq!(
+            lines[2..],
+            [
+                Err(JsonlReaderError::InvalidField {
+                    line: 3,
+                    column: "client",
+                    value: "x".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Err(JsonlReaderError::InvalidField {
+                    line: 4,
+                    column: "tx",
+                    value: "-3".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Err(JsonlReaderError::InvalidField {
+                    line: 5,
+                    column: "amount",
+                    value: String::new(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Err(JsonlReaderError::InvalidField {
+                    line: 6,
+                    column: "type",
+                    value: "adjustment".to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                }),
+                Ok(7),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_currency_field() {
+        let records = read(
+            "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1, \"amount\": 1.0, \"currency\": \"eur\"}\n\
+             {\"type\": \"dispute\", \"client\": 1, \"tx\": 1, \"currency\": null}\n\
+             {\"type\": \"dispute\", \"client\": 1, \"tx\": 1, \"currency\": 1}\n",
+        );
+        assert_eq!(
+            records[0].as_ref().unwrap().operation.currency(),
+            Some(Currency::try_from("EUR").unwrap())
+        );
+        assert_eq!(records[1].as_ref().unwrap().operation.currency(), None);
+        assert_eq!(
+            records[2].as_ref().unwrap_err().to_string(),
+            "line 3, column `currency`: invalid value `1`"
+        );
+    }
+
+    #[test]
+    fn test_reason_field() {
+        let records = read(
+            "{\"type\": \"freeze\", \"client\": 1, \"reason\": \"suspected fraud\"}\n\
+             {\"type\": \"close\", \"client\": 1, \"tx\": null}\n",
+        );
+        assert_eq!(records[0].as_ref().unwrap().operation.reason(), Some("suspected fraud"));
+        assert_eq!(
+            records[1].as_ref().unwrap_err().to_string(),
+            "line 2, column `reason`: invalid value ``"
+        );
+    }
+
+    #[test]
+    fn test_timestamp_field() {
+        let records = read(
+            "{\"type\": \"deposit\", \"client\": 1, \"tx\": 1, \"amount\": 1.0, \"timestamp\": 1700000000}\n\
+             {\"type\": \"dispute\", \"client\": 1, \"tx\": 1, \"timestamp\": \"1700000060\"}\n\
+             {\"type\": \"dispute\", \"client\": 1, \"tx\": 1, \"timestamp\": -1}\n",
+        );
+        assert_eq!(
+            records[0].as_ref().unwrap().operation.timestamp(),
+            Some(Timestamp::from(1_700_000_000))
+        );
+        assert_eq!(
+            records[1].as_ref().unwrap().operation.timestamp(),
+            Some(Timestamp::from(1_700_000_060))
+        );
+        assert_eq!(
+            records[2].as_ref().unwrap_err().to_string(),
+            "line 3, column `timestamp`: invalid value `-1`"
+        );
+    }
+}
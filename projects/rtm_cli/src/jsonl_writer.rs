@@ -0,0 +1,97 @@
+use std::io::Write;
+
+use rtm_core::processor::ClientAccount;
+
+/// Writes client accounts as newline delimited json objects, in the representation of
+/// [`ClientAccountSnapshot`](rtm_core::processor::ClientAccountSnapshot), with amounts
+/// encoded as strings to keep their exact precision.
+pub struct JsonlWriter<T: Write> {
+    stream: T,
+}
+
+impl<T: Write> JsonlWriter<T> {
+    pub fn new(stream: T) -> Self {
+        Self { stream }
+    }
+
+    pub fn write_client_account(&mut self, record: &ClientAccount) {
+        serde_json::to_writer(&mut self.stream, &record.snapshot()).unwrap();
+        writeln!(self.stream).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rtm_core::{
+        models::{AccountingOperation, Amount, ClientId, Currency, Transaction, TransactionId, TransactionKind},
+        processor::AccountingSystem,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_write_client_accounts() {
+        let mut accounting_system = AccountingSystem::new();
+        for (client, tx, currency) in [(2, 1, "XXX"), (1, 2, "XXX"), (1, 3, "EUR")] {
+            let transaction = Transaction::with_currency(
+                ClientId::from(client),
+                TransactionId::from(tx),
+                Amount::try_from("1.5").unwrap(),
+                Currency::try_from(currency).unwrap(),
+                TransactionKind::Deposit,
+            );
+            accounting_system
+                .run_operation(AccountingOperation::Transaction { transaction })
+                .unwrap();
+        }
+
+        let mut output = Vec::new();
+        let mut writer = JsonlWriter::new(&mut output);
+        for account in accounting_system.iter_accounts() {
+            writer.write_client_account(account);
+        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false,"currency":"EUR"}"#,
+                "\n",
+                r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}"#,
+                "\n",
+                r#"{"client":2,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_write_overdrawn_client_account() {
+        let mut accounting_system = AccountingSystem::new();
+        for (tx, kind) in [(1, TransactionKind::Deposit), (2, TransactionKind::Withdrawal)] {
+            let transaction = Transaction::new(ClientId::from(1), TransactionId::from(tx), Amount::from(1), kind);
+            accounting_system
+                .run_operation(AccountingOperation::Transaction { transaction })
+                .unwrap();
+        }
+        accounting_system
+            .run_operation(AccountingOperation::Dispute {
+                client_id: ClientId::from(1),
+                ref_id: TransactionId::from(1),
+                currency: None,
+                timestamp: None,
+            })
+            .unwrap();
+
+        let mut output = Vec::new();
+        let mut writer = JsonlWriter::new(&mut output);
+        for account in accounting_system.iter_accounts() {
+            writer.write_client_account(account);
+        }
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            concat!(
+                r#"{"client":1,"available":"-1.0000","held":"1.0000","total":"0.0000","locked":false,"overdrawn":true}"#,
+                "\n"
+            )
+        );
+    }
+}
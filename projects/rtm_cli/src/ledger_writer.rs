@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use rtm_core::processor::{AccountingEvent, EventSink, Ledger};
+
+const HEADERS: [&str; 5] = ["entry", "tx", "debit", "credit", "amount"];
+
+/// Posts events to a [`Ledger`], writing every posted entry as a csv row.
+pub struct LedgerWriter<T: Write> {
+    ledger: Ledger,
+    writer: csv::Writer<T>,
+}
+
+impl<T: Write> LedgerWriter<T> {
+    pub fn new(ledger: Ledger, stream: T) -> Self {
+        let mut writer = csv::Writer::from_writer(stream);
+        writer.write_record(HEADERS).unwrap();
+        Self { ledger, writer }
+    }
+
+    pub fn finish(mut self) -> Ledger {
+        self.writer.flush().unwrap();
+        self.ledger
+    }
+}
+
+impl<T: Write> EventSink for LedgerWriter<T> {
+    fn publish(&mut self, event: AccountingEvent) {
+        let Some(entry) = self.ledger.post(&event) else {
+            return;
+        };
+        self.writer
+            .write_record([
+                entry.id.to_string(),
+                entry.transaction_id.as_u32().to_string(),
+                entry.debit.to_string(),
+                entry.credit.to_string(),
+                entry.amount.to_string(),
+            ])
+            .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rtm_core::{
+        models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind},
+        processor::AccountingSystem,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_ledger_entries() {
+        let mut accounting_system = AccountingSystem::new();
+        let mut output = Vec::new();
+        let mut writer = LedgerWriter::new(Ledger::new(), &mut output);
+        for operation in [
+            AccountingOperation::Transaction {
+                transaction: Transaction::new(
+                    ClientId::from(1),
+                    TransactionId::from(1),
+                    Amount::try_from("2.5").unwrap(),
+                    TransactionKind::Deposit,
+                ),
+            },
+            AccountingOperation::Dispute {
+                client_id: ClientId::from(1),
+                ref_id: TransactionId::from(1),
+                currency: None,
+                timestamp: None,
+            },
+            AccountingOperation::Chargeback {
+                client_id: ClientId::from(1),
+                ref_id: TransactionId::from(1),
+                currency: None,
+                timestamp: None,
+            },
+        ] {
+            accounting_system
+                .run_operation_with_sink(operation, &mut writer)
+                .unwrap();
+        }
+        let ledger = writer.finish();
+
+        assert_eq!(ledger.entries(), 3);
+        assert_eq!(ledger.verify(&accounting_system), Ok(()));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "entry,tx,debit,credit,amount\n\
+             1,1,external:bank,client:1:available,2.5000\n\
+             2,1,client:1:available,client:1:held,2.5000\n\
+             3,1,client:1:held,external:bank,2.5000\n"
+        );
+    }
+}
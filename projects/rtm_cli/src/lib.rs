@@ -0,0 +1,14 @@
+//! Input readers of the rtm command line tool, also used by the fuzz targets.
+#![deny(warnings)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::unreadable_literal,
+    clippy::inline_always,
+    clippy::module_name_repetitions,
+    clippy::len_without_is_empty,
+    clippy::should_implement_trait
+)]
+
+pub mod csv_reader;
@@ -10,48 +10,486 @@
     clippy::should_implement_trait
 )]
 
-mod csv_reader;
 mod csv_writer;
+mod jsonl_reader;
+mod jsonl_writer;
+mod ledger_writer;
+mod rejection_writer;
 
-use std::path::PathBuf;
+use std::{
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
 
-use clap::Parser;
-use rtm_core::processor::AccountingSystem;
+use clap::{Parser, ValueEnum};
+use ledger_writer::LedgerWriter;
+use rejection_writer::{Rejection, RejectionWriter};
+use rtm_cli::csv_reader;
+use rtm_core::{
+    models::{self, AccountingOperation, AmountPolicies, AmountPolicy, Currency},
+    processor::{
+        AccountingSystem, AccountingSystemConfig, DisputePolicy, EventSink, Ledger, OverdraftPolicy,
+        ShardedAccountingSystem, SnapshotError,
+    },
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Disputes {
+    /// Deposits and withdrawals can be disputed.
+    All,
+    /// Only deposits can be disputed, withdrawals are not kept in memory.
+    DepositsOnly,
+    /// Disputes are rejected, no transactions are kept in memory.
+    None,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Overdraft {
+    /// The whole deposit is held, and the account is reported as overdrawn.
+    Allow,
+    /// The dispute is rejected.
+    Reject,
+    /// Only the available funds are held.
+    CapHold,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Rounding {
+    /// Amounts with more decimal places than the precision are rejected.
+    Reject,
+    /// Rounded half to even, e.g. `0.125` to `0.12` with precision 2.
+    HalfEven,
+    /// Rounded half away from zero, e.g. `0.125` to `0.13` with precision 2.
+    HalfUp,
+    /// Excess decimal places are dropped, e.g. `0.129` to `0.12` with precision 2.
+    Truncate,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Csv,
+    /// Newline delimited json objects.
+    Jsonl,
+}
+
+impl Format {
+    fn from_path(path: &Path) -> Self {
+        let is_jsonl = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("jsonl") || ext.eq_ignore_ascii_case("ndjson"));
+        if is_jsonl { Format::Jsonl } else { Format::Csv }
+    }
+}
+
+type InputRecord = Result<(u64, AccountingOperation), String>;
+
+enum Engine {
+    Sequential(AccountingSystem),
+    Sharded(ShardedAccountingSystem<(u64, AccountingOperation)>),
+}
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     filename: PathBuf,
+
+    /// Format of the input file. Jsonl for `.jsonl` and `.ndjson` files, csv otherwise.
+    #[arg(long, value_enum)]
+    input_format: Option<Format>,
+
+    /// Format of the accounts written to stdout. Same as the input format by default.
+    #[arg(long, value_enum)]
+    output_format: Option<Format>,
+
+    /// Add `currency`, `overdrawn` and `state` columns to the csv output.
+    #[arg(long)]
+    extended_output: bool,
+
+    /// Keep processing after an operation is rejected, instead of stopping at the first one.
+    #[arg(long)]
+    continue_on_error: bool,
+
+    /// Fail on malformed rows, reporting each of them, instead of silently skipping them.
+    #[arg(long)]
+    strict: bool,
+
+    /// Which transactions can be disputed, only these are kept in memory.
+    #[arg(long, value_enum, default_value_t = Disputes::All)]
+    dispute_policy: Disputes,
+
+    /// How disputes of deposits exceeding the available funds are handled.
+    #[arg(long, value_enum, default_value_t = Overdraft::Allow)]
+    overdraft_policy: Overdraft,
+
+    /// Maximal number of most recent transactions per client that can be disputed.
+    #[arg(long)]
+    dispute_window: Option<usize>,
+
+    /// Number of days after a timestamped transaction during which it can be disputed.
+    #[arg(long, value_name = "DAYS")]
+    dispute_time_limit: Option<u32>,
+
+    /// Number of days after a timestamped dispute during which it can be charged back.
+    #[arg(long, value_name = "DAYS")]
+    chargeback_time_limit: Option<u32>,
+
+    /// Number of decimal places of amounts and balances.
+    #[arg(long, default_value_t = models::DEFAULT_DECIMAL_PLACES, value_parser = clap::value_parser!(u32).range(0..=i64::from(models::MAX_DECIMAL_PLACES)))]
+    precision: u32,
+
+    /// Number of decimal places of a single currency, e.g. `JPY=0`, instead of the precision.
+    /// Can be given multiple times.
+    #[arg(long, value_name = "CURRENCY=PRECISION", value_parser = parse_currency_precision)]
+    currency_precision: Vec<(Currency, u32)>,
+
+    /// How amounts with more decimal places than the precision are treated.
+    #[arg(long, value_enum, default_value_t = Rounding::HalfEven)]
+    rounding: Rounding,
+
+    /// Number of threads processing operations, with clients partitioned between them.
+    #[arg(long, default_value_t = NonZeroUsize::MIN)]
+    threads: NonZeroUsize,
+
+    /// Snapshot to start from, instead of an empty state.
+    #[arg(long)]
+    load_state: Option<PathBuf>,
+
+    /// File to write the final state snapshot to, so that it can be loaded by a later run.
+    #[arg(long)]
+    save_state: Option<PathBuf>,
+
+    /// File to export double-entry ledger postings of all applied operations to, as csv.
+    /// Balances derived from the export are verified against the final balances.
+    #[arg(long)]
+    ledger: Option<PathBuf>,
+
+    /// File to write rejected operations to. Written as json if the extension is `.json`, as csv otherwise.
+    #[arg(long, requires = "continue_on_error")]
+    rejections: Option<PathBuf>,
+}
+
+impl Cli {
+    fn amount_policy(&self) -> AmountPolicy {
+        let rounding = match self.rounding {
+            Rounding::Reject => models::Rounding::Reject,
+            Rounding::HalfEven => models::Rounding::HalfEven,
+            Rounding::HalfUp => models::Rounding::HalfUp,
+            Rounding::Truncate => models::Rounding::Truncate,
+        };
+        AmountPolicy::new(self.precision, rounding)
+    }
+
+    fn amount_policies(&self) -> AmountPolicies {
+        let mut amount_policies = AmountPolicies::from(self.amount_policy());
+        for &(currency, decimal_places) in &self.currency_precision {
+            let amount_policy = AmountPolicy::new(decimal_places, amount_policies.default.rounding);
+            amount_policies.currencies.insert(currency, amount_policy);
+        }
+        amount_policies
+    }
 }
 
-fn main() {
+fn parse_currency_precision(value: &str) -> Result<(Currency, u32), String> {
+    let Some((currency, decimal_places)) = value.split_once('=') else {
+        return Err("expected CURRENCY=PRECISION".to_string());
+    };
+    let currency = Currency::try_from(currency.trim()).map_err(|err| err.to_string())?;
+    let decimal_places = decimal_places
+        .trim()
+        .parse::<u32>()
+        .ok()
+        .filter(|decimal_places| *decimal_places <= models::MAX_DECIMAL_PLACES)
+        .ok_or_else(|| format!("precision must be between 0 and {}", models::MAX_DECIMAL_PLACES))?;
+    Ok((currency, decimal_places))
+}
+
+fn main() -> ExitCode {
     let cli = Cli::parse();
     if !cli.filename.exists() {
         eprintln!("File does not exist: {}", cli.filename.display());
-        return;
+        return ExitCode::FAILURE;
     }
 
-    let stream = std::fs::File::open(&cli.filename).unwrap();
-    let mut reader = csv_reader::CsvReader::new(stream);
-    let iter = match reader.read_iter() {
-        Ok(iter) => iter,
+    let input_format = cli.input_format.unwrap_or_else(|| Format::from_path(&cli.filename));
+    let output_format = cli.output_format.unwrap_or(input_format);
+    let mut csv_reader = None;
+    let records = match open_input(&cli.filename, input_format, cli.amount_policies(), &mut csv_reader) {
+        Ok(records) => records,
         Err(e) => {
-            eprintln!("Error reading file: {e:?}");
-            return;
+            eprintln!("Error reading file: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut engine = match create_engine(&cli) {
+        Ok(engine) => engine,
+        Err(err) => {
+            eprintln!("Error loading state: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut ledger_writer = match (&cli.ledger, &engine) {
+        (None, _) => None,
+        (Some(path), Engine::Sequential(accounting_system)) => Some(create_ledger_writer(path, accounting_system)),
+        (Some(_), Engine::Sharded(_)) => {
+            eprintln!("Exporting a ledger is supported only with a single thread");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut rejection_writer = cli.rejections.as_deref().map(create_rejection_writer);
+
+    let mut malformed_rows = 0usize;
+    let mut failed = false;
+
+    for record in records {
+        let (line, operation) = match record {
+            Ok(record) => record,
+            Err(err) => {
+                if cli.strict {
+                    eprintln!("Malformed row: {err}");
+                    malformed_rows += 1;
+                }
+                continue;
+            }
+        };
+        if failed {
+            // Only looking for further malformed rows.
+            continue;
+        }
+
+        let processed = run_operation(
+            &mut engine,
+            (line, operation),
+            cli.continue_on_error,
+            &mut ledger_writer,
+            rejection_writer.as_mut(),
+        );
+        if !processed {
+            if !cli.strict {
+                return ExitCode::FAILURE;
+            }
+            failed = true;
+        }
+    }
+
+    if failed {
+        if malformed_rows > 0 {
+            eprintln!("Found {malformed_rows} malformed row(s)");
+        }
+        return ExitCode::FAILURE;
+    }
+
+    let Some(accounting_system) = finish_engine(engine, cli.continue_on_error, rejection_writer.as_mut()) else {
+        return ExitCode::FAILURE;
+    };
+
+    if let Some(writer) = rejection_writer {
+        writer.finish();
+    }
+
+    if let Some(writer) = ledger_writer {
+        if let Err(discrepancies) = writer.finish().verify(&accounting_system) {
+            for discrepancy in discrepancies {
+                eprintln!("Ledger discrepancy: {discrepancy}");
+            }
+            return ExitCode::FAILURE;
         }
+    }
+
+    if malformed_rows > 0 {
+        eprintln!("Found {malformed_rows} malformed row(s), no output written");
+        return ExitCode::FAILURE;
+    }
+
+    if let Some(path) = &cli.save_state {
+        if let Err(err) = save_state(path, &accounting_system) {
+            eprintln!("Error saving state: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    write_accounts(output_format, cli.extended_output, &accounting_system);
+    ExitCode::SUCCESS
+}
+
+fn open_input<'a>(
+    path: &Path,
+    format: Format,
+    amount_policies: AmountPolicies,
+    csv_reader: &'a mut Option<csv_reader::CsvReader<std::fs::File>>,
+) -> Result<Box<dyn Iterator<Item = InputRecord> + 'a>, csv_reader::CsvReaderError> {
+    let stream = std::fs::File::open(path).unwrap();
+    match format {
+        Format::Csv => {
+            let iter = csv_reader
+                .insert(csv_reader::CsvReader::with_amount_policies(stream, amount_policies))
+                .read_iter()?;
+            Ok(Box::new(iter.map(|record| {
+                record
+                    .map(|record| (record.line, record.operation))
+                    .map_err(|err| err.to_string())
+            })))
+        }
+        Format::Jsonl => Ok(Box::new(
+            jsonl_reader::JsonlReader::with_amount_policies(stream, amount_policies).map(|record| {
+                record
+                    .map(|record| (record.line, record.operation))
+                    .map_err(|err| err.to_string())
+            }),
+        )),
+    }
+}
+
+fn create_engine(cli: &Cli) -> Result<Engine, SnapshotError> {
+    let config = AccountingSystemConfig {
+        dispute_policy: match cli.dispute_policy {
+            Disputes::All => DisputePolicy::All,
+            Disputes::DepositsOnly => DisputePolicy::DepositsOnly,
+            Disputes::None => DisputePolicy::None,
+        },
+        overdraft_policy: match cli.overdraft_policy {
+            Overdraft::Allow => OverdraftPolicy::Allow,
+            Overdraft::Reject => OverdraftPolicy::Reject,
+            Overdraft::CapHold => OverdraftPolicy::CapHold,
+        },
+        dispute_window: cli.dispute_window,
+        dispute_time_limit: cli.dispute_time_limit.map(days),
+        chargeback_time_limit: cli.chargeback_time_limit.map(days),
+        amount_policies: cli.amount_policies(),
+    };
+    let accounting_system = match &cli.load_state {
+        Some(path) => load_state(path, config)?,
+        None => AccountingSystem::with_config(config),
     };
+    if cli.threads.get() > 1 {
+        Ok(Engine::Sharded(ShardedAccountingSystem::from_system(
+            accounting_system,
+            cli.threads,
+        )))
+    } else {
+        Ok(Engine::Sequential(accounting_system))
+    }
+}
 
-    let mut accounting_system = AccountingSystem::new();
+fn days(days: u32) -> Duration {
+    Duration::from_secs(u64::from(days) * 24 * 60 * 60)
+}
+
+fn load_state(path: &Path, config: AccountingSystemConfig) -> Result<AccountingSystem, SnapshotError> {
+    let stream = std::io::BufReader::new(std::fs::File::open(path)?);
+    AccountingSystem::load_snapshot(config, stream)
+}
 
-    for operation in iter {
-        if let Err(err) = accounting_system.run_operation(operation) {
-            eprintln!("Error processing operation: {err:?}");
-            return;
+/// Writes the snapshot next to the target first, so that a failed run never leaves a partial state behind.
+fn save_state(path: &Path, accounting_system: &AccountingSystem) -> Result<(), SnapshotError> {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    let file = std::fs::File::create(&temporary)?;
+    accounting_system.save_snapshot(std::io::BufWriter::new(&file))?;
+    file.sync_all()?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+fn write_accounts(format: Format, extended: bool, accounting_system: &AccountingSystem) {
+    match format {
+        Format::Csv => {
+            let mut writer = csv_writer::CsvWriter::new(std::io::stdout(), extended);
+            for account in accounting_system.iter_accounts() {
+                writer.write_client_account(account);
+            }
+        }
+        Format::Jsonl => {
+            let mut writer = jsonl_writer::JsonlWriter::new(std::io::BufWriter::new(std::io::stdout().lock()));
+            for account in accounting_system.iter_accounts() {
+                writer.write_client_account(account);
+            }
         }
     }
+}
+
+/// Runs the operation, returning `false` if its error has to fail the run.
+fn run_operation<T: std::io::Write>(
+    engine: &mut Engine,
+    (line, operation): (u64, AccountingOperation),
+    continue_on_error: bool,
+    sink: &mut impl EventSink,
+    rejection_writer: Option<&mut RejectionWriter<T>>,
+) -> bool {
+    match engine {
+        Engine::Sequential(accounting_system) => {
+            let rejected_operation = continue_on_error.then(|| operation.clone());
+            if let Err(error) = accounting_system.run_operation_with_sink(operation, sink) {
+                let Some(operation) = rejected_operation else {
+                    eprintln!("Error processing operation: {error:?}");
+                    return false;
+                };
+                let rejection = Rejection { line, operation, error };
+                report_rejection(rejection_writer, &rejection);
+            }
+        }
+        Engine::Sharded(accounting_system) => {
+            if let Err(error) = accounting_system.run_operation((line, operation.clone()), operation) {
+                eprintln!("Error processing operation: {error}");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+/// Reports rejections of the sharded engine, returning `None` if any of them, or a failed shard, fails the run.
+fn finish_engine<T: std::io::Write>(
+    engine: Engine,
+    continue_on_error: bool,
+    mut rejection_writer: Option<&mut RejectionWriter<T>>,
+) -> Option<AccountingSystem> {
+    match engine {
+        Engine::Sequential(accounting_system) => Some(accounting_system),
+        Engine::Sharded(accounting_system) => {
+            let (accounting_system, rejections) = match accounting_system.finish() {
+                Ok(finished) => finished,
+                Err(error) => {
+                    eprintln!("Error processing operation: {error}");
+                    return None;
+                }
+            };
+            for ((line, operation), error) in rejections {
+                if !continue_on_error {
+                    eprintln!("Error processing operation: {error:?}");
+                    return None;
+                }
+                let rejection = Rejection { line, operation, error };
+                report_rejection(rejection_writer.as_deref_mut(), &rejection);
+            }
+            Some(accounting_system)
+        }
+    }
+}
+
+fn report_rejection<T: std::io::Write>(writer: Option<&mut RejectionWriter<T>>, rejection: &Rejection) {
+    match writer {
+        Some(writer) => writer.write_rejection(rejection),
+        None => eprintln!("Rejected operation at line {}: {:?}", rejection.line, rejection.error),
+    }
+}
+
+fn create_ledger_writer(
+    path: &Path,
+    accounting_system: &AccountingSystem,
+) -> LedgerWriter<std::io::BufWriter<std::fs::File>> {
+    let stream = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    LedgerWriter::new(Ledger::with_opening_balances(accounting_system), stream)
+}
 
-    let mut writer = csv_writer::CsvWriter::new(std::io::stdout());
-    for account in accounting_system.iter_accounts() {
-        writer.write_client_account(account);
+fn create_rejection_writer(path: &Path) -> RejectionWriter<std::io::BufWriter<std::fs::File>> {
+    let stream = std::io::BufWriter::new(std::fs::File::create(path).unwrap());
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        RejectionWriter::json(stream)
+    } else {
+        RejectionWriter::csv(stream)
     }
 }
@@ -0,0 +1,203 @@
+use std::io::Write;
+
+use rtm_core::{
+    models::{AccountingOperation, TransactionKind},
+    processor::TransactionError,
+};
+
+const HEADERS: [&str; 6] = ["line", "type", "client", "tx", "amount", "error"];
+
+/// Operation rejected by the accounting system, together with the input line it came from.
+#[derive(Debug)]
+pub struct Rejection {
+    pub line: u64,
+    pub operation: AccountingOperation,
+    pub error: TransactionError,
+}
+
+impl Rejection {
+    fn fields(&self) -> [String; 6] {
+        let (kind, amount) = match &self.operation {
+            AccountingOperation::Transaction { transaction } => {
+                let kind = match transaction.kind() {
+                    TransactionKind::Deposit => "deposit",
+                    TransactionKind::Withdrawal => "withdrawal",
+                };
+                (kind, transaction.amount().to_string())
+            }
+            AccountingOperation::Dispute { .. } => ("dispute", String::new()),
+            AccountingOperation::Resolve { .. } => ("resolve", String::new()),
+            AccountingOperation::Chargeback { .. } => ("chargeback", String::new()),
+            AccountingOperation::Unlock { .. } => ("unlock", String::new()),
+            AccountingOperation::Freeze { .. } => ("freeze", String::new()),
+            AccountingOperation::Close { .. } => ("close", String::new()),
+        };
+        let tx = match &self.operation {
+            AccountingOperation::Transaction { transaction } => transaction.id().as_u32().to_string(),
+            AccountingOperation::Dispute { ref_id, .. }
+            | AccountingOperation::Resolve { ref_id, .. }
+            | AccountingOperation::Chargeback { ref_id, .. } => ref_id.as_u32().to_string(),
+            AccountingOperation::Unlock { .. }
+            | AccountingOperation::Freeze { .. }
+            | AccountingOperation::Close { .. } => String::new(),
+        };
+        [
+            self.line.to_string(),
+            kind.to_string(),
+            self.operation.client_id().as_u16().to_string(),
+            tx,
+            amount,
+            self.error.name().to_string(),
+        ]
+    }
+}
+
+/// Writes rejected operations either as csv rows or as a json array of objects.
+pub enum RejectionWriter<T: Write> {
+    Csv(Box<csv::Writer<T>>),
+    Json { stream: T, empty: bool },
+}
+
+impl<T: Write> RejectionWriter<T> {
+    pub fn csv(stream: T) -> Self {
+        let mut writer = csv::Writer::from_writer(stream);
+        writer.write_record(HEADERS).unwrap();
+        Self::Csv(Box::new(writer))
+    }
+
+    pub fn json(mut stream: T) -> Self {
+        stream.write_all(b"[").unwrap();
+        Self::Json { stream, empty: true }
+    }
+
+    pub fn write_rejection(&mut self, rejection: &Rejection) {
+        let fields = rejection.fields();
+        match self {
+            Self::Csv(writer) => {
+                writer.write_record(&fields).unwrap();
+            }
+            Self::Json { stream, empty } => {
+                if !*empty {
+                    stream.write_all(b",").unwrap();
+                }
+                *empty = false;
+                let [line, kind, client, tx, amount, error] = &fields;
+                let tx = if tx.is_empty() { "null" } else { tx };
+                let amount = if amount.is_empty() {
+                    "null".to_string()
+                } else {
+                    format!("\"{amount}\"")
+                };
+                write!(
+                    stream,
+                    "\n  {{\"line\": {line}, \"type\": \"{kind}\", \"client\": {client}, \"tx\": {tx}, \"amount\": {amount}, \"error\": \"{error}\"}}"
+                )
+                .unwrap();
+            }
+        }
+    }
+
+    /// Flushes the report, closing the json array if needed.
+    pub fn finish(self) {
+        match self {
+            Self::Csv(mut writer) => {
+                writer.flush().unwrap();
+            }
+            Self::Json { mut stream, empty } => {
+                let closing: &[u8] = if empty { b"]\n" } else { b"\n]\n" };
+                stream.write_all(closing).unwrap();
+                stream.flush().unwrap();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rtm_core::models::{Amount, ClientId, Transaction, TransactionId};
+
+    use super::*;
+
+    fn rejections() -> Vec<Rejection> {
+        vec![
+            Rejection {
+                line: 3,
+                operation: AccountingOperation::Transaction {
+                    transaction: Transaction::new(
+                        ClientId::from(1),
+                        TransactionId::from(2),
+                        Amount::try_from("1.5").unwrap(),
+                        TransactionKind::Withdrawal,
+                    ),
+                },
+                error: TransactionError::InsufficientFunds {
+                    cause_id: TransactionId::from(2),
+                },
+            },
+            Rejection {
+                line: 4,
+                operation: AccountingOperation::Dispute {
+                    client_id: ClientId::from(1),
+                    ref_id: TransactionId::from(7),
+                    currency: None,
+                    timestamp: None,
+                },
+                error: TransactionError::TransactionDoesNotExist {
+                    ref_id: TransactionId::from(7),
+                },
+            },
+            Rejection {
+                line: 5,
+                operation: AccountingOperation::Unlock {
+                    client_id: ClientId::from(1),
+                    reason: "reviewed".to_string(),
+                    timestamp: None,
+                },
+                error: TransactionError::AccountNotLocked {
+                    client_id: ClientId::from(1),
+                },
+            },
+        ]
+    }
+
+    fn write(mut writer: RejectionWriter<&mut Vec<u8>>) {
+        for rejection in rejections() {
+            writer.write_rejection(&rejection);
+        }
+        writer.finish();
+    }
+
+    #[test]
+    fn test_csv_report() {
+        let mut output = Vec::new();
+        write(RejectionWriter::csv(&mut output));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "line,type,client,tx,amount,error\n\
+             3,withdrawal,1,2,1.5000,InsufficientFunds\n\
+             4,dispute,1,7,,TransactionDoesNotExist\n\
+             5,unlock,1,,,AccountNotLocked\n"
+        );
+    }
+
+    #[test]
+    fn test_json_report() {
+        let mut output = Vec::new();
+        write(RejectionWriter::json(&mut output));
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "[\n  \
+             {\"line\": 3, \"type\": \"withdrawal\", \"client\": 1, \"tx\": 2, \"amount\": \"1.5000\", \"error\": \"InsufficientFunds\"},\n  \
+             {\"line\": 4, \"type\": \"dispute\", \"client\": 1, \"tx\": 7, \"amount\": null, \"error\": \"TransactionDoesNotExist\"},\n  \
+             {\"line\": 5, \"type\": \"unlock\", \"client\": 1, \"tx\": null, \"amount\": null, \"error\": \"AccountNotLocked\"}\n\
+             ]\n"
+        );
+    }
+
+    #[test]
+    fn test_empty_json_report() {
+        let mut output = Vec::new();
+        RejectionWriter::json(&mut output).finish();
+        assert_eq!(String::from_utf8(output).unwrap(), "[]\n");
+    }
+}
@@ -0,0 +1,102 @@
+//! Measures peak heap usage of the [`AccountingSystem`] while streaming a growing number
+//! of operations through it. With bounded retention the peak should stay flat.
+//!
+//! Run with `cargo bench -p rtm_core --bench memory`.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind},
+    processor::{AccountingSystem, AccountingSystemConfig, DisputePolicy},
+};
+
+struct CountingAllocator;
+
+static CURRENT: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let current = CURRENT.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(current, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        CURRENT.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+const CLIENTS: u32 = 1000;
+
+/// Groups of ten operations per client: deposits, then a dispute and a resolution of the
+/// first deposit of the group, followed by a withdrawal.
+fn operations(count: u32) -> impl Iterator<Item = AccountingOperation> {
+    (0..count).map(|index| {
+        let client_id = ClientId::from(u16::try_from((index / 10) % CLIENTS).unwrap());
+        let id = TransactionId::from(index);
+        let first_in_group = TransactionId::from(index - index % 10);
+        match index % 10 {
+            7 => AccountingOperation::Dispute {
+                client_id,
+                ref_id: first_in_group,
+                currency: None,
+                timestamp: None,
+            },
+            8 => AccountingOperation::Resolve {
+                client_id,
+                ref_id: first_in_group,
+                currency: None,
+                timestamp: None,
+            },
+            9 => AccountingOperation::Transaction {
+                transaction: Transaction::new(client_id, id, Amount::from(1), TransactionKind::Withdrawal),
+            },
+            _ => AccountingOperation::Transaction {
+                transaction: Transaction::new(client_id, id, Amount::from(10), TransactionKind::Deposit),
+            },
+        }
+    })
+}
+
+fn peak_memory(config: &AccountingSystemConfig, count: u32) -> usize {
+    let baseline = CURRENT.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let mut accounting_system = AccountingSystem::with_config(config.clone());
+    for operation in operations(count) {
+        let _ = accounting_system.run_operation(operation);
+    }
+    drop(accounting_system);
+    PEAK.load(Ordering::Relaxed) - baseline
+}
+
+fn main() {
+    let configs = [
+        ("retain all", AccountingSystemConfig::default()),
+        (
+            "deposits only, window 100",
+            AccountingSystemConfig {
+                dispute_policy: DisputePolicy::DepositsOnly,
+                dispute_window: Some(100),
+                ..AccountingSystemConfig::default()
+            },
+        ),
+    ];
+
+    for (name, config) in &configs {
+        println!("{name}:");
+        for count in [250_000, 1_000_000, 4_000_000] {
+            let peak = peak_memory(config, count);
+            println!("  {count:>9} operations: peak {:>8} KB", peak / 1024);
+        }
+    }
+}
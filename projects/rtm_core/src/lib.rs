@@ -11,3 +11,6 @@
 )]
 pub mod models;
 pub mod processor;
+
+#[cfg(feature = "tokio")]
+pub mod service;
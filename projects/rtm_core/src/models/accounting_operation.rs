@@ -1,24 +1,622 @@
-use crate::models::{ClientId, TransactionId};
+use crate::models::{
+    Amount, AmountPolicies, AmountPolicy, ClientId, Currency, InvalidCurrencyError, InvalidNumericalStringError,
+    Timestamp, TransactionId, TransactionKind,
+};
 
 use super::Transaction;
 
+/// Longest reason of an administrative operation, in bytes, so that it always fits a journal record.
+pub const MAX_REASON_LENGTH: usize = 256;
+
+/// Why a field of a textual operation record could not be parsed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[must_use]
+pub enum InvalidFieldReason {
+    /// Value is not of the type expected for the column, e.g. an unknown operation type.
+    Malformed,
+
+    /// Amount is zero or negative.
+    NotPositive,
+
+    /// Amount has more significant decimal places than allowed by the [`AmountPolicy`].
+    TooPrecise,
+
+    /// Amount is too large.
+    OutOfRange,
+
+    /// Reason is longer than [`MAX_REASON_LENGTH`].
+    TooLong,
+}
+
+impl From<InvalidNumericalStringError> for InvalidFieldReason {
+    fn from(value: InvalidNumericalStringError) -> Self {
+        match value {
+            InvalidNumericalStringError::Malformed => InvalidFieldReason::Malformed,
+            InvalidNumericalStringError::TooPrecise => InvalidFieldReason::TooPrecise,
+            InvalidNumericalStringError::OutOfRange => InvalidFieldReason::OutOfRange,
+        }
+    }
+}
+
+impl From<InvalidCurrencyError> for InvalidFieldReason {
+    fn from(_: InvalidCurrencyError) -> Self {
+        InvalidFieldReason::Malformed
+    }
+}
+
+impl From<std::num::ParseIntError> for InvalidFieldReason {
+    fn from(_: std::num::ParseIntError) -> Self {
+        InvalidFieldReason::Malformed
+    }
+}
+
+impl std::fmt::Display for InvalidFieldReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidFieldReason::Malformed => write!(f, "malformed"),
+            InvalidFieldReason::NotPositive => write!(f, "amount is not positive"),
+            InvalidFieldReason::TooPrecise => write!(f, "amount has too many decimal places"),
+            InvalidFieldReason::OutOfRange => write!(f, "amount is out of range"),
+            InvalidFieldReason::TooLong => write!(f, "reason is longer than {MAX_REASON_LENGTH} bytes"),
+        }
+    }
+}
+
+/// Field of a textual operation record that could not be parsed.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[must_use]
+pub struct InvalidOperationFieldError {
+    /// Name of the field: `type`, `client`, `tx`, `amount`, `currency`, `timestamp` or `reason`.
+    pub column: &'static str,
+    pub value: String,
+    pub reason: InvalidFieldReason,
+}
+
+impl std::fmt::Display for InvalidOperationFieldError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "column `{}`: invalid value `{}`", self.column, self.value)?;
+        if self.reason != InvalidFieldReason::Malformed {
+            write!(f, ", {}", self.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Textual fields of an operation record, e.g. a csv row, with missing fields left empty.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[must_use]
+pub struct OperationRecord<'a> {
+    pub record_type: &'a str,
+    pub client: &'a str,
+    pub tx: &'a str,
+    pub amount: &'a str,
+    pub currency: &'a str,
+    pub timestamp: &'a str,
+    pub reason: &'a str,
+}
+
 /// Represents an accounting operation that can be applied to the accounting system.
-#[derive(Debug)]
+///
+/// Disputes, resolves and chargebacks may name the currency of the referred transaction,
+/// which is then verified by the accounting system. Like transactions, they may carry the
+/// time they were made, used to enforce the time limits of disputes and chargebacks.
+///
+/// Unlocks, freezes and closes are administrative operations applying to all accounts of
+/// the client, with a reason recorded for audit.
+///
+/// Serialized as a flat object mirroring a csv row, with the `type` field as a tag and
+/// `amount` present only for deposits and withdrawals, e.g.
+/// `{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5000", "currency": "EUR"}`,
+/// `{"type": "dispute", "client": 1, "tx": 1}` or `{"type": "freeze", "client": 1, "reason": "fraud"}`.
+/// The `currency` is omitted when unspecified, and the `timestamp` when unknown.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "serde_repr::OperationRepr", try_from = "serde_repr::OperationRepr")
+)]
 #[must_use]
 pub enum AccountingOperation {
-    Transaction { transaction: Transaction },
-    Dispute { client_id: ClientId, ref_id: TransactionId },
-    Resolve { client_id: ClientId, ref_id: TransactionId },
-    Chargeback { client_id: ClientId, ref_id: TransactionId },
+    Transaction {
+        transaction: Transaction,
+    },
+    Dispute {
+        client_id: ClientId,
+        ref_id: TransactionId,
+        currency: Option<Currency>,
+        timestamp: Option<Timestamp>,
+    },
+    Resolve {
+        client_id: ClientId,
+        ref_id: TransactionId,
+        currency: Option<Currency>,
+        timestamp: Option<Timestamp>,
+    },
+    Chargeback {
+        client_id: ClientId,
+        ref_id: TransactionId,
+        currency: Option<Currency>,
+        timestamp: Option<Timestamp>,
+    },
+    /// Returns a locked or frozen client to normal.
+    Unlock {
+        client_id: ClientId,
+        reason: String,
+        timestamp: Option<Timestamp>,
+    },
+    /// Locks a client until it is unlocked.
+    Freeze {
+        client_id: ClientId,
+        reason: String,
+        timestamp: Option<Timestamp>,
+    },
+    /// Closes a client without any funds or open disputes for good.
+    Close {
+        client_id: ClientId,
+        reason: String,
+        timestamp: Option<Timestamp>,
+    },
 }
 
 impl AccountingOperation {
+    /// Parses operation from its textual fields, e.g. taken from a csv row. All fields are
+    /// trimmed before parsing, and type and currency code are matched case-insensitively.
+    /// Amount is only parsed for deposits and withdrawals, by the policy of their currency,
+    /// and has to be positive. Currency and timestamp, in seconds since the Unix epoch, may
+    /// be empty. Administrative operations ignore the `tx`, `amount` and `currency` fields
+    /// and require a non-empty `reason`.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidOperationFieldError`] with the first field that could not be parsed.
+    pub fn parse_record(
+        record: &OperationRecord<'_>,
+        amount_policies: &AmountPolicies,
+    ) -> Result<Self, InvalidOperationFieldError> {
+        macro_rules! parse_field {
+            ($parser:expr, $value:expr, $column:literal) => {{
+                let value = $value.trim();
+                match $parser(value) {
+                    Ok(result) => result,
+                    Err(err) => {
+                        return Err(InvalidOperationFieldError {
+                            column: $column,
+                            value: value.to_string(),
+                            reason: InvalidFieldReason::from(err),
+                        });
+                    }
+                }
+            }};
+        }
+
+        let record_type = record.record_type.trim();
+        let lowercase_type = record_type.to_ascii_lowercase();
+        let administrative = matches!(lowercase_type.as_str(), "unlock" | "freeze" | "close");
+
+        let client_id = parse_field!(ClientId::try_from, record.client, "client");
+        let id = if administrative {
+            None
+        } else {
+            Some(parse_field!(TransactionId::try_from, record.tx, "tx"))
+        };
+        let currency = match record.currency.trim() {
+            "" => None,
+            _ if administrative => None,
+            code => Some(parse_field!(Currency::try_from, code, "currency")),
+        };
+        let timestamp = match record.timestamp.trim() {
+            "" => None,
+            value => Some(parse_field!(Timestamp::try_from, value, "timestamp")),
+        };
+        let transaction_currency = currency.unwrap_or_default();
+        let parse_amount = |value| parse_amount(value, amount_policies.of(transaction_currency));
+        let amount = record.amount;
+        let reason = record.reason;
+
+        let operation = match (lowercase_type.as_str(), id) {
+            ("deposit", Some(id)) => AccountingOperation::Transaction {
+                transaction: Transaction::with_currency(
+                    client_id,
+                    id,
+                    parse_field!(parse_amount, amount, "amount"),
+                    transaction_currency,
+                    TransactionKind::Deposit,
+                )
+                .with_timestamp(timestamp),
+            },
+            ("withdrawal", Some(id)) => AccountingOperation::Transaction {
+                transaction: Transaction::with_currency(
+                    client_id,
+                    id,
+                    parse_field!(parse_amount, amount, "amount"),
+                    transaction_currency,
+                    TransactionKind::Withdrawal,
+                )
+                .with_timestamp(timestamp),
+            },
+            ("dispute", Some(ref_id)) => AccountingOperation::Dispute {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            },
+            ("resolve", Some(ref_id)) => AccountingOperation::Resolve {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            },
+            ("chargeback", Some(ref_id)) => AccountingOperation::Chargeback {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            },
+            ("unlock", _) => AccountingOperation::Unlock {
+                client_id,
+                reason: parse_field!(parse_reason, reason, "reason"),
+                timestamp,
+            },
+            ("freeze", _) => AccountingOperation::Freeze {
+                client_id,
+                reason: parse_field!(parse_reason, reason, "reason"),
+                timestamp,
+            },
+            ("close", _) => AccountingOperation::Close {
+                client_id,
+                reason: parse_field!(parse_reason, reason, "reason"),
+                timestamp,
+            },
+            _ => {
+                return Err(InvalidOperationFieldError {
+                    column: "type",
+                    value: record_type.to_string(),
+                    reason: InvalidFieldReason::Malformed,
+                });
+            }
+        };
+        Ok(operation)
+    }
+
     pub const fn client_id(&self) -> ClientId {
         match self {
             AccountingOperation::Transaction { transaction } => transaction.client_id(),
             AccountingOperation::Dispute { client_id, .. }
             | AccountingOperation::Resolve { client_id, .. }
-            | AccountingOperation::Chargeback { client_id, .. } => *client_id,
+            | AccountingOperation::Chargeback { client_id, .. }
+            | AccountingOperation::Unlock { client_id, .. }
+            | AccountingOperation::Freeze { client_id, .. }
+            | AccountingOperation::Close { client_id, .. } => *client_id,
+        }
+    }
+
+    /// Currency of the transaction, or the one expected by a dispute, resolve or chargeback.
+    /// Administrative operations apply to all currencies.
+    #[must_use]
+    pub const fn currency(&self) -> Option<Currency> {
+        match self {
+            AccountingOperation::Transaction { transaction } => Some(transaction.currency()),
+            AccountingOperation::Dispute { currency, .. }
+            | AccountingOperation::Resolve { currency, .. }
+            | AccountingOperation::Chargeback { currency, .. } => *currency,
+            AccountingOperation::Unlock { .. }
+            | AccountingOperation::Freeze { .. }
+            | AccountingOperation::Close { .. } => None,
+        }
+    }
+
+    /// Time the operation was made, if known.
+    #[must_use]
+    pub const fn timestamp(&self) -> Option<Timestamp> {
+        match self {
+            AccountingOperation::Transaction { transaction } => transaction.timestamp(),
+            AccountingOperation::Dispute { timestamp, .. }
+            | AccountingOperation::Resolve { timestamp, .. }
+            | AccountingOperation::Chargeback { timestamp, .. }
+            | AccountingOperation::Unlock { timestamp, .. }
+            | AccountingOperation::Freeze { timestamp, .. }
+            | AccountingOperation::Close { timestamp, .. } => *timestamp,
+        }
+    }
+
+    /// Audit reason of an administrative operation.
+    #[must_use]
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            AccountingOperation::Transaction { .. }
+            | AccountingOperation::Dispute { .. }
+            | AccountingOperation::Resolve { .. }
+            | AccountingOperation::Chargeback { .. } => None,
+            AccountingOperation::Unlock { reason, .. }
+            | AccountingOperation::Freeze { reason, .. }
+            | AccountingOperation::Close { reason, .. } => Some(reason),
+        }
+    }
+}
+
+fn parse_reason(value: &str) -> Result<String, InvalidFieldReason> {
+    if value.is_empty() {
+        return Err(InvalidFieldReason::Malformed);
+    }
+    if value.len() > MAX_REASON_LENGTH {
+        return Err(InvalidFieldReason::TooLong);
+    }
+    Ok(value.to_string())
+}
+
+fn parse_amount(value: &str, amount_policy: AmountPolicy) -> Result<Amount, InvalidFieldReason> {
+    let amount = amount_policy.parse(value)?;
+    if !amount.is_positive() {
+        return Err(InvalidFieldReason::NotPositive);
+    }
+    Ok(amount)
+}
+
+#[cfg(feature = "serde")]
+mod serde_repr {
+    use crate::models::{
+        AccountingOperation, Amount, ClientId, Currency, MAX_REASON_LENGTH, Timestamp, Transaction, TransactionId,
+        TransactionKind,
+    };
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(tag = "type", rename_all = "lowercase")]
+    pub(super) enum OperationRepr {
+        Deposit {
+            client: ClientId,
+            tx: TransactionId,
+            amount: Amount,
+            #[serde(default, skip_serializing_if = "Currency::is_unspecified")]
+            currency: Currency,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Withdrawal {
+            client: ClientId,
+            tx: TransactionId,
+            amount: Amount,
+            #[serde(default, skip_serializing_if = "Currency::is_unspecified")]
+            currency: Currency,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Dispute {
+            client: ClientId,
+            tx: TransactionId,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            currency: Option<Currency>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Resolve {
+            client: ClientId,
+            tx: TransactionId,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            currency: Option<Currency>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Chargeback {
+            client: ClientId,
+            tx: TransactionId,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            currency: Option<Currency>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Unlock {
+            client: ClientId,
+            reason: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Freeze {
+            client: ClientId,
+            reason: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+        Close {
+            client: ClientId,
+            reason: String,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            timestamp: Option<Timestamp>,
+        },
+    }
+
+    impl From<AccountingOperation> for OperationRepr {
+        fn from(value: AccountingOperation) -> Self {
+            match value {
+                AccountingOperation::Transaction { transaction } => {
+                    let client = transaction.client_id();
+                    let tx = transaction.id();
+                    let amount = transaction.amount().clone();
+                    let currency = transaction.currency();
+                    let timestamp = transaction.timestamp();
+                    match transaction.kind() {
+                        TransactionKind::Deposit => OperationRepr::Deposit {
+                            client,
+                            tx,
+                            amount,
+                            currency,
+                            timestamp,
+                        },
+                        TransactionKind::Withdrawal => OperationRepr::Withdrawal {
+                            client,
+                            tx,
+                            amount,
+                            currency,
+                            timestamp,
+                        },
+                    }
+                }
+                AccountingOperation::Dispute {
+                    client_id,
+                    ref_id,
+                    currency,
+                    timestamp,
+                } => OperationRepr::Dispute {
+                    client: client_id,
+                    tx: ref_id,
+                    currency,
+                    timestamp,
+                },
+                AccountingOperation::Resolve {
+                    client_id,
+                    ref_id,
+                    currency,
+                    timestamp,
+                } => OperationRepr::Resolve {
+                    client: client_id,
+                    tx: ref_id,
+                    currency,
+                    timestamp,
+                },
+                AccountingOperation::Chargeback {
+                    client_id,
+                    ref_id,
+                    currency,
+                    timestamp,
+                } => OperationRepr::Chargeback {
+                    client: client_id,
+                    tx: ref_id,
+                    currency,
+                    timestamp,
+                },
+                AccountingOperation::Unlock {
+                    client_id,
+                    reason,
+                    timestamp,
+                } => OperationRepr::Unlock {
+                    client: client_id,
+                    reason,
+                    timestamp,
+                },
+                AccountingOperation::Freeze {
+                    client_id,
+                    reason,
+                    timestamp,
+                } => OperationRepr::Freeze {
+                    client: client_id,
+                    reason,
+                    timestamp,
+                },
+                AccountingOperation::Close {
+                    client_id,
+                    reason,
+                    timestamp,
+                } => OperationRepr::Close {
+                    client: client_id,
+                    reason,
+                    timestamp,
+                },
+            }
+        }
+    }
+
+    impl TryFrom<OperationRepr> for AccountingOperation {
+        type Error = &'static str;
+
+        fn try_from(value: OperationRepr) -> Result<Self, Self::Error> {
+            if let OperationRepr::Deposit { amount, .. } | OperationRepr::Withdrawal { amount, .. } = &value {
+                if !amount.is_positive() {
+                    return Err("amount is not positive");
+                }
+            }
+            if let OperationRepr::Unlock { reason, .. }
+            | OperationRepr::Freeze { reason, .. }
+            | OperationRepr::Close { reason, .. } = &value
+            {
+                if reason.trim().is_empty() {
+                    return Err("reason is empty");
+                }
+                if reason.len() > MAX_REASON_LENGTH {
+                    return Err("reason is too long");
+                }
+            }
+            let operation = match value {
+                OperationRepr::Deposit {
+                    client,
+                    tx,
+                    amount,
+                    currency,
+                    timestamp,
+                } => AccountingOperation::Transaction {
+                    transaction: Transaction::with_currency(client, tx, amount, currency, TransactionKind::Deposit)
+                        .with_timestamp(timestamp),
+                },
+                OperationRepr::Withdrawal {
+                    client,
+                    tx,
+                    amount,
+                    currency,
+                    timestamp,
+                } => AccountingOperation::Transaction {
+                    transaction: Transaction::with_currency(client, tx, amount, currency, TransactionKind::Withdrawal)
+                        .with_timestamp(timestamp),
+                },
+                OperationRepr::Dispute {
+                    client,
+                    tx,
+                    currency,
+                    timestamp,
+                } => AccountingOperation::Dispute {
+                    client_id: client,
+                    ref_id: tx,
+                    currency,
+                    timestamp,
+                },
+                OperationRepr::Resolve {
+                    client,
+                    tx,
+                    currency,
+                    timestamp,
+                } => AccountingOperation::Resolve {
+                    client_id: client,
+                    ref_id: tx,
+                    currency,
+                    timestamp,
+                },
+                OperationRepr::Chargeback {
+                    client,
+                    tx,
+                    currency,
+                    timestamp,
+                } => AccountingOperation::Chargeback {
+                    client_id: client,
+                    ref_id: tx,
+                    currency,
+                    timestamp,
+                },
+                OperationRepr::Unlock {
+                    client,
+                    reason,
+                    timestamp,
+                } => AccountingOperation::Unlock {
+                    client_id: client,
+                    reason,
+                    timestamp,
+                },
+                OperationRepr::Freeze {
+                    client,
+                    reason,
+                    timestamp,
+                } => AccountingOperation::Freeze {
+                    client_id: client,
+                    reason,
+                    timestamp,
+                },
+                OperationRepr::Close {
+                    client,
+                    reason,
+                    timestamp,
+                } => AccountingOperation::Close {
+                    client_id: client,
+                    reason,
+                    timestamp,
+                },
+            };
+            Ok(operation)
         }
     }
 }
@@ -1,8 +1,28 @@
-use rust_decimal::Decimal;
+use std::collections::BTreeMap;
 
-const DECIMAL_PRECISION: u32 = 4;
+use rust_decimal::{Decimal, RoundingStrategy};
 
-/// Represents a decimal amount with a fixed precision 4.
+use super::Currency;
+
+/// Decimal places of amounts created without an [`AmountPolicy`], e.g. with `Amount::from(1)`.
+pub const DEFAULT_DECIMAL_PLACES: u32 = 4;
+
+/// Largest number of decimal places an amount can have.
+pub const MAX_DECIMAL_PLACES: u32 = Decimal::MAX_SCALE;
+
+/// Represents a decimal amount with a fixed number of decimal places, by default 4.
+///
+/// Amounts parsed with an [`AmountPolicy`] have the number of decimal places of the policy.
+/// Results of additions and subtractions have the decimal places of the more precise operand,
+/// so balances keep the precision of their transactions. Comparisons ignore decimal places,
+/// i.e. `1.50` is equal to `1.5000`.
+///
+/// With the `serde` feature it is serialized as a string, e.g. `"1.5000"`, to avoid
+/// losing precision on floating point conversions. It is deserialized keeping all decimal
+/// places, so that amounts of any [`AmountPolicy`] can be read back.
+///
+/// Arithmetic operators panic on overflow, like the ones of integers. The `checked_*`
+/// methods should be used for amounts coming from untrusted input.
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Hash)]
 #[repr(transparent)]
 #[must_use]
@@ -12,13 +32,196 @@ pub struct Amount {
 
 impl Amount {
     pub fn zero() -> Self {
-        Self::new(Decimal::from(0))
+        Self::new(Decimal::from(0), DEFAULT_DECIMAL_PLACES)
     }
 
-    fn new(mut value: Decimal) -> Self {
-        value.rescale(DECIMAL_PRECISION);
+    fn new(mut value: Decimal, decimal_places: u32) -> Self {
+        value.rescale(decimal_places);
         Self { value }
     }
+
+    /// Same as [`Amount::new`], but returns `None` when the value is too large to keep all
+    /// of its decimal places.
+    fn checked_new(value: Decimal, decimal_places: u32) -> Option<Self> {
+        let amount = Self::new(value, decimal_places);
+        (amount.value.scale() == decimal_places).then_some(amount)
+    }
+
+    /// Parses the value keeping all of its decimal places, as written by [`Amount`]'s `Display`.
+    pub(crate) fn parse_exact(value: &str) -> Result<Self, InvalidNumericalStringError> {
+        parse_decimal(value).map(|value| Self { value })
+    }
+
+    /// Number of decimal places, including trailing zeros.
+    #[must_use]
+    pub fn decimal_places(&self) -> u32 {
+        self.value.scale()
+    }
+
+    /// Whether the amount is greater than zero.
+    #[must_use]
+    pub fn is_positive(&self) -> bool {
+        self.value > Decimal::ZERO
+    }
+
+    /// Returns `None` on overflow instead of panicking.
+    #[must_use]
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        Self::checked_new(self.value.checked_add(rhs.value)?, self.result_decimal_places(rhs))
+    }
+
+    /// Returns `None` on overflow instead of panicking.
+    #[must_use]
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        Self::checked_new(self.value.checked_sub(rhs.value)?, self.result_decimal_places(rhs))
+    }
+
+    /// Returns `None` on overflow instead of panicking.
+    #[must_use]
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        Self::checked_new(self.value.checked_mul(rhs.value)?, self.result_decimal_places(rhs))
+    }
+
+    /// Returns `None` on overflow or division by zero instead of panicking.
+    #[must_use]
+    pub fn checked_div(&self, rhs: &Self) -> Option<Self> {
+        Self::checked_new(self.value.checked_div(rhs.value)?, self.result_decimal_places(rhs))
+    }
+
+    fn result_decimal_places(&self, rhs: &Self) -> u32 {
+        self.value.scale().max(rhs.value.scale())
+    }
+}
+
+/// How values with more decimal places than allowed by an [`AmountPolicy`] are treated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[must_use]
+pub enum Rounding {
+    /// Values are rejected as [`InvalidNumericalStringError::TooPrecise`].
+    Reject,
+
+    /// Rounded half to even, i.e. banker's rounding: `0.125` becomes `0.12`.
+    #[default]
+    HalfEven,
+
+    /// Rounded half away from zero: `0.125` becomes `0.13`.
+    HalfUp,
+
+    /// Excess decimal places are dropped: `0.129` becomes `0.12`.
+    Truncate,
+}
+
+/// Number of decimal places of amounts and the way excess ones are rounded. Applied to
+/// parsed amounts and to transactions of the [`AccountingSystem`](crate::processor::AccountingSystem),
+/// so that all balances have exactly `decimal_places` decimal places.
+///
+/// Defaults to 4 decimal places, rounding more precise values half to even.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[must_use]
+pub struct AmountPolicy {
+    /// Number of decimal places, at most [`MAX_DECIMAL_PLACES`].
+    pub decimal_places: u32,
+    pub rounding: Rounding,
+}
+
+impl AmountPolicy {
+    pub const fn new(decimal_places: u32, rounding: Rounding) -> Self {
+        Self {
+            decimal_places,
+            rounding,
+        }
+    }
+
+    /// Zero with the decimal places of the policy.
+    pub fn zero(self) -> Amount {
+        Amount::new(Decimal::ZERO, self.decimal_places())
+    }
+
+    /// Parses a decimal number and applies the policy to it. Trailing zeros never make
+    /// the number too precise, e.g. `"1.50000"` is accepted with 2 decimal places.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidNumericalStringError`] describing why the value is not a valid amount.
+    pub fn parse(self, value: &str) -> Result<Amount, InvalidNumericalStringError> {
+        let decimal = parse_decimal(value)?;
+        // The parser silently rounds excess decimal places, so they are counted in the input.
+        let decimal_places = value
+            .split_once('.')
+            .map_or("", |(_, fraction)| fraction)
+            .trim_end_matches(['0', '_'])
+            .chars()
+            .filter(char::is_ascii_digit)
+            .count();
+        if self.rounding == Rounding::Reject && decimal_places > self.decimal_places() as usize {
+            return Err(InvalidNumericalStringError::TooPrecise);
+        }
+        self.round(decimal)
+    }
+
+    /// Rounds the amount to the decimal places of the policy.
+    ///
+    /// # Errors
+    ///
+    /// [`InvalidNumericalStringError::TooPrecise`] if the amount has more significant decimal
+    /// places and the policy rejects them, [`InvalidNumericalStringError::OutOfRange`] if it
+    /// is too large to have the decimal places of the policy.
+    pub fn apply(self, amount: &Amount) -> Result<Amount, InvalidNumericalStringError> {
+        if self.rounding == Rounding::Reject && amount.value.normalize().scale() > self.decimal_places() {
+            return Err(InvalidNumericalStringError::TooPrecise);
+        }
+        self.round(amount.value)
+    }
+
+    fn round(self, value: Decimal) -> Result<Amount, InvalidNumericalStringError> {
+        let strategy = match self.rounding {
+            Rounding::Reject | Rounding::HalfEven => RoundingStrategy::MidpointNearestEven,
+            Rounding::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+            Rounding::Truncate => RoundingStrategy::ToZero,
+        };
+        let decimal_places = self.decimal_places();
+        let rounded = value.round_dp_with_strategy(decimal_places, strategy);
+        Amount::checked_new(rounded, decimal_places).ok_or(InvalidNumericalStringError::OutOfRange)
+    }
+
+    const fn decimal_places(self) -> u32 {
+        if self.decimal_places < MAX_DECIMAL_PLACES {
+            self.decimal_places
+        } else {
+            MAX_DECIMAL_PLACES
+        }
+    }
+}
+
+impl Default for AmountPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_DECIMAL_PLACES, Rounding::default())
+    }
+}
+
+/// [`AmountPolicy`] of every currency: the ones given in `currencies`, and `default`
+/// for all other currencies.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+#[must_use]
+pub struct AmountPolicies {
+    pub default: AmountPolicy,
+    pub currencies: BTreeMap<Currency, AmountPolicy>,
+}
+
+impl AmountPolicies {
+    pub fn of(&self, currency: Currency) -> AmountPolicy {
+        self.currencies.get(&currency).copied().unwrap_or(self.default)
+    }
+}
+
+/// Same policy for all currencies.
+impl From<AmountPolicy> for AmountPolicies {
+    fn from(default: AmountPolicy) -> Self {
+        Self {
+            default,
+            currencies: BTreeMap::new(),
+        }
+    }
 }
 
 impl std::fmt::Display for Amount {
@@ -29,13 +232,13 @@ impl std::fmt::Display for Amount {
 
 impl From<i32> for Amount {
     fn from(value: i32) -> Self {
-        Self::new(value.into())
+        Self::new(value.into(), DEFAULT_DECIMAL_PLACES)
     }
 }
 
 impl From<u32> for Amount {
     fn from(value: u32) -> Self {
-        Self::new(value.into())
+        Self::new(value.into(), DEFAULT_DECIMAL_PLACES)
     }
 }
 
@@ -45,37 +248,77 @@ impl Default for Amount {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash)]
-pub struct InvalidNumericalStringError;
+/// Reason why a string is not a valid [`Amount`].
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum InvalidNumericalStringError {
+    /// Not a decimal number.
+    Malformed,
 
+    /// Number has more significant decimal places than allowed, so it would have to be rounded.
+    TooPrecise,
+
+    /// Number is too large to be represented with the required decimal places.
+    OutOfRange,
+}
+
+impl std::fmt::Display for InvalidNumericalStringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvalidNumericalStringError::Malformed => write!(f, "not a decimal number"),
+            InvalidNumericalStringError::TooPrecise => write!(f, "too many decimal places"),
+            InvalidNumericalStringError::OutOfRange => write!(f, "out of range"),
+        }
+    }
+}
+
+/// Parses an amount with the default [`AmountPolicy`], i.e. a decimal number rounded half to even
+/// to 4 decimal places.
 impl TryFrom<&str> for Amount {
     type Error = InvalidNumericalStringError;
 
     fn try_from(value: &str) -> Result<Self, Self::Error> {
-        match Decimal::try_from(value) {
-            Ok(result) => Ok(Self::new(result)),
-            Err(_) => Err(InvalidNumericalStringError),
-        }
+        AmountPolicy::default().parse(value)
     }
 }
 
+fn parse_decimal(value: &str) -> Result<Decimal, InvalidNumericalStringError> {
+    Decimal::try_from(value).map_err(|_| {
+        // Well formed numbers are rejected by the parser only when they are too large.
+        if is_plain_number(value) {
+            InvalidNumericalStringError::OutOfRange
+        } else {
+            InvalidNumericalStringError::Malformed
+        }
+    })
+}
+
+/// Whether the value consists only of an optional sign, digits and at most a single dot.
+fn is_plain_number(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value);
+    digits.chars().any(|c| c.is_ascii_digit())
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.' || c == '_')
+        && digits.matches('.').count() <= 1
+}
+
 macro_rules! impl_binary_op {
     ( $op: ty, $method: ident ) => {
         impl $op for Amount {
             type Output = Self;
 
             fn $method(self, rhs: Self) -> Self {
-                Self::new(self.value.$method(rhs.value))
+                let decimal_places = self.result_decimal_places(&rhs);
+                Self::new(self.value.$method(rhs.value), decimal_places)
             }
         }
     };
 }
 
-macro_rules! impl_unary_op {
-    ( $op: ty, $method: ident ) => {
+macro_rules! impl_assign_op {
+    ( $op: ty, $method: ident, $binary_op: path, $binary_method: ident ) => {
         impl $op for Amount {
             fn $method(&mut self, rhs: Self) {
-                self.value.$method(rhs.value);
+                let lhs = std::mem::take(self);
+                *self = <Self as $binary_op>::$binary_method(lhs, rhs);
             }
         }
     };
@@ -85,15 +328,35 @@ impl_binary_op!(std::ops::Add, add);
 impl_binary_op!(std::ops::Sub, sub);
 impl_binary_op!(std::ops::Mul, mul);
 impl_binary_op!(std::ops::Div, div);
-impl_unary_op!(std::ops::AddAssign, add_assign);
-impl_unary_op!(std::ops::SubAssign, sub_assign);
-impl_unary_op!(std::ops::MulAssign, mul_assign);
-impl_unary_op!(std::ops::DivAssign, div_assign);
+impl_assign_op!(std::ops::AddAssign, add_assign, std::ops::Add, add);
+impl_assign_op!(std::ops::SubAssign, sub_assign, std::ops::Sub, sub);
+impl_assign_op!(std::ops::MulAssign, mul_assign, std::ops::Mul, mul);
+impl_assign_op!(std::ops::DivAssign, div_assign, std::ops::Div, div);
 
 impl std::ops::Neg for Amount {
     type Output = Self;
 
+    /// Zero stays positive, so that it is never displayed as `-0`.
     fn neg(self) -> Self {
-        Self::new(self.value.neg())
+        if self.value.is_zero() {
+            return self;
+        }
+        Self { value: -self.value }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Amount {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Amount {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Amount::parse_exact(value.as_ref())
+            .map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(&value), &"decimal string"))
     }
 }
@@ -1,7 +1,8 @@
 use std::num::ParseIntError;
 
-/// Represents a client id.
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+/// Represents a client id. Serialized as a plain number.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
 #[repr(transparent)]
 #[must_use]
 pub struct ClientId {
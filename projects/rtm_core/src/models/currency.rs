@@ -0,0 +1,100 @@
+/// ISO 4217 currency code, e.g. `EUR`. Serialized as the code string.
+///
+/// Amounts without a currency are in [`Currency::UNSPECIFIED`], which is also the default.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[must_use]
+pub struct Currency {
+    code: [u8; 3],
+}
+
+impl Currency {
+    /// `XXX`, the ISO 4217 code for transactions involving no currency.
+    pub const UNSPECIFIED: Currency = Currency { code: *b"XXX" };
+
+    /// Smallest and largest valid codes, bounding ranges of currencies.
+    pub(crate) const MIN: Currency = Currency { code: *b"AAA" };
+    pub(crate) const MAX: Currency = Currency { code: *b"ZZZ" };
+
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        // Codes are validated to be ascii letters on construction.
+        std::str::from_utf8(&self.code).unwrap_or_default()
+    }
+
+    #[must_use]
+    pub const fn as_bytes(&self) -> [u8; 3] {
+        self.code
+    }
+
+    #[must_use]
+    pub fn is_unspecified(&self) -> bool {
+        *self == Currency::UNSPECIFIED
+    }
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Currency::UNSPECIFIED
+    }
+}
+
+impl std::fmt::Debug for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Currency").field(&self.as_str()).finish()
+    }
+}
+
+impl std::fmt::Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Value is not a currency code consisting of three ascii letters.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct InvalidCurrencyError;
+
+impl std::fmt::Display for InvalidCurrencyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "not an ISO 4217 currency code")
+    }
+}
+
+impl TryFrom<[u8; 3]> for Currency {
+    type Error = InvalidCurrencyError;
+
+    fn try_from(value: [u8; 3]) -> Result<Self, Self::Error> {
+        if !value.iter().all(u8::is_ascii_alphabetic) {
+            return Err(InvalidCurrencyError);
+        }
+        Ok(Self {
+            code: value.map(|byte| byte.to_ascii_uppercase()),
+        })
+    }
+}
+
+/// Parses the code case-insensitively, e.g. `"eur"` is [`Currency`] `EUR`.
+impl TryFrom<&str> for Currency {
+    type Error = InvalidCurrencyError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let code = <[u8; 3]>::try_from(value.as_bytes()).map_err(|_| InvalidCurrencyError)?;
+        Self::try_from(code)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Currency {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Currency {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        Currency::try_from(value.as_ref())
+            .map_err(|_| serde::de::Error::invalid_value(serde::de::Unexpected::Str(&value), &"currency code"))
+    }
+}
@@ -1,12 +1,18 @@
 mod amount;
 pub use amount::*;
 
+mod currency;
+pub use currency::*;
+
 mod client_id;
 pub use client_id::*;
 
 mod transaction_id;
 pub use transaction_id::*;
 
+mod timestamp;
+pub use timestamp::*;
+
 mod transaction;
 pub use transaction::*;
 
@@ -0,0 +1,44 @@
+use std::{num::ParseIntError, time::Duration};
+
+/// Point in time of an operation, in whole seconds since the Unix epoch. Serialized as a plain number.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(transparent))]
+#[repr(transparent)]
+#[must_use]
+pub struct Timestamp {
+    seconds: u64,
+}
+
+impl Timestamp {
+    #[must_use]
+    pub const fn as_secs(&self) -> u64 {
+        self.seconds
+    }
+
+    /// Time elapsed from `earlier` to this timestamp, zero if `earlier` is later.
+    #[must_use]
+    pub const fn duration_since(self, earlier: Timestamp) -> Duration {
+        Duration::from_secs(self.seconds.saturating_sub(earlier.seconds))
+    }
+}
+
+impl From<u64> for Timestamp {
+    fn from(seconds: u64) -> Self {
+        Self { seconds }
+    }
+}
+
+impl TryFrom<&str> for Timestamp {
+    type Error = ParseIntError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let seconds = value.parse::<u64>()?;
+        Ok(Self { seconds })
+    }
+}
+
+impl std::fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.seconds)
+    }
+}
@@ -1,6 +1,12 @@
-use super::{Amount, ClientId, TransactionId};
+use super::{Amount, ClientId, Currency, Timestamp, TransactionId};
 
+/// Kind of a [`Transaction`]. Serialized as `"deposit"` or `"withdrawal"`.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[must_use]
 pub enum TransactionKind {
     Deposit,
@@ -8,25 +14,55 @@ pub enum TransactionKind {
 }
 
 /// Represents an accounting operation that deals with the actual money.
-#[derive(Debug)]
+///
+/// Serialized with the same field names as the csv columns, e.g.
+/// `{"client": 1, "tx": 1, "amount": "1.5000", "currency": "EUR", "type": "deposit", "timestamp": 1700000000}`,
+/// where `currency` is omitted when unspecified and `timestamp` when unknown.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[must_use]
 pub struct Transaction {
+    #[cfg_attr(feature = "serde", serde(rename = "client"))]
     client_id: ClientId,
+    #[cfg_attr(feature = "serde", serde(rename = "tx"))]
     id: TransactionId,
     amount: Amount,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+    currency: Currency,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
     kind: TransactionKind,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
+    timestamp: Option<Timestamp>,
 }
 
 impl Transaction {
+    /// Transaction in [`Currency::UNSPECIFIED`].
     pub const fn new(client_id: ClientId, id: TransactionId, amount: Amount, kind: TransactionKind) -> Self {
+        Self::with_currency(client_id, id, amount, Currency::UNSPECIFIED, kind)
+    }
+
+    pub const fn with_currency(
+        client_id: ClientId,
+        id: TransactionId,
+        amount: Amount,
+        currency: Currency,
+        kind: TransactionKind,
+    ) -> Self {
         Self {
             client_id,
             id,
             amount,
+            currency,
             kind,
+            timestamp: None,
         }
     }
 
+    /// Same transaction, made at the given time if known.
+    pub fn with_timestamp(self, timestamp: Option<Timestamp>) -> Self {
+        Self { timestamp, ..self }
+    }
+
     pub const fn id(&self) -> TransactionId {
         self.id
     }
@@ -42,4 +78,13 @@ impl Transaction {
     pub const fn amount(&self) -> &Amount {
         &self.amount
     }
+
+    pub const fn currency(&self) -> Currency {
+        self.currency
+    }
+
+    #[must_use]
+    pub const fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
 }
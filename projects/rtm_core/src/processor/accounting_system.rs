@@ -1,25 +1,86 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ops::RangeInclusive,
+    time::Duration,
+};
 
 use crate::{
-    models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind},
+    models::{
+        AccountingOperation, Amount, AmountPolicy, ClientId, Currency, InvalidNumericalStringError, Timestamp,
+        Transaction, TransactionId, TransactionKind,
+    },
     processor::ClientAccountState,
 };
 
-use super::{ClientAccount, TransactionError};
+use super::{
+    AccountingEvent, AccountingSystemConfig, BalanceChange, ClientAccount, EventSink, OverdraftPolicy,
+    TransactionError, TransactionIdSet,
+};
 
 #[derive(Debug)]
 #[must_use]
 pub struct AccountingSystem {
-    client_accounts: HashMap<ClientId, ClientAccount>,
-    seen_transactions: HashSet<TransactionId>,
+    pub(super) config: AccountingSystemConfig,
+    /// Accounts of every client in every currency it used.
+    pub(super) client_accounts: BTreeMap<(ClientId, Currency), ClientAccount>,
+    pub(super) seen_transactions: TransactionIdSet,
 }
 
 impl AccountingSystem {
     pub fn new() -> Self {
+        Self::with_config(AccountingSystemConfig::default())
+    }
+
+    pub fn with_config(config: AccountingSystemConfig) -> Self {
         Self {
-            client_accounts: HashMap::new(),
-            seen_transactions: HashSet::new(),
+            config,
+            client_accounts: BTreeMap::new(),
+            seen_transactions: TransactionIdSet::new(),
+        }
+    }
+
+    /// System tracking the given accounts, e.g. deserialized ones, with their retained transactions
+    /// marked as processed. Retained transactions are dropped from the oldest within the dispute
+    /// window of the `config`, like the ones of a restored snapshot. Balances are taken as they are,
+    /// [`AccountingSystem::verify_invariants`] checks whether they are consistent.
+    ///
+    /// # Errors
+    ///
+    /// When two accounts are of the same client and currency, or retain the same transaction.
+    pub fn with_accounts(
+        config: AccountingSystemConfig,
+        accounts: impl IntoIterator<Item = ClientAccount>,
+    ) -> Result<Self, String> {
+        let mut accounting_system = Self::with_config(config);
+        let ordered = accounting_system.config.dispute_window.is_some();
+        for mut account in accounts {
+            for transaction_id in account.transactions.keys() {
+                if !accounting_system.seen_transactions.insert(*transaction_id) {
+                    return Err("transaction retained by more than one account".to_string());
+                }
+            }
+            account.retention_order = if ordered {
+                account
+                    .retained_transactions()
+                    .into_iter()
+                    .map(Transaction::id)
+                    .collect()
+            } else {
+                VecDeque::new()
+            };
+            if accounting_system
+                .client_accounts
+                .insert((account.client_id, account.currency), account)
+                .is_some()
+            {
+                return Err("duplicate client account".to_string());
+            }
         }
+        Ok(accounting_system)
+    }
+
+    pub const fn config(&self) -> &AccountingSystemConfig {
+        &self.config
     }
 
     /// Runs a transaction over the existing accounting system state.
@@ -27,18 +88,65 @@ impl AccountingSystem {
     /// # Errors
     ///
     /// For especific errors see [`TransactionError`].
-    #[allow(clippy::missing_panics_doc)]
     pub fn run_operation(&mut self, operation: AccountingOperation) -> Result<(), TransactionError> {
+        self.run_operation_with_sink(operation, &mut ())
+    }
+
+    /// Same as [`AccountingSystem::run_operation`], additionally publishing events describing
+    /// the state changes to `sink`. Rejected operations do not change the state, and so they
+    /// do not publish any events.
+    ///
+    /// # Errors
+    ///
+    /// For especific errors see [`TransactionError`].
+    pub fn run_operation_with_sink(
+        &mut self,
+        operation: AccountingOperation,
+        sink: &mut impl EventSink,
+    ) -> Result<(), TransactionError> {
         let client_id = operation.client_id();
+        let state = self.iter_client_accounts(client_id).next().map(|account| account.state);
+        match operation {
+            AccountingOperation::Unlock { reason, .. } => return self.unlock(client_id, state, reason, sink),
+            AccountingOperation::Freeze { reason, .. } => return self.freeze(client_id, state, reason, sink),
+            AccountingOperation::Close { reason, .. } => return self.close(client_id, state, reason, sink),
+            _ => {}
+        }
+        if let Some(error) = state.and_then(|state| state_error(client_id, state)) {
+            // Disputes opened before a chargeback can still be settled, otherwise their funds stay held forever.
+            let settles_dispute = state == Some(ClientAccountState::Locked) && self.settles_open_dispute(&operation);
+            if !settles_dispute {
+                return Err(error);
+            }
+        }
+
+        let currency = self.operation_currency(&operation)?;
+        // Rejected operations must not leave an empty account of the client behind.
+        let key = (client_id, currency);
+        let created = !self.client_accounts.contains_key(&key);
+        let result = self.apply_operation(operation, state, currency, sink);
+        if created && result.is_err() {
+            self.client_accounts.remove(&key);
+        }
+        result
+    }
 
+    /// Applies a transaction, dispute, resolve or chargeback to the account of its client in `currency`,
+    /// creating the account if needed.
+    #[allow(clippy::too_many_lines)]
+    fn apply_operation(
+        &mut self,
+        operation: AccountingOperation,
+        state: Option<ClientAccountState>,
+        currency: Currency,
+        sink: &mut impl EventSink,
+    ) -> Result<(), TransactionError> {
+        let client_id = operation.client_id();
+        let amount_policy = self.config.amount_policies.of(currency);
         let client_account = self
             .client_accounts
-            .entry(client_id)
-            .or_insert_with(|| ClientAccount::new(client_id));
-
-        if client_account.state == ClientAccountState::Locked {
-            return Err(TransactionError::AccountLocked { client_id: client_id });
-        }
+            .entry((client_id, currency))
+            .or_insert_with(|| ClientAccount::with_currency(client_id, currency, amount_policy));
 
         macro_rules! referred_transaction {
             ($transaction_id: expr) => {{
@@ -55,8 +163,21 @@ impl AccountingSystem {
 
         match operation {
             AccountingOperation::Transaction { transaction } => {
+                let transaction = round_amount(&transaction, amount_policy)?;
+                if !transaction.amount().is_positive() {
+                    return Err(TransactionError::NonPositiveAmount {
+                        cause_id: transaction.id(),
+                    });
+                }
                 let amount = normalize_amount(&transaction);
-                let new_amount = client_account.available_balance.clone() + amount;
+                let overflow = TransactionError::ArithmeticOverflow {
+                    cause_id: transaction.id(),
+                };
+                let new_amount = client_account.available_balance.checked_add(&amount).ok_or(overflow)?;
+                // The total balance has to stay representable as well.
+                if new_amount.checked_add(&client_account.held_balance).is_none() {
+                    return Err(overflow);
+                }
                 if new_amount < Amount::zero() {
                     return Err(TransactionError::InsufficientFunds {
                         cause_id: transaction.id(),
@@ -64,19 +185,45 @@ impl AccountingSystem {
                 }
 
                 let transaction_id = transaction.id();
-                if self.seen_transactions.contains(&transaction_id) {
+                if !self.seen_transactions.insert(transaction_id) {
                     return Err(TransactionError::DuplicateTransaction {
                         cause_id: transaction_id,
                     });
                 }
-                self.seen_transactions.insert(transaction_id);
 
+                let before = client_account.balances();
                 client_account.available_balance = new_amount;
-                client_account.transactions.insert(transaction_id, transaction);
+                let balances = BalanceChange {
+                    before,
+                    after: client_account.balances(),
+                };
+                let amount = transaction.amount().clone();
+                let event = match transaction.kind() {
+                    TransactionKind::Deposit => AccountingEvent::Deposited {
+                        client_id,
+                        transaction_id,
+                        amount,
+                        currency,
+                        balances,
+                    },
+                    TransactionKind::Withdrawal => AccountingEvent::Withdrawn {
+                        client_id,
+                        transaction_id,
+                        amount,
+                        currency,
+                        balances,
+                    },
+                };
+                if self.config.dispute_policy.allows(transaction.kind()) {
+                    client_account.retain_transaction(transaction, self.config.dispute_window);
+                }
+                sink.publish(event);
             }
             AccountingOperation::Dispute {
                 client_id,
                 ref_id: transaction_id,
+                timestamp,
+                ..
             } => {
                 let referred_transaction = referred_transaction!(transaction_id);
 
@@ -84,59 +231,339 @@ impl AccountingSystem {
                     return Err(TransactionError::CrossClientTransaction);
                 }
 
-                if !client_account.disputed_transactions.insert(transaction_id) {
+                if !self.config.dispute_policy.allows(referred_transaction.kind()) {
+                    return Err(TransactionError::TransactionNotDisputable { ref_id: transaction_id });
+                }
+
+                if client_account.disputed_transactions.contains(&transaction_id)
+                    || client_account.charged_back_transactions.contains(&transaction_id)
+                {
                     return Err(TransactionError::TransactionAlreadyDisputed { ref_id: transaction_id });
                 }
 
-                let amount = normalize_amount(referred_transaction);
+                if expired(
+                    self.config.dispute_time_limit,
+                    referred_transaction.timestamp(),
+                    timestamp,
+                ) {
+                    return Err(TransactionError::DisputeWindowExpired { ref_id: transaction_id });
+                }
 
-                client_account.held_balance += amount.clone();
-                client_account.available_balance -= amount;
+                let kind = referred_transaction.kind();
+                let amount = dispute_hold(
+                    self.config.overdraft_policy,
+                    &client_account.available_balance,
+                    referred_transaction,
+                    amount_policy,
+                )?;
+                let (held_change, available_change) = dispute_changes(kind, &amount, amount_policy);
+                let (held, available) =
+                    client_account.moved_balances(&held_change, &available_change, transaction_id)?;
+
+                let before = client_account.balances();
+                if amount != *referred_transaction.amount() {
+                    client_account.partial_holds.insert(transaction_id, amount.clone());
+                }
+                if let Some(timestamp) = timestamp {
+                    client_account.dispute_timestamps.insert(transaction_id, timestamp);
+                }
+                client_account.disputed_transactions.insert(transaction_id);
+                client_account.held_balance = held;
+                client_account.available_balance = available;
+                sink.publish(AccountingEvent::FundsHeld {
+                    client_id,
+                    transaction_id,
+                    amount,
+                    currency,
+                    kind,
+                    balances: BalanceChange {
+                        before,
+                        after: client_account.balances(),
+                    },
+                });
+                if kind == TransactionKind::Deposit && client_account.is_overdrawn() {
+                    sink.publish(AccountingEvent::AccountOverdrawn {
+                        client_id,
+                        transaction_id,
+                        currency,
+                    });
+                }
             }
             AccountingOperation::Resolve {
                 client_id,
                 ref_id: transaction_id,
+                ..
             } => {
                 let referred_transaction = referred_transaction!(transaction_id);
                 if client_id != referred_transaction.client_id() {
                     return Err(TransactionError::CrossClientTransaction);
                 }
 
-                if !client_account.disputed_transactions.remove(&transaction_id) {
+                if !client_account.disputed_transactions.contains(&transaction_id) {
                     return Err(TransactionError::TransactionNotDisputed { ref_id: transaction_id });
                 }
 
-                let amount = normalize_amount(referred_transaction);
+                let kind = referred_transaction.kind();
+                let amount = client_account.held_amount(referred_transaction);
+                let (held_change, available_change) = dispute_changes(kind, &amount, amount_policy);
+                let (held, available) =
+                    client_account.moved_balances(&-held_change, &-available_change, transaction_id)?;
 
-                client_account.held_balance -= amount.clone();
-                client_account.available_balance += amount;
+                let before = client_account.balances();
+                client_account.partial_holds.remove(&transaction_id);
+                client_account.dispute_timestamps.remove(&transaction_id);
+                client_account.disputed_transactions.remove(&transaction_id);
+                client_account.held_balance = held;
+                client_account.available_balance = available;
+                sink.publish(AccountingEvent::FundsReleased {
+                    client_id,
+                    transaction_id,
+                    amount,
+                    currency,
+                    kind,
+                    balances: BalanceChange {
+                        before,
+                        after: client_account.balances(),
+                    },
+                });
             }
             AccountingOperation::Chargeback {
                 client_id,
                 ref_id: transaction_id,
+                timestamp,
+                ..
             } => {
                 let referred_transaction = referred_transaction!(transaction_id);
                 if client_id != referred_transaction.client_id() {
                     return Err(TransactionError::CrossClientTransaction);
                 }
 
-                if !client_account.disputed_transactions.remove(&transaction_id) {
+                if !client_account.disputed_transactions.contains(&transaction_id) {
                     return Err(TransactionError::TransactionNotDisputed { ref_id: transaction_id });
                 }
 
-                let amount = normalize_amount(referred_transaction);
+                let disputed_at = client_account.dispute_timestamps.get(&transaction_id).copied();
+                if expired(self.config.chargeback_time_limit, disputed_at, timestamp) {
+                    return Err(TransactionError::DisputeWindowExpired { ref_id: transaction_id });
+                }
+
+                let kind = referred_transaction.kind();
+                let amount = client_account.held_amount(referred_transaction);
+                // Reversing a withdrawal returns the held amount to the client.
+                let available_change = match kind {
+                    TransactionKind::Deposit => amount_policy.zero(),
+                    TransactionKind::Withdrawal => amount.clone(),
+                };
+                let (held, available) =
+                    client_account.moved_balances(&-amount.clone(), &available_change, transaction_id)?;
 
-                client_account.held_balance -= amount.clone();
-                client_account.state = ClientAccountState::Locked;
+                let before = client_account.balances();
+                client_account.partial_holds.remove(&transaction_id);
+                client_account.dispute_timestamps.remove(&transaction_id);
+                client_account.disputed_transactions.remove(&transaction_id);
+                client_account.held_balance = held;
+                client_account.available_balance = available;
+                client_account.charged_back_transactions.insert(transaction_id);
+                sink.publish(AccountingEvent::ChargedBack {
+                    client_id,
+                    transaction_id,
+                    amount,
+                    currency,
+                    kind,
+                    balances: BalanceChange {
+                        before,
+                        after: client_account.balances(),
+                    },
+                });
+                // The whole client is locked, in all currencies, unless it already was by an earlier chargeback.
+                if state != Some(ClientAccountState::Locked) {
+                    for account in self.client_accounts_mut(client_id) {
+                        account.state = ClientAccountState::Locked;
+                    }
+                    sink.publish(AccountingEvent::AccountLocked {
+                        client_id,
+                        transaction_id,
+                    });
+                }
             }
+            AccountingOperation::Unlock { .. }
+            | AccountingOperation::Freeze { .. }
+            | AccountingOperation::Close { .. } => unreachable!("administrative operations are applied above"),
+        }
+        Ok(())
+    }
+
+    /// Returns a locked or frozen client to normal.
+    fn unlock(
+        &mut self,
+        client_id: ClientId,
+        state: Option<ClientAccountState>,
+        reason: String,
+        sink: &mut impl EventSink,
+    ) -> Result<(), TransactionError> {
+        match state {
+            None => return Err(TransactionError::ClientDoesNotExist { client_id }),
+            Some(ClientAccountState::Normal) => return Err(TransactionError::AccountNotLocked { client_id }),
+            Some(ClientAccountState::Closed) => return Err(TransactionError::AccountClosed { client_id }),
+            Some(ClientAccountState::Locked | ClientAccountState::Frozen) => {}
+        }
+        for account in self.client_accounts_mut(client_id) {
+            account.state = ClientAccountState::Normal;
+        }
+        sink.publish(AccountingEvent::AccountUnlocked { client_id, reason });
+        Ok(())
+    }
+
+    /// Locks a client in normal state until it is unlocked.
+    fn freeze(
+        &mut self,
+        client_id: ClientId,
+        state: Option<ClientAccountState>,
+        reason: String,
+        sink: &mut impl EventSink,
+    ) -> Result<(), TransactionError> {
+        let state = state.ok_or(TransactionError::ClientDoesNotExist { client_id })?;
+        if let Some(error) = state_error(client_id, state) {
+            return Err(error);
+        }
+        for account in self.client_accounts_mut(client_id) {
+            account.state = ClientAccountState::Frozen;
+        }
+        sink.publish(AccountingEvent::AccountFrozen { client_id, reason });
+        Ok(())
+    }
+
+    /// Closes a client that has no funds or open disputes in any currency.
+    fn close(
+        &mut self,
+        client_id: ClientId,
+        state: Option<ClientAccountState>,
+        reason: String,
+        sink: &mut impl EventSink,
+    ) -> Result<(), TransactionError> {
+        match state {
+            None => return Err(TransactionError::ClientDoesNotExist { client_id }),
+            Some(ClientAccountState::Closed) => return Err(TransactionError::AccountClosed { client_id }),
+            Some(_) => {}
         }
+        if !self.iter_client_accounts(client_id).all(ClientAccount::is_settled) {
+            return Err(TransactionError::AccountNotSettled { client_id });
+        }
+        for account in self.client_accounts_mut(client_id) {
+            account.close();
+        }
+        sink.publish(AccountingEvent::AccountClosed { client_id, reason });
         Ok(())
     }
 
-    /// Iterates over all currently tracked client accounts.
+    /// Returns account of the given client in the currency, if it is tracked.
+    #[must_use]
+    pub fn account(&self, client_id: ClientId, currency: Currency) -> Option<&ClientAccount> {
+        self.client_accounts.get(&(client_id, currency))
+    }
+
+    /// Iterates over all currently tracked client accounts, ordered by client id and currency.
     pub fn iter_accounts(&self) -> impl Iterator<Item = &ClientAccount> {
         self.client_accounts.values()
     }
+
+    /// Iterates over accounts of the client in all currencies, ordered by currency.
+    pub fn iter_client_accounts(&self, client_id: ClientId) -> impl Iterator<Item = &ClientAccount> {
+        self.client_accounts
+            .range(client_range(client_id))
+            .map(|(_, account)| account)
+    }
+
+    fn client_accounts_mut(&mut self, client_id: ClientId) -> impl Iterator<Item = &mut ClientAccount> {
+        self.client_accounts
+            .range_mut(client_range(client_id))
+            .map(|(_, account)| account)
+    }
+
+    /// Whether the operation resolves or charges back a transaction currently disputed by its client.
+    fn settles_open_dispute(&self, operation: &AccountingOperation) -> bool {
+        match operation {
+            AccountingOperation::Resolve { client_id, ref_id, .. }
+            | AccountingOperation::Chargeback { client_id, ref_id, .. } => self
+                .iter_client_accounts(*client_id)
+                .any(|account| account.disputed_transactions.contains(ref_id)),
+            _ => false,
+        }
+    }
+
+    /// Currency of the account the operation applies to. Disputes, resolves and chargebacks
+    /// apply to the account retaining the referred transaction, which has to be in the
+    /// expected currency, if any.
+    fn operation_currency(&self, operation: &AccountingOperation) -> Result<Currency, TransactionError> {
+        let (client_id, ref_id, expected) = match operation {
+            AccountingOperation::Transaction { transaction } => return Ok(transaction.currency()),
+            AccountingOperation::Dispute {
+                client_id,
+                ref_id,
+                currency,
+                ..
+            }
+            | AccountingOperation::Resolve {
+                client_id,
+                ref_id,
+                currency,
+                ..
+            }
+            | AccountingOperation::Chargeback {
+                client_id,
+                ref_id,
+                currency,
+                ..
+            } => (*client_id, *ref_id, *currency),
+            AccountingOperation::Unlock { .. }
+            | AccountingOperation::Freeze { .. }
+            | AccountingOperation::Close { .. } => return Ok(Currency::UNSPECIFIED),
+        };
+        let retaining = self
+            .iter_client_accounts(client_id)
+            .find(|account| account.transactions.contains_key(&ref_id))
+            .map(|account| account.currency);
+        match (expected, retaining) {
+            (Some(expected), Some(actual)) if expected != actual => Err(TransactionError::CurrencyMismatch {
+                ref_id,
+                expected,
+                actual,
+            }),
+            _ => Ok(retaining.or(expected).unwrap_or_default()),
+        }
+    }
+
+    /// Makes future transactions with given id fail as duplicates.
+    pub(super) fn mark_seen(&mut self, transaction_id: TransactionId) {
+        self.seen_transactions.insert(transaction_id);
+    }
+
+    /// Splits clients between `parts` systems, by client id modulo `parts`. Returned systems
+    /// do not track any processed transactions, these are returned separately instead.
+    pub(super) fn split(self, parts: usize) -> (Vec<AccountingSystem>, TransactionIdSet) {
+        let mut result = (0..parts)
+            .map(|_| AccountingSystem::with_config(self.config.clone()))
+            .collect::<Vec<_>>();
+        for ((client_id, currency), account) in self.client_accounts {
+            let part = usize::from(client_id.as_u16()) % parts;
+            result[part].client_accounts.insert((client_id, currency), account);
+        }
+        (result, self.seen_transactions)
+    }
+
+    /// Builds a single system out of systems holding disjoint sets of clients.
+    pub(super) fn merge(
+        config: AccountingSystemConfig,
+        parts: impl IntoIterator<Item = AccountingSystem>,
+        seen_transactions: TransactionIdSet,
+    ) -> Self {
+        let client_accounts = parts.into_iter().flat_map(|part| part.client_accounts).collect();
+        Self {
+            config,
+            client_accounts,
+            seen_transactions,
+        }
+    }
 }
 
 impl Default for AccountingSystem {
@@ -145,6 +572,81 @@ impl Default for AccountingSystem {
     }
 }
 
+/// Applies the [`AmountPolicy`] to the amount of the transaction.
+fn round_amount(transaction: &Transaction, amount_policy: AmountPolicy) -> Result<Transaction, TransactionError> {
+    let cause_id = transaction.id();
+    let amount = amount_policy.apply(transaction.amount()).map_err(|err| match err {
+        InvalidNumericalStringError::TooPrecise => TransactionError::ExcessivePrecision { cause_id },
+        InvalidNumericalStringError::Malformed | InvalidNumericalStringError::OutOfRange => {
+            TransactionError::ArithmeticOverflow { cause_id }
+        }
+    })?;
+    Ok(Transaction::with_currency(
+        transaction.client_id(),
+        cause_id,
+        amount,
+        transaction.currency(),
+        transaction.kind(),
+    )
+    .with_timestamp(transaction.timestamp()))
+}
+
+/// Error of deposits, withdrawals and disputes rejected because of the state of the client, if any.
+const fn state_error(client_id: ClientId, state: ClientAccountState) -> Option<TransactionError> {
+    match state {
+        ClientAccountState::Normal => None,
+        ClientAccountState::Locked => Some(TransactionError::AccountLocked { client_id }),
+        ClientAccountState::Frozen => Some(TransactionError::AccountFrozen { client_id }),
+        ClientAccountState::Closed => Some(TransactionError::AccountClosed { client_id }),
+    }
+}
+
+/// Whether more than `limit` passed from `start` to `end`. Never true if any of them is unknown.
+fn expired(limit: Option<Duration>, start: Option<Timestamp>, end: Option<Timestamp>) -> bool {
+    match (limit, start, end) {
+        (Some(limit), Some(start), Some(end)) => end.duration_since(start) > limit,
+        _ => false,
+    }
+}
+
+fn client_range(client_id: ClientId) -> RangeInclusive<(ClientId, Currency)> {
+    (client_id, Currency::MIN)..=(client_id, Currency::MAX)
+}
+
+/// Amount held by disputing the transaction. Withdrawals are always held in full, while
+/// deposits exceeding the available funds are handled according to the `overdraft_policy`.
+/// Nothing held keeps the decimal places of the `amount_policy`.
+fn dispute_hold(
+    overdraft_policy: OverdraftPolicy,
+    available: &Amount,
+    referred_transaction: &Transaction,
+    amount_policy: AmountPolicy,
+) -> Result<Amount, TransactionError> {
+    let amount = referred_transaction.amount();
+    if referred_transaction.kind() == TransactionKind::Withdrawal || amount <= available {
+        return Ok(amount.clone());
+    }
+    match overdraft_policy {
+        OverdraftPolicy::Allow => Ok(amount.clone()),
+        OverdraftPolicy::Reject => Err(TransactionError::InsufficientFundsForDispute {
+            ref_id: referred_transaction.id(),
+        }),
+        OverdraftPolicy::CapHold if *available > Amount::zero() => Ok(available.clone()),
+        OverdraftPolicy::CapHold => Ok(amount_policy.zero()),
+    }
+}
+
+/// Changes of held and available funds caused by holding the amount of a disputed transaction,
+/// reverted by its resolve. A disputed deposit is moved from available to held funds, while
+/// the amount of a disputed withdrawal is held on top of the available funds. Unchanged funds
+/// keep the decimal places of the `amount_policy`.
+fn dispute_changes(kind: TransactionKind, amount: &Amount, amount_policy: AmountPolicy) -> (Amount, Amount) {
+    match kind {
+        TransactionKind::Deposit => (amount.clone(), -amount.clone()),
+        TransactionKind::Withdrawal => (amount.clone(), amount_policy.zero()),
+    }
+}
+
 fn normalize_amount(referred_transaction: &Transaction) -> Amount {
     let amount = referred_transaction.amount().clone();
     match referred_transaction.kind() {
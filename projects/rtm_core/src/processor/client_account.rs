@@ -1,35 +1,443 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::models::{Amount, ClientId, Transaction, TransactionId};
+use crate::models::{Amount, AmountPolicy, ClientId, Currency, Timestamp, Transaction, TransactionId};
 
+use super::{Balances, TransactionError};
+
+/// State of a [`ClientAccount`]. Serialized as `"normal"`, `"locked"`, `"frozen"` or `"closed"`.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
 #[must_use]
 pub enum ClientAccountState {
     #[default]
     Normal,
+
+    /// Locked by a chargeback, until unlocked by an administrator.
     Locked,
+
+    /// Locked by an administrator, until unlocked by an administrator.
+    Frozen,
+
+    /// Closed by an administrator for good.
+    Closed,
+}
+
+impl ClientAccountState {
+    /// Whether deposits, withdrawals and disputes are rejected, i.e. the state is not [`ClientAccountState::Normal`].
+    /// Disputes opened before a chargeback can still be resolved or charged back while [`ClientAccountState::Locked`].
+    #[must_use]
+    pub const fn is_locked(self) -> bool {
+        !matches!(self, ClientAccountState::Normal)
+    }
 }
 
-#[derive(Debug)]
+impl std::fmt::Display for ClientAccountState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientAccountState::Normal => write!(f, "normal"),
+            ClientAccountState::Locked => write!(f, "locked"),
+            ClientAccountState::Frozen => write!(f, "frozen"),
+            ClientAccountState::Closed => write!(f, "closed"),
+        }
+    }
+}
+
+/// Balances and state of a [`ClientAccount`], detached from the accounting system.
+///
+/// Serialized as `{"client": 1, "available": "1.5000", "held": "0.0000", "total": "1.5000", "locked": false}`,
+/// with an additional `currency` field for accounts in a currency, `"overdrawn": true` for overdrawn
+/// accounts and `state` for frozen or closed accounts. This is the json representation of accounts
+/// in all outputs.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        into = "serde_repr::ClientAccountSnapshotRepr",
+        try_from = "serde_repr::ClientAccountSnapshotRepr"
+    )
+)]
+#[must_use]
+pub struct ClientAccountSnapshot {
+    pub client_id: ClientId,
+    pub currency: Currency,
+    pub available_balance: Amount,
+    pub held_balance: Amount,
+    pub state: ClientAccountState,
+}
+
+impl ClientAccountSnapshot {
+    pub fn total_balance(&self) -> Amount {
+        self.available_balance.clone() + self.held_balance.clone()
+    }
+
+    /// Whether available funds are negative, e.g. after a dispute of an already withdrawn deposit.
+    #[must_use]
+    pub fn is_overdrawn(&self) -> bool {
+        self.available_balance < Amount::zero()
+    }
+}
+
+/// Account of a single client in a single currency, together with the transactions in
+/// that currency retained for disputes. All accounts of a client share the same state.
+///
+/// Serialized like its [`ClientAccountSnapshot`], with additional fields: `transactions`,
+/// holding the retained [`Transaction`]s sorted by id, `disputed`, holding
+/// sorted ids of the currently disputed transactions, `partially_held`, holding disputes
+/// with capped holds as `{"tx": 1, "held": "0.5000"}`, and `dispute_timestamps`, holding
+/// times of disputes as `{"tx": 1, "timestamp": 1700000000}`. The last two are omitted when empty.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "serde_repr::ClientAccountRepr", try_from = "serde_repr::ClientAccountRepr")
+)]
 #[must_use]
 pub struct ClientAccount {
     pub client_id: ClientId,
+    pub currency: Currency,
     pub available_balance: Amount,
     pub held_balance: Amount,
     pub state: ClientAccountState,
     pub(super) transactions: HashMap<TransactionId, Transaction>,
     pub(super) disputed_transactions: HashSet<TransactionId>,
+    /// Held amounts of disputes holding less than their whole transaction.
+    pub(super) partial_holds: HashMap<TransactionId, Amount>,
+    /// Times of disputes that have a timestamp.
+    pub(super) dispute_timestamps: HashMap<TransactionId, Timestamp>,
+    /// Retained transactions reversed by a chargeback, which can not be disputed again.
+    pub(super) charged_back_transactions: HashSet<TransactionId>,
+    pub(super) retention_order: VecDeque<TransactionId>,
 }
 
 impl ClientAccount {
+    /// Empty account in [`Currency::UNSPECIFIED`].
     pub fn new(client_id: ClientId) -> Self {
+        Self::with_currency(client_id, Currency::UNSPECIFIED, AmountPolicy::default())
+    }
+
+    /// Empty account with zero balances having the decimal places of `amount_policy`.
+    pub fn with_currency(client_id: ClientId, currency: Currency, amount_policy: AmountPolicy) -> Self {
         Self {
             client_id,
-            available_balance: Amount::default(),
-            held_balance: Amount::default(),
+            currency,
+            available_balance: amount_policy.zero(),
+            held_balance: amount_policy.zero(),
             state: ClientAccountState::default(),
             transactions: HashMap::default(),
             disputed_transactions: HashSet::default(),
+            partial_holds: HashMap::default(),
+            dispute_timestamps: HashMap::default(),
+            charged_back_transactions: HashSet::default(),
+            retention_order: VecDeque::default(),
+        }
+    }
+
+    /// Whether available funds are negative, e.g. after a dispute of an already withdrawn deposit.
+    #[must_use]
+    pub fn is_overdrawn(&self) -> bool {
+        self.available_balance < Amount::zero()
+    }
+
+    pub fn snapshot(&self) -> ClientAccountSnapshot {
+        ClientAccountSnapshot {
+            client_id: self.client_id,
+            currency: self.currency,
+            available_balance: self.available_balance.clone(),
+            held_balance: self.held_balance.clone(),
+            state: self.state,
+        }
+    }
+
+    pub(super) fn balances(&self) -> Balances {
+        Balances {
+            available: self.available_balance.clone(),
+            held: self.held_balance.clone(),
+        }
+    }
+
+    /// Amount held by the dispute of the transaction, less than its whole amount for capped holds.
+    pub(super) fn held_amount(&self, transaction: &Transaction) -> Amount {
+        self.partial_holds
+            .get(&transaction.id())
+            .unwrap_or(transaction.amount())
+            .clone()
+    }
+
+    /// Held and available balances after adding the given changes, or an
+    /// [`TransactionError::ArithmeticOverflow`] caused by the referred transaction,
+    /// also when their total would not be representable.
+    pub(super) fn moved_balances(
+        &self,
+        held_change: &Amount,
+        available_change: &Amount,
+        transaction_id: TransactionId,
+    ) -> Result<(Amount, Amount), TransactionError> {
+        let held = self.held_balance.checked_add(held_change);
+        let available = self.available_balance.checked_add(available_change);
+        held.zip(available)
+            .filter(|(held, available)| held.checked_add(available).is_some())
+            .ok_or(TransactionError::ArithmeticOverflow {
+                cause_id: transaction_id,
+            })
+    }
+
+    /// Stores transaction for future disputes. With `dispute_window` set, the oldest
+    /// non-disputed transactions are dropped to keep at most `dispute_window` of them.
+    pub(super) fn retain_transaction(&mut self, transaction: Transaction, dispute_window: Option<usize>) {
+        let transaction_id = transaction.id();
+        self.transactions.insert(transaction_id, transaction);
+
+        let Some(dispute_window) = dispute_window else {
+            return;
+        };
+
+        self.retention_order.push_back(transaction_id);
+        let mut skipped = 0;
+        while self.transactions.len() > dispute_window && skipped < self.retention_order.len() {
+            let Some(oldest) = self.retention_order.pop_front() else {
+                break;
+            };
+            if self.disputed_transactions.contains(&oldest) {
+                self.retention_order.push_back(oldest);
+                skipped += 1;
+            } else {
+                self.transactions.remove(&oldest);
+                self.charged_back_transactions.remove(&oldest);
+            }
+        }
+    }
+
+    /// Retained transactions in a stable order: ones retained without a dispute window sorted
+    /// by id, followed by ones within the dispute window from the oldest.
+    pub(super) fn retained_transactions(&self) -> Vec<&Transaction> {
+        let ordered = self.retention_order.iter().collect::<HashSet<_>>();
+        let mut transactions = self
+            .transactions
+            .values()
+            .filter(|transaction| !ordered.contains(&transaction.id()))
+            .collect::<Vec<_>>();
+        transactions.sort_unstable_by_key(|transaction| transaction.id());
+        transactions.extend(self.retention_order.iter().filter_map(|id| self.transactions.get(id)));
+        transactions
+    }
+
+    /// Whether the account holds no funds and has no open disputes, so that it can be closed.
+    #[must_use]
+    pub fn is_settled(&self) -> bool {
+        self.available_balance == Amount::zero()
+            && self.held_balance == Amount::zero()
+            && self.disputed_transactions.is_empty()
+    }
+
+    /// Closes a settled account, dropping its retained transactions as they can no longer be disputed.
+    pub(super) fn close(&mut self) {
+        self.state = ClientAccountState::Closed;
+        self.transactions.clear();
+        self.charged_back_transactions.clear();
+        self.retention_order.clear();
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_repr {
+    use std::collections::{HashMap, HashSet, VecDeque};
+
+    use crate::models::{Amount, ClientId, Currency, Timestamp, Transaction, TransactionId};
+
+    use super::{ClientAccount, ClientAccountSnapshot, ClientAccountState};
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub(super) struct ClientAccountSnapshotRepr {
+        client: ClientId,
+        available: Amount,
+        held: Amount,
+        total: Amount,
+        locked: bool,
+        #[serde(default, skip_serializing_if = "Currency::is_unspecified")]
+        currency: Currency,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        overdrawn: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        state: Option<ClientAccountState>,
+    }
+
+    impl From<ClientAccountSnapshot> for ClientAccountSnapshotRepr {
+        fn from(value: ClientAccountSnapshot) -> Self {
+            Self {
+                client: value.client_id,
+                total: value.total_balance(),
+                locked: value.state.is_locked(),
+                overdrawn: value.is_overdrawn(),
+                state: match value.state {
+                    ClientAccountState::Frozen | ClientAccountState::Closed => Some(value.state),
+                    ClientAccountState::Normal | ClientAccountState::Locked => None,
+                },
+                currency: value.currency,
+                available: value.available_balance,
+                held: value.held_balance,
+            }
+        }
+    }
+
+    impl TryFrom<ClientAccountSnapshotRepr> for ClientAccountSnapshot {
+        type Error = String;
+
+        fn try_from(value: ClientAccountSnapshotRepr) -> Result<Self, Self::Error> {
+            let state = match value.state {
+                Some(state) => state,
+                None if value.locked => ClientAccountState::Locked,
+                None => ClientAccountState::Normal,
+            };
+            if state.is_locked() != value.locked {
+                return Err("locked does not match the state".to_string());
+            }
+            let snapshot = Self {
+                client_id: value.client,
+                currency: value.currency,
+                available_balance: value.available,
+                held_balance: value.held,
+                state,
+            };
+            if snapshot.total_balance() != value.total {
+                return Err("total is not the sum of available and held funds".to_string());
+            }
+            if snapshot.is_overdrawn() != value.overdrawn {
+                return Err("overdrawn does not match available funds".to_string());
+            }
+            Ok(snapshot)
+        }
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    pub(super) struct ClientAccountRepr {
+        #[serde(flatten)]
+        account: ClientAccountSnapshot,
+        transactions: Vec<Transaction>,
+        disputed: Vec<TransactionId>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        partially_held: Vec<PartialHoldRepr>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        dispute_timestamps: Vec<DisputeTimestampRepr>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        charged_back: Vec<TransactionId>,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct PartialHoldRepr {
+        tx: TransactionId,
+        held: Amount,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    struct DisputeTimestampRepr {
+        tx: TransactionId,
+        timestamp: Timestamp,
+    }
+
+    impl From<ClientAccount> for ClientAccountRepr {
+        fn from(value: ClientAccount) -> Self {
+            let account = value.snapshot();
+            let transactions = value.retained_transactions().into_iter().cloned().collect();
+            let mut disputed = value.disputed_transactions.into_iter().collect::<Vec<_>>();
+            disputed.sort_unstable();
+            let mut partially_held = value
+                .partial_holds
+                .into_iter()
+                .map(|(tx, held)| PartialHoldRepr { tx, held })
+                .collect::<Vec<_>>();
+            partially_held.sort_unstable_by_key(|hold| hold.tx);
+            let mut dispute_timestamps = value
+                .dispute_timestamps
+                .into_iter()
+                .map(|(tx, timestamp)| DisputeTimestampRepr { tx, timestamp })
+                .collect::<Vec<_>>();
+            dispute_timestamps.sort_unstable_by_key(|dispute| dispute.tx);
+            let mut charged_back = value.charged_back_transactions.into_iter().collect::<Vec<_>>();
+            charged_back.sort_unstable();
+            Self {
+                account,
+                transactions,
+                disputed,
+                partially_held,
+                dispute_timestamps,
+                charged_back,
+            }
+        }
+    }
+
+    impl TryFrom<ClientAccountRepr> for ClientAccount {
+        type Error = String;
+
+        fn try_from(value: ClientAccountRepr) -> Result<Self, Self::Error> {
+            // Transactions are serialized from the oldest, so that they are dropped in the same
+            // order by a dispute window.
+            let retention_order = value.transactions.iter().map(Transaction::id).collect::<VecDeque<_>>();
+            let count = value.transactions.len();
+            let transactions = value
+                .transactions
+                .into_iter()
+                .map(|transaction| (transaction.id(), transaction))
+                .collect::<HashMap<_, _>>();
+            if transactions.len() != count {
+                return Err("duplicate retained transaction".to_string());
+            }
+            if transactions
+                .values()
+                .any(|transaction| transaction.client_id() != value.account.client_id)
+            {
+                return Err("retained transaction of a different client".to_string());
+            }
+            if transactions
+                .values()
+                .any(|transaction| transaction.currency() != value.account.currency)
+            {
+                return Err("retained transaction in a different currency".to_string());
+            }
+            let disputed_transactions = value.disputed.into_iter().collect::<HashSet<_>>();
+            if !disputed_transactions.iter().all(|id| transactions.contains_key(id)) {
+                return Err("disputed transaction is not retained".to_string());
+            }
+            let partial_holds = value
+                .partially_held
+                .into_iter()
+                .map(|hold| (hold.tx, hold.held))
+                .collect::<HashMap<_, _>>();
+            if !partial_holds.keys().all(|id| disputed_transactions.contains(id)) {
+                return Err("partially held transaction is not disputed".to_string());
+            }
+            let dispute_timestamps = value
+                .dispute_timestamps
+                .into_iter()
+                .map(|dispute| (dispute.tx, dispute.timestamp))
+                .collect::<HashMap<_, _>>();
+            if !dispute_timestamps.keys().all(|id| disputed_transactions.contains(id)) {
+                return Err("dispute timestamp of a transaction that is not disputed".to_string());
+            }
+            let charged_back_transactions = value.charged_back.into_iter().collect::<HashSet<_>>();
+            if !charged_back_transactions.iter().all(|id| transactions.contains_key(id)) {
+                return Err("charged back transaction is not retained".to_string());
+            }
+            if !charged_back_transactions.is_disjoint(&disputed_transactions) {
+                return Err("charged back transaction is disputed".to_string());
+            }
+            Ok(Self {
+                client_id: value.account.client_id,
+                currency: value.account.currency,
+                available_balance: value.account.available_balance,
+                held_balance: value.account.held_balance,
+                state: value.account.state,
+                transactions,
+                disputed_transactions,
+                partial_holds,
+                dispute_timestamps,
+                charged_back_transactions,
+                retention_order,
+            })
         }
     }
 }
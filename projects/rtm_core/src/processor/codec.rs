@@ -0,0 +1,382 @@
+use std::io::{self, Read, Write};
+
+use crate::models::{
+    AccountingOperation, Amount, ClientId, Currency, Timestamp, Transaction, TransactionId, TransactionKind,
+};
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut index = 0;
+    while index < 256 {
+        #[allow(clippy::cast_possible_truncation)]
+        let mut value = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                (value >> 1) ^ 0xEDB88320
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+        table[index] = value;
+        index += 1;
+    }
+    table
+};
+
+/// Incrementally computed CRC-32 (IEEE 802.3) checksum.
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Crc32(u32);
+
+impl Crc32 {
+    pub(super) const fn new() -> Self {
+        Self(0xFFFFFFFF)
+    }
+
+    pub(super) fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 = CRC_TABLE[((self.0 ^ u32::from(*byte)) & 0xFF) as usize] ^ (self.0 >> 8);
+        }
+    }
+
+    pub(super) const fn value(self) -> u32 {
+        !self.0
+    }
+}
+
+/// Little endian binary encoding shared by the on-disk formats, with a checksum
+/// computed over everything written.
+pub(super) struct Encoder<W: Write> {
+    stream: W,
+    checksum: Crc32,
+}
+
+impl<W: Write> Encoder<W> {
+    pub(super) const fn new(stream: W) -> Self {
+        Self {
+            stream,
+            checksum: Crc32::new(),
+        }
+    }
+
+    /// Writes the checksum of all bytes written so far and returns the inner stream.
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        let checksum = self.checksum.value();
+        self.stream.write_all(&checksum.to_le_bytes())?;
+        Ok(self.stream)
+    }
+
+    pub(super) fn bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.checksum.update(bytes);
+        self.stream.write_all(bytes)
+    }
+
+    pub(super) fn u8(&mut self, value: u8) -> io::Result<()> {
+        self.bytes(&[value])
+    }
+
+    pub(super) fn u16(&mut self, value: u16) -> io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub(super) fn u32(&mut self, value: u32) -> io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    pub(super) fn u64(&mut self, value: u64) -> io::Result<()> {
+        self.bytes(&value.to_le_bytes())
+    }
+
+    /// Writes collection length, failing for collections not fitting into u32.
+    pub(super) fn len(&mut self, len: usize) -> io::Result<()> {
+        let len = u32::try_from(len).map_err(|_| io::Error::other("collection too large"))?;
+        self.u32(len)
+    }
+
+    pub(super) fn amount(&mut self, amount: &Amount) -> io::Result<()> {
+        let value = amount.to_string();
+        let len = u8::try_from(value.len()).map_err(|_| io::Error::other("amount too long"))?;
+        self.u8(len)?;
+        self.bytes(value.as_bytes())
+    }
+
+    pub(super) fn string(&mut self, value: &str) -> io::Result<()> {
+        self.len(value.len())?;
+        self.bytes(value.as_bytes())
+    }
+
+    pub(super) fn currency(&mut self, currency: Currency) -> io::Result<()> {
+        self.bytes(&currency.as_bytes())
+    }
+
+    /// Writes the currency, or zeros for `None`.
+    pub(super) fn optional_currency(&mut self, currency: Option<Currency>) -> io::Result<()> {
+        self.bytes(&currency.map_or([0; 3], |currency| currency.as_bytes()))
+    }
+
+    /// Writes a flag whether the timestamp is known, followed by the timestamp itself.
+    pub(super) fn optional_timestamp(&mut self, timestamp: Option<Timestamp>) -> io::Result<()> {
+        match timestamp {
+            None => self.u8(0),
+            Some(timestamp) => {
+                self.u8(1)?;
+                self.u64(timestamp.as_secs())
+            }
+        }
+    }
+
+    pub(super) fn transaction(&mut self, transaction: &Transaction) -> io::Result<()> {
+        self.u16(transaction.client_id().as_u16())?;
+        self.u32(transaction.id().as_u32())?;
+        self.u8(match transaction.kind() {
+            TransactionKind::Deposit => 0,
+            TransactionKind::Withdrawal => 1,
+        })?;
+        self.amount(transaction.amount())?;
+        self.currency(transaction.currency())?;
+        self.optional_timestamp(transaction.timestamp())
+    }
+
+    pub(super) fn operation(&mut self, operation: &AccountingOperation) -> io::Result<()> {
+        let (kind, client_id, ref_id, currency, timestamp) = match operation {
+            AccountingOperation::Transaction { transaction } => {
+                self.u8(0)?;
+                return self.transaction(transaction);
+            }
+            AccountingOperation::Dispute {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            } => (1, client_id, ref_id, currency, timestamp),
+            AccountingOperation::Resolve {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            } => (2, client_id, ref_id, currency, timestamp),
+            AccountingOperation::Chargeback {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            } => (3, client_id, ref_id, currency, timestamp),
+            AccountingOperation::Unlock {
+                client_id,
+                reason,
+                timestamp,
+            } => return self.administrative_operation(4, *client_id, reason, *timestamp),
+            AccountingOperation::Freeze {
+                client_id,
+                reason,
+                timestamp,
+            } => return self.administrative_operation(5, *client_id, reason, *timestamp),
+            AccountingOperation::Close {
+                client_id,
+                reason,
+                timestamp,
+            } => return self.administrative_operation(6, *client_id, reason, *timestamp),
+        };
+        self.u8(kind)?;
+        self.u16(client_id.as_u16())?;
+        self.u32(ref_id.as_u32())?;
+        self.optional_currency(*currency)?;
+        self.optional_timestamp(*timestamp)
+    }
+
+    fn administrative_operation(
+        &mut self,
+        kind: u8,
+        client_id: ClientId,
+        reason: &str,
+        timestamp: Option<Timestamp>,
+    ) -> io::Result<()> {
+        self.u8(kind)?;
+        self.u16(client_id.as_u16())?;
+        self.string(reason)?;
+        self.optional_timestamp(timestamp)
+    }
+}
+
+/// Errors of [`Decoder`], either the stream failed or it contains unexpected data.
+#[derive(Debug)]
+pub(super) enum DecodeError {
+    Io(io::Error),
+    Invalid(&'static str),
+}
+
+impl From<io::Error> for DecodeError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+pub(super) struct Decoder<R: Read> {
+    stream: R,
+    checksum: Crc32,
+}
+
+impl<R: Read> Decoder<R> {
+    pub(super) const fn new(stream: R) -> Self {
+        Self {
+            stream,
+            checksum: Crc32::new(),
+        }
+    }
+
+    /// Reads the checksum and compares it with the checksum of all bytes read so far.
+    pub(super) fn verify_checksum(&mut self) -> Result<bool, DecodeError> {
+        let expected = self.checksum.value();
+        let mut buffer = [0; 4];
+        self.stream.read_exact(&mut buffer)?;
+        Ok(u32::from_le_bytes(buffer) == expected)
+    }
+
+    pub(super) fn bytes<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let mut buffer = [0; N];
+        self.stream.read_exact(&mut buffer)?;
+        self.checksum.update(&buffer);
+        Ok(buffer)
+    }
+
+    pub(super) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.bytes::<1>()?[0])
+    }
+
+    pub(super) fn u16(&mut self) -> Result<u16, DecodeError> {
+        Ok(u16::from_le_bytes(self.bytes()?))
+    }
+
+    pub(super) fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.bytes()?))
+    }
+
+    pub(super) fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.bytes()?))
+    }
+
+    pub(super) fn len(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.u32()?).map_err(|_| DecodeError::Invalid("collection too large"))
+    }
+
+    pub(super) fn amount(&mut self) -> Result<Amount, DecodeError> {
+        let len = usize::from(self.u8()?);
+        let mut buffer = vec![0; len];
+        self.stream.read_exact(&mut buffer)?;
+        self.checksum.update(&buffer);
+        std::str::from_utf8(&buffer)
+            .ok()
+            .and_then(|value| Amount::parse_exact(value).ok())
+            .ok_or(DecodeError::Invalid("invalid amount"))
+    }
+
+    pub(super) fn string(&mut self) -> Result<String, DecodeError> {
+        let len = self.len()?;
+        // Corrupted lengths must not allocate more than the stream actually contains.
+        let mut buffer = Vec::new();
+        (&mut self.stream).take(len as u64).read_to_end(&mut buffer)?;
+        if buffer.len() != len {
+            return Err(DecodeError::Io(io::ErrorKind::UnexpectedEof.into()));
+        }
+        self.checksum.update(&buffer);
+        String::from_utf8(buffer).map_err(|_| DecodeError::Invalid("invalid string"))
+    }
+
+    pub(super) fn currency(&mut self) -> Result<Currency, DecodeError> {
+        Currency::try_from(self.bytes::<3>()?).map_err(|_| DecodeError::Invalid("invalid currency"))
+    }
+
+    pub(super) fn optional_currency(&mut self) -> Result<Option<Currency>, DecodeError> {
+        match self.bytes::<3>()? {
+            [0, 0, 0] => Ok(None),
+            code => Currency::try_from(code)
+                .map(Some)
+                .map_err(|_| DecodeError::Invalid("invalid currency")),
+        }
+    }
+
+    pub(super) fn optional_timestamp(&mut self) -> Result<Option<Timestamp>, DecodeError> {
+        match self.u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(Timestamp::from(self.u64()?))),
+            _ => Err(DecodeError::Invalid("invalid timestamp flag")),
+        }
+    }
+
+    pub(super) fn transaction(&mut self) -> Result<Transaction, DecodeError> {
+        let client_id = ClientId::from(self.u16()?);
+        let id = TransactionId::from(self.u32()?);
+        let kind = match self.u8()? {
+            0 => TransactionKind::Deposit,
+            1 => TransactionKind::Withdrawal,
+            _ => return Err(DecodeError::Invalid("invalid transaction kind")),
+        };
+        let amount = self.amount()?;
+        let currency = self.currency()?;
+        let timestamp = self.optional_timestamp()?;
+        Ok(Transaction::with_currency(client_id, id, amount, currency, kind).with_timestamp(timestamp))
+    }
+
+    pub(super) fn operation(&mut self) -> Result<AccountingOperation, DecodeError> {
+        let kind = self.u8()?;
+        if kind == 0 {
+            return Ok(AccountingOperation::Transaction {
+                transaction: self.transaction()?,
+            });
+        }
+        if kind >= 4 {
+            return self.administrative_operation(kind);
+        }
+        let client_id = ClientId::from(self.u16()?);
+        let ref_id = TransactionId::from(self.u32()?);
+        let currency = self.optional_currency()?;
+        let timestamp = self.optional_timestamp()?;
+        match kind {
+            1 => Ok(AccountingOperation::Dispute {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            }),
+            2 => Ok(AccountingOperation::Resolve {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            }),
+            3 => Ok(AccountingOperation::Chargeback {
+                client_id,
+                ref_id,
+                currency,
+                timestamp,
+            }),
+            _ => Err(DecodeError::Invalid("invalid operation kind")),
+        }
+    }
+
+    /// Reads the client, reason and timestamp of an administrative operation of the given kind.
+    fn administrative_operation(&mut self, kind: u8) -> Result<AccountingOperation, DecodeError> {
+        let client_id = ClientId::from(self.u16()?);
+        let reason = self.string()?;
+        let timestamp = self.optional_timestamp()?;
+        match kind {
+            4 => Ok(AccountingOperation::Unlock {
+                client_id,
+                reason,
+                timestamp,
+            }),
+            5 => Ok(AccountingOperation::Freeze {
+                client_id,
+                reason,
+                timestamp,
+            }),
+            6 => Ok(AccountingOperation::Close {
+                client_id,
+                reason,
+                timestamp,
+            }),
+            _ => Err(DecodeError::Invalid("invalid operation kind")),
+        }
+    }
+}
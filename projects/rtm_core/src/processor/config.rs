@@ -0,0 +1,75 @@
+use std::time::Duration;
+
+use crate::models::{AmountPolicies, TransactionKind};
+
+/// Decides which transactions can be disputed. Only these are kept by the
+/// [`AccountingSystem`](super::AccountingSystem) after being applied, others are forgotten.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[must_use]
+pub enum DisputePolicy {
+    /// Deposits and withdrawals can be disputed. Disputing a withdrawal holds the withdrawn
+    /// amount without making it available, and its chargeback returns the amount to the client.
+    #[default]
+    All,
+
+    /// Only deposits can be disputed, withdrawals are not retained.
+    DepositsOnly,
+
+    /// No transaction can be disputed or is retained.
+    None,
+}
+
+impl DisputePolicy {
+    #[must_use]
+    pub const fn allows(self, kind: TransactionKind) -> bool {
+        match self {
+            DisputePolicy::All => true,
+            DisputePolicy::DepositsOnly => matches!(kind, TransactionKind::Deposit),
+            DisputePolicy::None => false,
+        }
+    }
+}
+
+/// Decides how a dispute of a deposit larger than the available funds is handled, e.g. when
+/// most of the deposit was already withdrawn.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+#[must_use]
+pub enum OverdraftPolicy {
+    /// The whole deposit is held, leaving negative available funds. Such an account is
+    /// reported as overdrawn.
+    #[default]
+    Allow,
+
+    /// The dispute is rejected.
+    Reject,
+
+    /// Only the available funds are held, possibly nothing. Resolve and chargeback of the
+    /// dispute move only the held amount.
+    CapHold,
+}
+
+/// Configuration of the [`AccountingSystem`](super::AccountingSystem).
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[must_use]
+pub struct AccountingSystemConfig {
+    /// Which transactions can be disputed, and so are retained for future disputes.
+    pub dispute_policy: DisputePolicy,
+
+    /// How disputes of deposits exceeding available funds are handled.
+    pub overdraft_policy: OverdraftPolicy,
+
+    /// Maximal number of retained transactions per client. When exceeded, the oldest
+    /// transaction that is not currently disputed is forgotten. `None` means no limit.
+    pub dispute_window: Option<usize>,
+
+    /// Longest time from a transaction to its dispute. `None` means no limit.
+    /// Enforced only when both the transaction and the dispute have timestamps.
+    pub dispute_time_limit: Option<Duration>,
+
+    /// Longest time from a dispute to its chargeback. `None` means no limit.
+    /// Enforced only when both the dispute and the chargeback have timestamps.
+    pub chargeback_time_limit: Option<Duration>,
+
+    /// Decimal places and rounding of transaction amounts in every currency, and so of all balances.
+    pub amount_policies: AmountPolicies,
+}
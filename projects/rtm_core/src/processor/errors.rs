@@ -1,22 +1,45 @@
-use crate::models::{ClientId, TransactionId};
+use crate::models::{ClientId, Currency, TransactionId};
 
 /// Represents possible errors during transaction processing.
+///
+/// Serialized as an object with the variant name in the `error` field, followed by the
+/// variant fields, e.g. `{"error": "InsufficientFunds", "cause_id": 4}`.
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "error")
+)]
 #[must_use]
 pub enum TransactionError {
-    /// Current account is locked.
+    /// Current account is locked by a chargeback.
     AccountLocked { client_id: ClientId },
 
+    /// Current account is frozen by an administrator.
+    AccountFrozen { client_id: ClientId },
+
+    /// Current account is closed.
+    AccountClosed { client_id: ClientId },
+
+    /// Tried to Unlock an account that is neither locked nor frozen.
+    AccountNotLocked { client_id: ClientId },
+
+    /// Tried to Close an account with funds or open disputes.
+    AccountNotSettled { client_id: ClientId },
+
+    /// Administrative operation refers to a client without any account.
+    ClientDoesNotExist { client_id: ClientId },
+
     /// Tried to apply Withdrawl with amount greater than available balance.
     InsufficientFunds { cause_id: TransactionId },
 
-    /// Dispute, Resolve or Chargeback transaction refers to a non-existent transaction.
+    /// Dispute, Resolve or Chargeback transaction refers to a non-existent or no longer retained transaction.
     TransactionDoesNotExist { ref_id: TransactionId },
 
     /// Tried to apply a transaction with id that was already prcoessed.
     DuplicateTransaction { cause_id: TransactionId },
 
-    /// Tried to Dispute the same transaction twice.
+    /// Tried to Dispute the same transaction twice, also after it was charged back.
     TransactionAlreadyDisputed { ref_id: TransactionId },
 
     /// Tried to Resolve or Chargeback a non-disputed transaction.
@@ -24,4 +47,120 @@ pub enum TransactionError {
 
     /// Tried to apply a transaction to a different client.
     CrossClientTransaction,
+
+    /// Applying the transaction would make a balance too large to represent.
+    ArithmeticOverflow { cause_id: TransactionId },
+
+    /// Deposit or Withdrawal has zero or negative amount.
+    NonPositiveAmount { cause_id: TransactionId },
+
+    /// Deposit or Withdrawal has more decimal places than allowed by the
+    /// [`AmountPolicy`](crate::models::AmountPolicy) that rejects rounding.
+    ExcessivePrecision { cause_id: TransactionId },
+
+    /// Dispute, Resolve or Chargeback expects the referred transaction in a different currency.
+    CurrencyMismatch {
+        ref_id: TransactionId,
+        expected: Currency,
+        actual: Currency,
+    },
+
+    /// Tried to Dispute a transaction of a kind not allowed by the
+    /// [`DisputePolicy`](super::DisputePolicy). Such transactions are not retained, so this
+    /// applies to ones retained under a different policy, e.g. restored from a snapshot.
+    TransactionNotDisputable { ref_id: TransactionId },
+
+    /// Tried to Dispute a deposit larger than the available funds, rejected by the
+    /// [`OverdraftPolicy`](super::OverdraftPolicy).
+    InsufficientFundsForDispute { ref_id: TransactionId },
+
+    /// Tried to Dispute a transaction, or Chargeback a dispute, later than allowed by the
+    /// [`AccountingSystemConfig`](super::AccountingSystemConfig) time limits.
+    DisputeWindowExpired { ref_id: TransactionId },
+}
+
+impl TransactionError {
+    /// Name of the error variant, stable for reporting purposes.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            TransactionError::AccountLocked { .. } => "AccountLocked",
+            TransactionError::AccountFrozen { .. } => "AccountFrozen",
+            TransactionError::AccountClosed { .. } => "AccountClosed",
+            TransactionError::AccountNotLocked { .. } => "AccountNotLocked",
+            TransactionError::AccountNotSettled { .. } => "AccountNotSettled",
+            TransactionError::ClientDoesNotExist { .. } => "ClientDoesNotExist",
+            TransactionError::InsufficientFunds { .. } => "InsufficientFunds",
+            TransactionError::TransactionDoesNotExist { .. } => "TransactionDoesNotExist",
+            TransactionError::DuplicateTransaction { .. } => "DuplicateTransaction",
+            TransactionError::TransactionAlreadyDisputed { .. } => "TransactionAlreadyDisputed",
+            TransactionError::TransactionNotDisputed { .. } => "TransactionNotDisputed",
+            TransactionError::CrossClientTransaction => "CrossClientTransaction",
+            TransactionError::ArithmeticOverflow { .. } => "ArithmeticOverflow",
+            TransactionError::NonPositiveAmount { .. } => "NonPositiveAmount",
+            TransactionError::ExcessivePrecision { .. } => "ExcessivePrecision",
+            TransactionError::CurrencyMismatch { .. } => "CurrencyMismatch",
+            TransactionError::TransactionNotDisputable { .. } => "TransactionNotDisputable",
+            TransactionError::InsufficientFundsForDispute { .. } => "InsufficientFundsForDispute",
+            TransactionError::DisputeWindowExpired { .. } => "DisputeWindowExpired",
+        }
+    }
+}
+
+impl std::fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransactionError::AccountLocked { client_id } => write!(f, "account {} is locked", client_id.as_u16()),
+            TransactionError::AccountFrozen { client_id } => write!(f, "account {} is frozen", client_id.as_u16()),
+            TransactionError::AccountClosed { client_id } => write!(f, "account {} is closed", client_id.as_u16()),
+            TransactionError::AccountNotLocked { client_id } => {
+                write!(f, "account {} is not locked", client_id.as_u16())
+            }
+            TransactionError::AccountNotSettled { client_id } => {
+                write!(f, "account {} has funds or open disputes", client_id.as_u16())
+            }
+            TransactionError::ClientDoesNotExist { client_id } => {
+                write!(f, "client {} does not exist", client_id.as_u16())
+            }
+            TransactionError::InsufficientFunds { cause_id } => {
+                write!(f, "insufficient funds for transaction {}", cause_id.as_u32())
+            }
+            TransactionError::TransactionDoesNotExist { ref_id } => {
+                write!(f, "transaction {} does not exist", ref_id.as_u32())
+            }
+            TransactionError::DuplicateTransaction { cause_id } => {
+                write!(f, "transaction {} was already processed", cause_id.as_u32())
+            }
+            TransactionError::TransactionAlreadyDisputed { ref_id } => {
+                write!(f, "transaction {} is already disputed", ref_id.as_u32())
+            }
+            TransactionError::TransactionNotDisputed { ref_id } => {
+                write!(f, "transaction {} is not disputed", ref_id.as_u32())
+            }
+            TransactionError::CrossClientTransaction => write!(f, "transaction belongs to a different client"),
+            TransactionError::ArithmeticOverflow { cause_id } => {
+                write!(f, "transaction {} overflows the balance", cause_id.as_u32())
+            }
+            TransactionError::NonPositiveAmount { cause_id } => {
+                write!(f, "transaction {} has non-positive amount", cause_id.as_u32())
+            }
+            TransactionError::ExcessivePrecision { cause_id } => {
+                write!(f, "transaction {} has too many decimal places", cause_id.as_u32())
+            }
+            TransactionError::CurrencyMismatch {
+                ref_id,
+                expected,
+                actual,
+            } => write!(f, "transaction {} is in {actual}, not in {expected}", ref_id.as_u32()),
+            TransactionError::TransactionNotDisputable { ref_id } => {
+                write!(f, "transaction {} cannot be disputed", ref_id.as_u32())
+            }
+            TransactionError::InsufficientFundsForDispute { ref_id } => {
+                write!(f, "insufficient funds to dispute transaction {}", ref_id.as_u32())
+            }
+            TransactionError::DisputeWindowExpired { ref_id } => {
+                write!(f, "time limit to dispute transaction {} has expired", ref_id.as_u32())
+            }
+        }
+    }
 }
@@ -0,0 +1,196 @@
+use crate::models::{Amount, ClientId, Currency, TransactionId, TransactionKind};
+
+/// Balances of a client account in a single currency at a single point in time.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[must_use]
+pub struct Balances {
+    pub available: Amount,
+    pub held: Amount,
+}
+
+/// Balances of a client account before and after an event.
+#[derive(Debug, PartialEq, Eq, Clone, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[must_use]
+pub struct BalanceChange {
+    pub before: Balances,
+    pub after: Balances,
+}
+
+/// State change caused by a successfully applied operation.
+///
+/// [`AccountingEvent::FundsHeld`], [`AccountingEvent::FundsReleased`] and
+/// [`AccountingEvent::ChargedBack`] carry the held amount and kind of the disputed transaction,
+/// where the held amount is less than the transaction amount only for capped holds.
+/// Balances are the ones of the client account in the `currency` of the event. Events of
+/// locking, unlocking, freezing and closing apply to accounts of the client in all currencies.
+///
+/// With the `serde` feature it is serialized with an `event` tag, e.g.
+/// `{"event": "AccountLocked", "client": 1, "tx": 2}`.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "event")
+)]
+#[must_use]
+pub enum AccountingEvent {
+    /// Amount was added to available funds.
+    Deposited {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        amount: Amount,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+        balances: BalanceChange,
+    },
+
+    /// Amount was taken from available funds.
+    Withdrawn {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        amount: Amount,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+        balances: BalanceChange,
+    },
+
+    /// Disputed amount was added to held funds, taken from available funds for deposits.
+    FundsHeld {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        amount: Amount,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+        #[cfg_attr(feature = "serde", serde(rename = "type"))]
+        kind: TransactionKind,
+        balances: BalanceChange,
+    },
+
+    /// Dispute was resolved and the amount was removed from held funds, moved back to
+    /// available funds for deposits.
+    FundsReleased {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        amount: Amount,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+        #[cfg_attr(feature = "serde", serde(rename = "type"))]
+        kind: TransactionKind,
+        balances: BalanceChange,
+    },
+
+    /// Disputed amount was removed from held funds, returned to available funds for withdrawals.
+    /// Followed by [`AccountingEvent::AccountLocked`], unless the client is already locked.
+    ChargedBack {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        amount: Amount,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+        #[cfg_attr(feature = "serde", serde(rename = "type"))]
+        kind: TransactionKind,
+        balances: BalanceChange,
+    },
+
+    /// Available funds became negative because of a dispute of the given deposit. Always
+    /// preceded by [`AccountingEvent::FundsHeld`].
+    AccountOverdrawn {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+        #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Currency::is_unspecified"))]
+        currency: Currency,
+    },
+
+    /// Account was locked because of a chargeback of the given transaction.
+    AccountLocked {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        #[cfg_attr(feature = "serde", serde(rename = "tx"))]
+        transaction_id: TransactionId,
+    },
+
+    /// Locked or frozen account was returned to normal by an administrator.
+    AccountUnlocked {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        reason: String,
+    },
+
+    /// Account was locked by an administrator.
+    AccountFrozen {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        reason: String,
+    },
+
+    /// Account was closed by an administrator.
+    AccountClosed {
+        #[cfg_attr(feature = "serde", serde(rename = "client"))]
+        client_id: ClientId,
+        reason: String,
+    },
+}
+
+impl AccountingEvent {
+    pub const fn client_id(&self) -> ClientId {
+        match self {
+            AccountingEvent::Deposited { client_id, .. }
+            | AccountingEvent::Withdrawn { client_id, .. }
+            | AccountingEvent::FundsHeld { client_id, .. }
+            | AccountingEvent::FundsReleased { client_id, .. }
+            | AccountingEvent::ChargedBack { client_id, .. }
+            | AccountingEvent::AccountOverdrawn { client_id, .. }
+            | AccountingEvent::AccountLocked { client_id, .. }
+            | AccountingEvent::AccountUnlocked { client_id, .. }
+            | AccountingEvent::AccountFrozen { client_id, .. }
+            | AccountingEvent::AccountClosed { client_id, .. } => *client_id,
+        }
+    }
+}
+
+/// Receiver of [`AccountingEvent`]s, in the order they happened.
+///
+/// Implemented for `()`, which drops all events, for `Vec<AccountingEvent>`, which collects
+/// them, for closures, and for `Option` of any sink.
+pub trait EventSink {
+    fn publish(&mut self, event: AccountingEvent);
+}
+
+impl EventSink for () {
+    #[inline(always)]
+    fn publish(&mut self, _event: AccountingEvent) {}
+}
+
+impl EventSink for Vec<AccountingEvent> {
+    fn publish(&mut self, event: AccountingEvent) {
+        self.push(event);
+    }
+}
+
+impl<S: EventSink> EventSink for Option<S> {
+    fn publish(&mut self, event: AccountingEvent) {
+        if let Some(sink) = self {
+            sink.publish(event);
+        }
+    }
+}
+
+impl<F: FnMut(AccountingEvent)> EventSink for F {
+    fn publish(&mut self, event: AccountingEvent) {
+        self(event);
+    }
+}
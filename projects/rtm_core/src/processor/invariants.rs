@@ -0,0 +1,305 @@
+use crate::models::{Amount, ClientId, TransactionId, TransactionKind};
+
+use super::{AccountingSystem, ClientAccount, DisputePolicy, OverdraftPolicy};
+
+/// Inconsistency of the accounting system state, found by [`AccountingSystem::verify_invariants`].
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum InvariantViolation {
+    /// Held funds differ from the sum of amounts held by currently disputed transactions.
+    HeldMismatch {
+        client_id: ClientId,
+        held: Amount,
+        disputed: Amount,
+    },
+
+    /// Held funds are negative.
+    NegativeHeld { client_id: ClientId, held: Amount },
+
+    /// Available and held funds differ from the sum of all applied and not charged back
+    /// transactions, plus the held amounts of disputed withdrawals. Checked only when every
+    /// transaction is retained and reversed in whole.
+    TotalMismatch {
+        client_id: ClientId,
+        total: Amount,
+        transactions: Amount,
+    },
+
+    /// Balances or transactions of the account sum to more than an [`Amount`] can represent.
+    Overflow { client_id: ClientId },
+
+    /// Disputed transaction is not retained.
+    DisputedNotRetained {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+
+    /// Charged back transaction is not retained, or is still disputed.
+    InvalidChargeback {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+
+    /// Retained transaction belongs to a different client.
+    ForeignTransaction {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+
+    /// Retained transaction is in a different currency than its account.
+    ForeignCurrency {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+
+    /// Retained transaction is not marked as processed, so its id could be reused.
+    UnseenTransaction {
+        client_id: ClientId,
+        transaction_id: TransactionId,
+    },
+
+    /// Accounts of the client in different currencies are in different states.
+    InconsistentState { client_id: ClientId },
+}
+
+impl std::fmt::Display for InvariantViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvariantViolation::HeldMismatch {
+                client_id,
+                held,
+                disputed,
+            } => write!(
+                f,
+                "account {} holds {held}, but its disputed transactions sum to {disputed}",
+                client_id.as_u16()
+            ),
+            InvariantViolation::NegativeHeld { client_id, held } => {
+                write!(f, "account {} holds negative {held}", client_id.as_u16())
+            }
+            InvariantViolation::TotalMismatch {
+                client_id,
+                total,
+                transactions,
+            } => write!(
+                f,
+                "account {} has total {total}, but its transactions sum to {transactions}",
+                client_id.as_u16()
+            ),
+            InvariantViolation::Overflow { client_id } => {
+                write!(
+                    f,
+                    "account {} sums to more than an amount can represent",
+                    client_id.as_u16()
+                )
+            }
+            InvariantViolation::DisputedNotRetained {
+                client_id,
+                transaction_id,
+            } => write!(
+                f,
+                "account {} disputes transaction {} that is not retained",
+                client_id.as_u16(),
+                transaction_id.as_u32()
+            ),
+            InvariantViolation::InvalidChargeback {
+                client_id,
+                transaction_id,
+            } => write!(
+                f,
+                "account {} charged back transaction {} that is not retained or still disputed",
+                client_id.as_u16(),
+                transaction_id.as_u32()
+            ),
+            InvariantViolation::ForeignTransaction {
+                client_id,
+                transaction_id,
+            } => write!(
+                f,
+                "account {} retains transaction {} of a different client",
+                client_id.as_u16(),
+                transaction_id.as_u32()
+            ),
+            InvariantViolation::ForeignCurrency {
+                client_id,
+                transaction_id,
+            } => write!(
+                f,
+                "account {} retains transaction {} in a different currency",
+                client_id.as_u16(),
+                transaction_id.as_u32()
+            ),
+            InvariantViolation::UnseenTransaction {
+                client_id,
+                transaction_id,
+            } => write!(
+                f,
+                "account {} retains transaction {} that is not marked as processed",
+                client_id.as_u16(),
+                transaction_id.as_u32()
+            ),
+            InvariantViolation::InconsistentState { client_id } => {
+                write!(f, "accounts of client {} are in different states", client_id.as_u16())
+            }
+        }
+    }
+}
+
+impl AccountingSystem {
+    /// Recomputes balances of every account from its retained transactions and compares
+    /// them with the tracked ones.
+    ///
+    /// Held funds are always checked against the disputed transactions. Total funds are
+    /// checked against the retained transactions only with [`DisputePolicy::All`],
+    /// no dispute window and no capped holds, since otherwise some applied transactions are
+    /// forgotten or charged back only in part.
+    /// Accounts of a client in different currencies have to share the same state.
+    ///
+    /// # Errors
+    ///
+    /// All found violations, ordered by client id.
+    pub fn verify_invariants(&self) -> Result<(), Vec<InvariantViolation>> {
+        let retains_all = self.config.dispute_policy == DisputePolicy::All
+            && self.config.dispute_window.is_none()
+            && self.config.overdraft_policy != OverdraftPolicy::CapHold;
+        let mut violations = Vec::new();
+        let accounts = self.client_accounts.values().collect::<Vec<_>>();
+        for client_accounts in accounts.chunk_by(|lhs, rhs| lhs.client_id == rhs.client_id) {
+            for account in client_accounts {
+                self.verify_account(account, retains_all, &mut violations);
+            }
+            if client_accounts
+                .iter()
+                .any(|account| account.state != client_accounts[0].state)
+            {
+                violations.push(InvariantViolation::InconsistentState {
+                    client_id: client_accounts[0].client_id,
+                });
+            }
+        }
+
+        if violations.is_empty() { Ok(()) } else { Err(violations) }
+    }
+
+    fn verify_account(&self, account: &ClientAccount, retains_all: bool, violations: &mut Vec<InvariantViolation>) {
+        let client_id = account.client_id;
+
+        let mut retained = Vec::with_capacity(account.transactions.len());
+        let mut transactions = account.transactions.iter().collect::<Vec<_>>();
+        transactions.sort_unstable_by_key(|(transaction_id, _)| **transaction_id);
+        for (transaction_id, transaction) in transactions {
+            if transaction.client_id() != client_id {
+                violations.push(InvariantViolation::ForeignTransaction {
+                    client_id,
+                    transaction_id: *transaction_id,
+                });
+            }
+            if transaction.currency() != account.currency {
+                violations.push(InvariantViolation::ForeignCurrency {
+                    client_id,
+                    transaction_id: *transaction_id,
+                });
+            }
+            if !self.seen_transactions.contains(*transaction_id) {
+                violations.push(InvariantViolation::UnseenTransaction {
+                    client_id,
+                    transaction_id: *transaction_id,
+                });
+            }
+            // Charged back transaction was reversed, so it no longer counts to the total.
+            if !account.charged_back_transactions.contains(transaction_id) {
+                retained.push(signed_amount(transaction.kind(), transaction.amount()));
+            }
+        }
+
+        let mut charged_back = account.charged_back_transactions.iter().copied().collect::<Vec<_>>();
+        charged_back.sort_unstable();
+        for transaction_id in charged_back {
+            if !account.transactions.contains_key(&transaction_id)
+                || account.disputed_transactions.contains(&transaction_id)
+            {
+                violations.push(InvariantViolation::InvalidChargeback {
+                    client_id,
+                    transaction_id,
+                });
+            }
+        }
+
+        let mut disputed = Vec::with_capacity(account.disputed_transactions.len());
+        let mut disputed_ids = account.disputed_transactions.iter().copied().collect::<Vec<_>>();
+        disputed_ids.sort_unstable();
+        for transaction_id in disputed_ids {
+            let Some(transaction) = account.transactions.get(&transaction_id) else {
+                violations.push(InvariantViolation::DisputedNotRetained {
+                    client_id,
+                    transaction_id,
+                });
+                continue;
+            };
+            // Disputed withdrawal is held on top of the balance it was taken from.
+            let held = account.held_amount(transaction);
+            if transaction.kind() == TransactionKind::Withdrawal {
+                retained.push(held.clone());
+            }
+            disputed.push(held);
+        }
+
+        let (Some(disputed), Some(retained), Some(total)) = (
+            checked_sum(disputed),
+            checked_sum(retained),
+            account.available_balance.checked_add(&account.held_balance),
+        ) else {
+            violations.push(InvariantViolation::Overflow { client_id });
+            return;
+        };
+
+        if account.held_balance != disputed {
+            violations.push(InvariantViolation::HeldMismatch {
+                client_id,
+                held: account.held_balance.clone(),
+                disputed,
+            });
+        }
+        if account.held_balance < Amount::zero() {
+            violations.push(InvariantViolation::NegativeHeld {
+                client_id,
+                held: account.held_balance.clone(),
+            });
+        }
+
+        if retains_all && total != retained {
+            violations.push(InvariantViolation::TotalMismatch {
+                client_id,
+                total,
+                transactions: retained,
+            });
+        }
+    }
+}
+
+/// Sums the amounts, returning `None` only if the sum itself does not fit an [`Amount`]. Partial
+/// sums never overflow before that, since positive and negative amounts are interleaved to keep
+/// them closer to zero than the largest amount.
+fn checked_sum(mut amounts: Vec<Amount>) -> Option<Amount> {
+    amounts.sort_unstable();
+    let zero = Amount::zero();
+    let mut amounts = std::collections::VecDeque::from(amounts);
+    let mut sum = Amount::zero();
+    loop {
+        let next = if sum < zero {
+            amounts.pop_back()
+        } else {
+            amounts.pop_front()
+        };
+        let Some(next) = next else {
+            return Some(sum);
+        };
+        sum = sum.checked_add(&next)?;
+    }
+}
+
+fn signed_amount(kind: TransactionKind, amount: &Amount) -> Amount {
+    match kind {
+        TransactionKind::Deposit => amount.clone(),
+        TransactionKind::Withdrawal => -amount.clone(),
+    }
+}
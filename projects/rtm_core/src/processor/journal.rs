@@ -0,0 +1,502 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+};
+
+use crate::models::AccountingOperation;
+
+use super::{
+    AccountingSystem, AccountingSystemConfig, SnapshotError, TransactionError,
+    codec::{DecodeError, Decoder, Encoder},
+};
+
+const JOURNAL_MAGIC: [u8; 8] = *b"RTMWAL\0\0";
+
+/// Version of the journal format written by [`JournaledAccountingSystem`].
+/// Only journals of this version can be recovered.
+pub const JOURNAL_VERSION: u16 = 1;
+
+/// Records are tiny, anything larger is garbage left by an interrupted write.
+const MAX_RECORD_SIZE: u32 = 1024;
+
+/// Magic, version, base sequence and checksum.
+const HEADER_SIZE: u64 = 22;
+
+/// When the journal is flushed to the disk with `fsync`. Regardless of the policy every record
+/// is handed to the operating system before the operation is reported as applied, so it
+/// survives a crash of the process, but only synced records survive a crash of the machine.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum FsyncPolicy {
+    /// Sync after every record.
+    #[default]
+    Always,
+
+    /// Sync after every given number of records.
+    Every(NonZeroUsize),
+
+    /// Sync only on [`JournaledAccountingSystem::sync`] and checkpoints.
+    Never,
+}
+
+#[derive(Debug)]
+pub enum JournalError {
+    Io(io::Error),
+
+    /// Operation was rejected by the accounting system, and so it was not journaled.
+    Transaction(TransactionError),
+
+    Snapshot(SnapshotError),
+
+    /// File does not start with the journal header.
+    InvalidHeader,
+
+    /// Journal was written in a format version other than the supported one.
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+    },
+
+    /// Journal does not continue the snapshot, some operations are lost.
+    MissingRecords {
+        expected: u64,
+        found: u64,
+    },
+
+    /// Journaled operation was rejected during replay, so the journal does not match the snapshot.
+    ReplayFailed {
+        sequence: u64,
+        error: TransactionError,
+    },
+
+    /// Record is damaged, but followed by further records, so it is not an interrupted write.
+    Corrupted {
+        sequence: u64,
+    },
+
+    /// Operation does not fit a journal record, and so it was not applied.
+    RecordTooLarge {
+        size: usize,
+    },
+}
+
+impl std::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JournalError::Io(err) => write!(f, "{err}"),
+            JournalError::Transaction(err) => write!(f, "{err}"),
+            JournalError::Snapshot(err) => write!(f, "{err}"),
+            JournalError::InvalidHeader => write!(f, "not an rtm journal"),
+            JournalError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "journal version {found} is not supported, the supported version is {supported}"
+            ),
+            JournalError::MissingRecords { expected, found } => {
+                write!(f, "expected journal record {expected}, found {found}")
+            }
+            JournalError::ReplayFailed { sequence, error } => {
+                write!(f, "journal record {sequence} could not be replayed: {error}")
+            }
+            JournalError::Corrupted { sequence } => {
+                write!(
+                    f,
+                    "journal record {sequence} is corrupted and followed by further records"
+                )
+            }
+            JournalError::RecordTooLarge { size } => write!(
+                f,
+                "operation takes {size} bytes, more than the {MAX_RECORD_SIZE} bytes of a journal record"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for JournalError {}
+
+impl From<io::Error> for JournalError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<TransactionError> for JournalError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
+
+impl From<SnapshotError> for JournalError {
+    fn from(value: SnapshotError) -> Self {
+        Self::Snapshot(value)
+    }
+}
+
+/// Outcome of [`JournaledAccountingSystem::open`].
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[must_use]
+pub struct RecoveryReport {
+    /// Sequence of the last journal record included in the snapshot.
+    pub snapshot_sequence: u64,
+
+    /// Number of journal records replayed on top of the snapshot.
+    pub replayed: u64,
+
+    /// Size of the incomplete or corrupted last record cut off from the journal.
+    pub discarded_bytes: u64,
+}
+
+/// [`AccountingSystem`] with every successfully applied operation appended to a journal
+/// (write-ahead log), so that its exact state can be recovered after a crash.
+///
+/// State is persisted as the latest snapshot plus the journal of operations applied since.
+/// Each journal record carries a sequence number and a checksum; a snapshot remembers the
+/// sequence of the last record it includes. [`JournaledAccountingSystem::checkpoint`] replaces
+/// the snapshot and starts a new, empty journal.
+#[must_use]
+pub struct JournaledAccountingSystem {
+    accounting_system: AccountingSystem,
+    snapshot_path: PathBuf,
+    journal_path: PathBuf,
+    journal: BufWriter<File>,
+    fsync: FsyncPolicy,
+    sequence: u64,
+    unsynced: usize,
+}
+
+impl JournaledAccountingSystem {
+    /// Recovers state from the snapshot and the journal, creating them when missing.
+    ///
+    /// An incomplete or corrupted last record of the journal, left by a crash in the middle of
+    /// a write, is cut off. A journal older than the snapshot, left by a crash in the middle
+    /// of a checkpoint, is replaced.
+    ///
+    /// # Errors
+    ///
+    /// When files cannot be accessed, or their content is inconsistent, including
+    /// [`JournalError::Corrupted`] for a damaged record that is not the last one.
+    pub fn open(
+        config: AccountingSystemConfig,
+        snapshot_path: impl Into<PathBuf>,
+        journal_path: impl Into<PathBuf>,
+        fsync: FsyncPolicy,
+    ) -> Result<(Self, RecoveryReport), JournalError> {
+        let snapshot_path = snapshot_path.into();
+        let journal_path = journal_path.into();
+
+        let (mut accounting_system, snapshot_sequence) = match File::open(&snapshot_path) {
+            Ok(file) => AccountingSystem::read_snapshot(config, BufReader::new(file))?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (AccountingSystem::with_config(config), 0),
+            Err(err) => return Err(err.into()),
+        };
+        let mut report = RecoveryReport {
+            snapshot_sequence,
+            ..RecoveryReport::default()
+        };
+
+        let journal = match OpenOptions::new().read(true).write(true).open(&journal_path) {
+            Ok(file) => Some(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let mut sequence = snapshot_sequence;
+        let journal = match journal {
+            Some(mut file) => {
+                let base_sequence = read_header(&mut file)?;
+                if base_sequence > snapshot_sequence {
+                    return Err(JournalError::MissingRecords {
+                        expected: snapshot_sequence + 1,
+                        found: base_sequence + 1,
+                    });
+                }
+                if base_sequence < snapshot_sequence {
+                    None
+                } else {
+                    let (valid_end, file_end) = replay(&mut file, &mut accounting_system, &mut sequence)?;
+                    report.replayed = sequence - snapshot_sequence;
+                    report.discarded_bytes = file_end - valid_end;
+                    if valid_end < file_end {
+                        file.set_len(valid_end)?;
+                        file.sync_all()?;
+                    }
+                    file.seek(SeekFrom::Start(valid_end))?;
+                    Some(file)
+                }
+            }
+            None => None,
+        };
+        let journal = match journal {
+            Some(file) => file,
+            None => create_journal(&journal_path, sequence)?,
+        };
+
+        let system = Self {
+            accounting_system,
+            snapshot_path,
+            journal_path,
+            journal: BufWriter::new(journal),
+            fsync,
+            sequence,
+            unsynced: 0,
+        };
+        Ok((system, report))
+    }
+
+    pub const fn accounting_system(&self) -> &AccountingSystem {
+        &self.accounting_system
+    }
+
+    /// Sequence of the last applied operation.
+    #[must_use]
+    pub const fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// Runs operation and, if it was applied, appends it to the journal according to the
+    /// [`FsyncPolicy`].
+    ///
+    /// # Errors
+    ///
+    /// [`JournalError::Transaction`] if the operation was rejected, [`JournalError::RecordTooLarge`]
+    /// if it does not fit a journal record, or [`JournalError::Io`] if it could not be journaled. In the latter case the operation is applied in memory,
+    /// but it is lost on recovery, so the system should not be used anymore.
+    pub fn run_operation(&mut self, operation: AccountingOperation) -> Result<(), JournalError> {
+        let record = encode_record(self.sequence + 1, &operation)?;
+        self.accounting_system.run_operation(operation)?;
+        self.sequence += 1;
+
+        self.journal.write_all(&record)?;
+        self.journal.flush()?;
+        self.unsynced += 1;
+        let sync = match self.fsync {
+            FsyncPolicy::Always => true,
+            FsyncPolicy::Every(records) => self.unsynced >= records.get(),
+            FsyncPolicy::Never => false,
+        };
+        if sync {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Flushes all journaled operations to the disk.
+    ///
+    /// # Errors
+    ///
+    /// When writing to the journal fails.
+    pub fn sync(&mut self) -> Result<(), JournalError> {
+        self.journal.flush()?;
+        self.journal.get_ref().sync_data()?;
+        self.unsynced = 0;
+        Ok(())
+    }
+
+    /// Replaces the snapshot with the current state and starts a new, empty journal.
+    ///
+    /// # Errors
+    ///
+    /// When writing the files fails. The previous snapshot and journal stay valid then.
+    pub fn checkpoint(&mut self) -> Result<(), JournalError> {
+        self.sync()?;
+
+        let temporary = temporary_path(&self.snapshot_path);
+        let file = File::create(&temporary)?;
+        self.accounting_system
+            .write_snapshot(BufWriter::new(&file), self.sequence)?;
+        file.sync_all()?;
+        std::fs::rename(&temporary, &self.snapshot_path)?;
+        sync_directory(&self.snapshot_path)?;
+
+        self.journal = BufWriter::new(create_journal(&self.journal_path, self.sequence)?);
+        Ok(())
+    }
+
+    pub fn into_inner(self) -> AccountingSystem {
+        self.accounting_system
+    }
+}
+
+fn temporary_path(path: &Path) -> PathBuf {
+    let mut temporary = path.as_os_str().to_owned();
+    temporary.push(".tmp");
+    PathBuf::from(temporary)
+}
+
+/// Makes renames within the directory of `path` durable.
+fn sync_directory(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    {
+        let parent = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        File::open(parent)?.sync_all()?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Atomically replaces the journal with an empty one, continuing after `base_sequence`.
+fn create_journal(path: &Path, base_sequence: u64) -> io::Result<File> {
+    let temporary = temporary_path(path);
+    let mut file = File::create(&temporary)?;
+    let mut encoder = Encoder::new(&mut file);
+    encoder.bytes(&JOURNAL_MAGIC)?;
+    encoder.u16(JOURNAL_VERSION)?;
+    encoder.u64(base_sequence)?;
+    encoder.finish()?;
+    file.sync_all()?;
+    std::fs::rename(&temporary, path)?;
+    sync_directory(path)?;
+    Ok(file)
+}
+
+/// Reads the header, returning the base sequence of the journal.
+fn read_header(file: &mut File) -> Result<u64, JournalError> {
+    let invalid_header = |err: DecodeError| match err {
+        DecodeError::Io(err) if err.kind() != io::ErrorKind::UnexpectedEof => JournalError::Io(err),
+        _ => JournalError::InvalidHeader,
+    };
+
+    file.seek(SeekFrom::Start(0))?;
+    let mut decoder = Decoder::new(BufReader::new(&*file));
+    if decoder.bytes::<8>().map_err(invalid_header)? != JOURNAL_MAGIC {
+        return Err(JournalError::InvalidHeader);
+    }
+    let version = decoder.u16().map_err(invalid_header)?;
+    if version != JOURNAL_VERSION {
+        return Err(JournalError::UnsupportedVersion {
+            found: version,
+            supported: JOURNAL_VERSION,
+        });
+    }
+    let base_sequence = decoder.u64().map_err(invalid_header)?;
+    if !decoder.verify_checksum().map_err(invalid_header)? {
+        return Err(JournalError::InvalidHeader);
+    }
+    Ok(base_sequence)
+}
+
+/// Encodes record as `[length][sequence, operation][checksum]`, with the checksum covering the length.
+fn encode_record(sequence: u64, operation: &AccountingOperation) -> Result<Vec<u8>, JournalError> {
+    let mut payload = Vec::new();
+    let mut encoder = Encoder::new(&mut payload);
+    encoder.u64(sequence)?;
+    encoder.operation(operation)?;
+    // Replay stops at a longer record, losing it together with all following ones.
+    if payload.len() > MAX_RECORD_SIZE as usize {
+        return Err(JournalError::RecordTooLarge { size: payload.len() });
+    }
+
+    let mut record = Encoder::new(Vec::with_capacity(payload.len() + 8));
+    record.len(payload.len())?;
+    record.bytes(&payload)?;
+    Ok(record.finish()?)
+}
+
+/// Replays all valid records following the header, returning the end of the last valid
+/// record and the end of the file. Anything after it has to be a single damaged record.
+fn replay(
+    file: &mut File,
+    accounting_system: &mut AccountingSystem,
+    sequence: &mut u64,
+) -> Result<(u64, u64), JournalError> {
+    let file_end = file.seek(SeekFrom::End(0))?;
+    let mut valid_end = HEADER_SIZE;
+    file.seek(SeekFrom::Start(valid_end))?;
+    let mut reader = BufReader::new(&*file);
+
+    loop {
+        let mut length = [0; 4];
+        if read_full(&mut reader, &mut length)? < length.len() {
+            break;
+        }
+        let payload_length = u32::from_le_bytes(length);
+        if payload_length > MAX_RECORD_SIZE {
+            break;
+        }
+        let mut record = vec![0; payload_length as usize + 8];
+        record[..4].copy_from_slice(&length);
+        if read_full(&mut reader, &mut record[4..])? < record.len() - 4 {
+            break;
+        }
+
+        let Some((record_sequence, operation)) = decode_record(&record) else {
+            break;
+        };
+
+        if record_sequence != *sequence + 1 {
+            return Err(JournalError::MissingRecords {
+                expected: *sequence + 1,
+                found: record_sequence,
+            });
+        }
+        if let Err(error) = accounting_system.run_operation(operation) {
+            return Err(JournalError::ReplayFailed {
+                sequence: record_sequence,
+                error,
+            });
+        }
+        *sequence = record_sequence;
+        valid_end += record.len() as u64;
+    }
+    drop(reader);
+
+    // An interrupted write damages only the last record, which is never longer than a whole record.
+    if valid_end < file_end {
+        let corrupted = JournalError::Corrupted {
+            sequence: *sequence + 1,
+        };
+        let Some(rest) = usize::try_from(file_end - valid_end)
+            .ok()
+            .filter(|rest| *rest <= MAX_RECORD_SIZE as usize + 8)
+        else {
+            return Err(corrupted);
+        };
+        let mut tail = vec![0; rest];
+        file.seek(SeekFrom::Start(valid_end))?;
+        file.read_exact(&mut tail)?;
+        if contains_record(&tail) {
+            return Err(corrupted);
+        }
+    }
+    Ok((valid_end, file_end))
+}
+
+/// Whether a valid record starts anywhere within `bytes`.
+fn contains_record(bytes: &[u8]) -> bool {
+    (0..bytes.len()).any(|start| {
+        let rest = &bytes[start..];
+        let Some(length) = rest.get(..4) else {
+            return false;
+        };
+        let length = u32::from_le_bytes(length.try_into().unwrap());
+        length <= MAX_RECORD_SIZE
+            && rest
+                .get(..length as usize + 8)
+                .is_some_and(|record| decode_record(record).is_some())
+    })
+}
+
+fn decode_record(record: &[u8]) -> Option<(u64, AccountingOperation)> {
+    let mut decoder = Decoder::new(record);
+    decoder.u32().ok()?;
+    let sequence = decoder.u64().ok()?;
+    let operation = decoder.operation().ok()?;
+    decoder.verify_checksum().ok()?.then_some((sequence, operation))
+}
+
+/// Reads until the buffer is full or the stream ends, returning the number of bytes read.
+fn read_full(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match reader.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(read)
+}
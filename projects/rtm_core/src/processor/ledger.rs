@@ -0,0 +1,372 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::models::{Amount, ClientId, Currency, TransactionId, TransactionKind};
+
+use super::{AccountingEvent, AccountingSystem, EventSink};
+
+/// Account of the double-entry [`Ledger`]. Every account holds funds in a single currency,
+/// which is appended to its name unless unspecified, e.g. `client:1:available:EUR`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[must_use]
+pub enum LedgerAccount {
+    /// `client:N:available`, funds of client `N` available for trading and withdrawals.
+    Available(ClientId, Currency),
+
+    /// `client:N:held`, disputed funds of client `N`.
+    Held(ClientId, Currency),
+
+    /// `external:bank`, counterpart of all deposits, withdrawals, chargebacks and withdrawal disputes.
+    ExternalBank(Currency),
+}
+
+impl LedgerAccount {
+    pub const fn currency(&self) -> Currency {
+        match self {
+            LedgerAccount::Available(_, currency)
+            | LedgerAccount::Held(_, currency)
+            | LedgerAccount::ExternalBank(currency) => *currency,
+        }
+    }
+}
+
+impl std::fmt::Display for LedgerAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerAccount::Available(client_id, _) => write!(f, "client:{}:available", client_id.as_u16())?,
+            LedgerAccount::Held(client_id, _) => write!(f, "client:{}:held", client_id.as_u16())?,
+            LedgerAccount::ExternalBank(_) => write!(f, "external:bank")?,
+        }
+        let currency = self.currency();
+        if !currency.is_unspecified() {
+            write!(f, ":{currency}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Transfer of a positive amount from the `debit` account to the `credit` account.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+#[must_use]
+pub struct LedgerEntry {
+    /// Position of the entry in the ledger, starting from 1.
+    pub id: u64,
+    pub transaction_id: TransactionId,
+    pub debit: LedgerAccount,
+    pub credit: LedgerAccount,
+    pub amount: Amount,
+}
+
+/// Disagreement found by [`Ledger::verify`].
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum LedgerDiscrepancy {
+    /// Balances of all ledger accounts in the currency do not sum to zero.
+    Unbalanced { currency: Currency, total: Amount },
+
+    /// Balance derived from entries differs from the balance of the accounting system.
+    BalanceMismatch {
+        account: LedgerAccount,
+        ledger: Amount,
+        accounting_system: Amount,
+    },
+
+    /// Balance of the account became too large to be represented, so it is no longer tracked.
+    Overflow { account: LedgerAccount },
+}
+
+impl std::fmt::Display for LedgerDiscrepancy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LedgerDiscrepancy::Unbalanced { currency, total } => {
+                write!(f, "ledger does not sum to zero, but to {total}")?;
+                if !currency.is_unspecified() {
+                    write!(f, " in {currency}")?;
+                }
+                Ok(())
+            }
+            LedgerDiscrepancy::BalanceMismatch {
+                account,
+                ledger,
+                accounting_system,
+            } => write!(
+                f,
+                "{account} has balance {ledger} in the ledger, but {accounting_system} in the accounting system"
+            ),
+            LedgerDiscrepancy::Overflow { account } => write!(f, "{account} balance is out of range"),
+        }
+    }
+}
+
+/// Double-entry export of the accounting system, built from its [`AccountingEvent`]s.
+///
+/// Every balance change is posted as a [`LedgerEntry`] moving funds between two accounts,
+/// and ledger balances are derived from the entries only, as credits minus debits. So client
+/// accounts have positive balances, the bank has a negative one, and all of them in the same
+/// currency always sum to zero.
+///
+/// The accounting system keeps its own balances and does not read them from the ledger, so the
+/// export is an independent reconstruction, cross-checked by [`Ledger::verify`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct Ledger {
+    balances: BTreeMap<LedgerAccount, Amount>,
+    /// Accounts whose balance overflowed, e.g. the bank after deposits of many clients.
+    overflowed: BTreeSet<LedgerAccount>,
+    entries: u64,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ledger continuing an existing accounting system, with a single opening entry for every
+    /// non-zero balance, transferring it from the bank under transaction id 0.
+    pub fn with_opening_balances(accounting_system: &AccountingSystem) -> Self {
+        let mut ledger = Self::new();
+        for account in accounting_system.iter_accounts() {
+            let transaction_id = TransactionId::from(0);
+            let client_id = account.client_id;
+            let currency = account.currency;
+            ledger.transfer(
+                transaction_id,
+                LedgerAccount::ExternalBank(currency),
+                LedgerAccount::Available(client_id, currency),
+                account.available_balance.clone(),
+            );
+            ledger.transfer(
+                transaction_id,
+                LedgerAccount::ExternalBank(currency),
+                LedgerAccount::Held(client_id, currency),
+                account.held_balance.clone(),
+            );
+        }
+        ledger
+    }
+
+    /// Posts the entry describing the event and returns it. Events not moving any funds are ignored.
+    pub fn post(&mut self, event: &AccountingEvent) -> Option<LedgerEntry> {
+        let (transaction_id, debit, credit, amount) = match event {
+            AccountingEvent::Deposited {
+                client_id,
+                transaction_id,
+                amount,
+                currency,
+                ..
+            } => (
+                transaction_id,
+                LedgerAccount::ExternalBank(*currency),
+                LedgerAccount::Available(*client_id, *currency),
+                amount,
+            ),
+            AccountingEvent::Withdrawn {
+                client_id,
+                transaction_id,
+                amount,
+                currency,
+                ..
+            } => (
+                transaction_id,
+                LedgerAccount::Available(*client_id, *currency),
+                LedgerAccount::ExternalBank(*currency),
+                amount,
+            ),
+            AccountingEvent::FundsHeld {
+                client_id,
+                transaction_id,
+                amount,
+                currency,
+                kind,
+                ..
+            } => (
+                transaction_id,
+                disputed_source(*kind, *client_id, *currency),
+                LedgerAccount::Held(*client_id, *currency),
+                amount,
+            ),
+            AccountingEvent::FundsReleased {
+                client_id,
+                transaction_id,
+                amount,
+                currency,
+                kind,
+                ..
+            } => (
+                transaction_id,
+                LedgerAccount::Held(*client_id, *currency),
+                disputed_source(*kind, *client_id, *currency),
+                amount,
+            ),
+            AccountingEvent::ChargedBack {
+                client_id,
+                transaction_id,
+                amount,
+                currency,
+                kind,
+                ..
+            } => (
+                transaction_id,
+                LedgerAccount::Held(*client_id, *currency),
+                match kind {
+                    TransactionKind::Deposit => LedgerAccount::ExternalBank(*currency),
+                    TransactionKind::Withdrawal => LedgerAccount::Available(*client_id, *currency),
+                },
+                amount,
+            ),
+            AccountingEvent::AccountOverdrawn { .. }
+            | AccountingEvent::AccountLocked { .. }
+            | AccountingEvent::AccountUnlocked { .. }
+            | AccountingEvent::AccountFrozen { .. }
+            | AccountingEvent::AccountClosed { .. } => return None,
+        };
+        self.transfer(*transaction_id, debit, credit, amount.clone())
+    }
+
+    /// Balance of the account, as credits minus debits.
+    pub fn balance(&self, account: LedgerAccount) -> Amount {
+        self.balances.get(&account).cloned().unwrap_or_default()
+    }
+
+    /// Iterates over balances of all accounts that had any entries, ordered by account.
+    pub fn iter_balances(&self) -> impl Iterator<Item = (LedgerAccount, &Amount)> {
+        self.balances.iter().map(|(account, balance)| (*account, balance))
+    }
+
+    /// Number of posted entries.
+    #[must_use]
+    pub const fn entries(&self) -> u64 {
+        self.entries
+    }
+
+    /// Checks that the ledger sums to zero in every currency, and that client balances derived from the ledger
+    /// match balances of the accounting system.
+    ///
+    /// # Errors
+    ///
+    /// All found discrepancies.
+    pub fn verify(&self, accounting_system: &AccountingSystem) -> Result<(), Vec<LedgerDiscrepancy>> {
+        let mut discrepancies = Vec::new();
+        for account in &self.overflowed {
+            discrepancies.push(LedgerDiscrepancy::Overflow { account: *account });
+        }
+
+        // The bank is summed first, so that client balances bring a balanced total back towards zero.
+        // Currencies with an overflowed account, or with a total out of range, can not be summed.
+        let mut totals = BTreeMap::new();
+        for (account, balance) in self.balances.iter().rev() {
+            let total = totals.entry(account.currency()).or_insert_with(|| Some(Amount::zero()));
+            *total = total.as_ref().and_then(|total| total.checked_add(balance));
+        }
+        for account in &self.overflowed {
+            totals.insert(account.currency(), None);
+        }
+        for (currency, total) in totals {
+            if let Some(total) = total.filter(|total| *total != Amount::zero()) {
+                discrepancies.push(LedgerDiscrepancy::Unbalanced { currency, total });
+            }
+        }
+
+        let mut expected = BTreeMap::new();
+        for account in accounting_system.iter_accounts() {
+            let (client_id, currency) = (account.client_id, account.currency);
+            expected.insert(
+                LedgerAccount::Available(client_id, currency),
+                &account.available_balance,
+            );
+            expected.insert(LedgerAccount::Held(client_id, currency), &account.held_balance);
+        }
+        let zero = Amount::zero();
+        for (account, ledger) in &self.balances {
+            if matches!(account, LedgerAccount::ExternalBank(_))
+                || expected.contains_key(account)
+                || self.overflowed.contains(account)
+            {
+                continue;
+            }
+            if *ledger != zero {
+                discrepancies.push(LedgerDiscrepancy::BalanceMismatch {
+                    account: *account,
+                    ledger: ledger.clone(),
+                    accounting_system: zero.clone(),
+                });
+            }
+        }
+        for (account, accounting_system) in expected {
+            if self.overflowed.contains(&account) {
+                continue;
+            }
+            let ledger = self.balance(account);
+            if ledger != *accounting_system {
+                discrepancies.push(LedgerDiscrepancy::BalanceMismatch {
+                    account,
+                    ledger,
+                    accounting_system: accounting_system.clone(),
+                });
+            }
+        }
+
+        if discrepancies.is_empty() {
+            Ok(())
+        } else {
+            Err(discrepancies)
+        }
+    }
+
+    /// Posts a transfer of `amount`, swapping the accounts if it is negative. Zero amounts are not posted.
+    /// Balances that would overflow are no longer tracked, and reported by [`Ledger::verify`] instead.
+    fn transfer(
+        &mut self,
+        transaction_id: TransactionId,
+        mut debit: LedgerAccount,
+        mut credit: LedgerAccount,
+        mut amount: Amount,
+    ) -> Option<LedgerEntry> {
+        let zero = Amount::zero();
+        if amount == zero {
+            return None;
+        }
+        if amount < zero {
+            std::mem::swap(&mut debit, &mut credit);
+            amount = -amount;
+        }
+
+        let debit_balance = self.balance(debit).checked_sub(&amount);
+        self.update_balance(debit, debit_balance);
+        let credit_balance = self.balance(credit).checked_add(&amount);
+        self.update_balance(credit, credit_balance);
+        self.entries += 1;
+        Some(LedgerEntry {
+            id: self.entries,
+            transaction_id,
+            debit,
+            credit,
+            amount,
+        })
+    }
+
+    fn update_balance(&mut self, account: LedgerAccount, balance: Option<Amount>) {
+        if self.overflowed.contains(&account) {
+            return;
+        }
+        if let Some(balance) = balance {
+            self.balances.insert(account, balance);
+        } else {
+            self.balances.remove(&account);
+            self.overflowed.insert(account);
+        }
+    }
+}
+
+impl EventSink for Ledger {
+    fn publish(&mut self, event: AccountingEvent) {
+        self.post(&event);
+    }
+}
+
+/// Account the disputed amount is held from: available funds for deposits, and the bank for
+/// withdrawals, as their amount already left the client.
+const fn disputed_source(kind: TransactionKind, client_id: ClientId, currency: Currency) -> LedgerAccount {
+    match kind {
+        TransactionKind::Deposit => LedgerAccount::Available(client_id, currency),
+        TransactionKind::Withdrawal => LedgerAccount::ExternalBank(currency),
+    }
+}
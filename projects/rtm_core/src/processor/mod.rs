@@ -1,8 +1,34 @@
 mod errors;
 pub use errors::*;
 
+mod events;
+pub use events::*;
+
+mod ledger;
+pub use ledger::*;
+
+mod config;
+pub use config::*;
+
+mod codec;
+
+mod transaction_id_set;
+pub use transaction_id_set::*;
+
 mod accounting_system;
 pub use accounting_system::*;
 
 mod client_account;
 pub use client_account::*;
+
+mod invariants;
+pub use invariants::*;
+
+mod sharded_accounting_system;
+pub use sharded_accounting_system::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod journal;
+pub use journal::*;
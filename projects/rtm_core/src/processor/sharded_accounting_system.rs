@@ -0,0 +1,305 @@
+use std::{
+    collections::HashMap,
+    num::NonZeroUsize,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError, mpsc},
+    thread::JoinHandle,
+};
+
+use crate::models::{AccountingOperation, TransactionId};
+
+use super::{AccountingSystem, AccountingSystemConfig, TransactionError, TransactionIdSet};
+
+const BATCH_SIZE: usize = 256;
+const QUEUED_BATCHES: usize = 16;
+
+struct Job<T> {
+    sequence: u64,
+    tag: T,
+    operation: AccountingOperation,
+    already_applied: bool,
+}
+
+struct InFlight {
+    shard: usize,
+    count: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ShardError {
+    /// Worker of the shard panicked, so the state of its clients is lost.
+    WorkerPanicked { shard: usize },
+}
+
+impl std::fmt::Display for ShardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShardError::WorkerPanicked { shard } => write!(f, "worker of shard {shard} panicked"),
+        }
+    }
+}
+
+impl std::error::Error for ShardError {}
+
+#[derive(Default)]
+struct RegistryState {
+    seen_transactions: TransactionIdSet,
+    in_flight: HashMap<TransactionId, InFlight>,
+    waiting: bool,
+    failed_shard: Option<usize>,
+}
+
+impl RegistryState {
+    fn check(&self) -> Result<(), ShardError> {
+        match self.failed_shard {
+            Some(shard) => Err(ShardError::WorkerPanicked { shard }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Global view over transaction ids, shared by all shards.
+#[derive(Default)]
+struct Registry {
+    state: Mutex<RegistryState>,
+    completed: Condvar,
+}
+
+impl Registry {
+    /// A poisoned state is still consistent, as the panicking worker is recorded in it by [`WorkerGuard`].
+    fn lock(&self) -> MutexGuard<'_, RegistryState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn complete(&self, transaction_id: TransactionId, applied: bool) {
+        let mut state = self.lock();
+        if applied {
+            state.seen_transactions.insert(transaction_id);
+        }
+        let in_flight = state.in_flight.get_mut(&transaction_id).unwrap();
+        in_flight.count -= 1;
+        if in_flight.count == 0 {
+            state.in_flight.remove(&transaction_id);
+            if state.waiting {
+                state.waiting = false;
+                self.completed.notify_all();
+            }
+        }
+    }
+}
+
+/// Marks the shard as failed when its worker panics, waking up the producer waiting for it.
+struct WorkerGuard<'a> {
+    registry: &'a Registry,
+    shard: usize,
+}
+
+impl Drop for WorkerGuard<'_> {
+    fn drop(&mut self) {
+        if std::thread::panicking() {
+            self.registry.lock().failed_shard.get_or_insert(self.shard);
+            self.registry.completed.notify_all();
+        }
+    }
+}
+
+type WorkerResult<T> = (AccountingSystem, Vec<(u64, T, TransactionError)>);
+
+/// Runs operations on multiple threads, with clients partitioned between shards
+/// by their [`ClientId`](crate::models::ClientId).
+///
+/// Operations of a single client are applied in submission order by the same shard.
+/// Transactions sharing an id but belonging to different shards are additionally
+/// applied in submission order, so [`TransactionError::DuplicateTransaction`] is
+/// enforced globally and the final state is exactly the same as if all operations
+/// were passed to a single [`AccountingSystem`].
+///
+/// Each operation carries a caller-provided tag, that is returned together with
+/// the error if the operation is rejected.
+///
+/// If a worker panics, [`ShardedAccountingSystem::run_operation`] or at the latest
+/// [`ShardedAccountingSystem::finish`] fails with [`ShardError::WorkerPanicked`].
+#[must_use]
+pub struct ShardedAccountingSystem<T: Send + 'static> {
+    config: AccountingSystemConfig,
+    registry: Arc<Registry>,
+    senders: Vec<mpsc::SyncSender<Vec<Job<T>>>>,
+    batches: Vec<Vec<Job<T>>>,
+    workers: Vec<JoinHandle<WorkerResult<T>>>,
+    next_sequence: u64,
+}
+
+impl<T: Send + 'static> ShardedAccountingSystem<T> {
+    pub fn new(config: AccountingSystemConfig, shards: NonZeroUsize) -> Self {
+        Self::from_system(AccountingSystem::with_config(config), shards)
+    }
+
+    /// Continues processing on top of the existing accounting system state.
+    #[allow(clippy::missing_panics_doc)]
+    pub fn from_system(accounting_system: AccountingSystem, shards: NonZeroUsize) -> Self {
+        let config = accounting_system.config().clone();
+        let (parts, seen_transactions) = accounting_system.split(shards.get());
+        let registry = Arc::new(Registry {
+            state: Mutex::new(RegistryState {
+                seen_transactions,
+                ..RegistryState::default()
+            }),
+            completed: Condvar::new(),
+        });
+        let mut senders = Vec::with_capacity(shards.get());
+        let mut workers = Vec::with_capacity(shards.get());
+        for (index, part) in parts.into_iter().enumerate() {
+            let (sender, receiver) = mpsc::sync_channel(QUEUED_BATCHES);
+            let worker_registry = registry.clone();
+            let worker = std::thread::Builder::new()
+                .name(format!("rtm-shard-{index}"))
+                .spawn(move || run_worker(part, &receiver, &worker_registry, index))
+                .unwrap();
+            senders.push(sender);
+            workers.push(worker);
+        }
+        Self {
+            config,
+            registry,
+            senders,
+            batches: (0..shards.get()).map(|_| Vec::with_capacity(BATCH_SIZE)).collect(),
+            workers,
+            next_sequence: 0,
+        }
+    }
+
+    /// Schedules operation to be run by the shard owning its client.
+    ///
+    /// # Errors
+    ///
+    /// [`ShardError::WorkerPanicked`] if a worker this operation depends on panicked.
+    pub fn run_operation(&mut self, tag: T, operation: AccountingOperation) -> Result<(), ShardError> {
+        let shard = usize::from(operation.client_id().as_u16()) % self.senders.len();
+
+        let already_applied = match &operation {
+            AccountingOperation::Transaction { transaction } => self.reserve(transaction.id(), shard)?,
+            _ => false,
+        };
+
+        self.batches[shard].push(Job {
+            sequence: self.next_sequence,
+            tag,
+            operation,
+            already_applied,
+        });
+        self.next_sequence += 1;
+
+        if self.batches[shard].len() >= BATCH_SIZE {
+            self.flush(shard)?;
+        }
+        Ok(())
+    }
+
+    /// Waits for all scheduled operations and merges shards into a single [`AccountingSystem`].
+    /// Rejected operations are returned in submission order.
+    ///
+    /// # Errors
+    ///
+    /// [`ShardError::WorkerPanicked`] if any worker panicked.
+    pub fn finish(mut self) -> Result<(AccountingSystem, Vec<(T, TransactionError)>), ShardError> {
+        self.flush_all()?;
+        self.senders.clear();
+
+        let mut parts = Vec::with_capacity(self.workers.len());
+        let mut rejections = Vec::new();
+        for (shard, worker) in self.workers.drain(..).enumerate() {
+            let (part, part_rejections) = worker.join().map_err(|_| ShardError::WorkerPanicked { shard })?;
+            parts.push(part);
+            rejections.extend(part_rejections);
+        }
+        rejections.sort_unstable_by_key(|(sequence, _, _)| *sequence);
+
+        let registry = std::mem::take(&mut *self.registry.lock());
+        let accounting_system = AccountingSystem::merge(self.config.clone(), parts, registry.seen_transactions);
+        let rejections = rejections.into_iter().map(|(_, tag, error)| (tag, error)).collect();
+        Ok((accounting_system, rejections))
+    }
+
+    /// Registers transaction id as being processed by the shard. Blocks until transactions with
+    /// the same id scheduled on other shards are done, and returns whether a transaction with
+    /// that id was already applied by any shard.
+    fn reserve(&mut self, transaction_id: TransactionId, shard: usize) -> Result<bool, ShardError> {
+        let conflicts = |state: &RegistryState| {
+            state
+                .in_flight
+                .get(&transaction_id)
+                .is_some_and(|in_flight| in_flight.shard != shard)
+        };
+
+        let mut state = self.registry.lock();
+        if conflicts(&state) {
+            drop(state);
+            self.flush_all()?;
+            state = self.registry.lock();
+            while conflicts(&state) {
+                // The conflicting transaction never completes if its worker is gone.
+                state.check()?;
+                state.waiting = true;
+                state = self
+                    .registry
+                    .completed
+                    .wait(state)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+
+        state
+            .in_flight
+            .entry(transaction_id)
+            .or_insert(InFlight { shard, count: 0 })
+            .count += 1;
+        Ok(state.seen_transactions.contains(transaction_id))
+    }
+
+    fn flush(&mut self, shard: usize) -> Result<(), ShardError> {
+        if self.batches[shard].is_empty() {
+            return Ok(());
+        }
+        let batch = std::mem::replace(&mut self.batches[shard], Vec::with_capacity(BATCH_SIZE));
+        // Sending fails only once the worker is gone.
+        self.senders[shard]
+            .send(batch)
+            .map_err(|_| ShardError::WorkerPanicked { shard })
+    }
+
+    fn flush_all(&mut self) -> Result<(), ShardError> {
+        for shard in 0..self.batches.len() {
+            self.flush(shard)?;
+        }
+        Ok(())
+    }
+}
+
+fn run_worker<T>(
+    mut accounting_system: AccountingSystem,
+    receiver: &mpsc::Receiver<Vec<Job<T>>>,
+    registry: &Registry,
+    shard: usize,
+) -> WorkerResult<T> {
+    let _guard = WorkerGuard { registry, shard };
+    let mut rejections = Vec::new();
+    for batch in receiver {
+        for job in batch {
+            let transaction_id = match &job.operation {
+                AccountingOperation::Transaction { transaction } => Some(transaction.id()),
+                _ => None,
+            };
+            if let (Some(transaction_id), true) = (transaction_id, job.already_applied) {
+                accounting_system.mark_seen(transaction_id);
+            }
+
+            let result = accounting_system.run_operation(job.operation);
+            if let Some(transaction_id) = transaction_id {
+                registry.complete(transaction_id, result.is_ok());
+            }
+            if let Err(error) = result {
+                rejections.push((job.sequence, job.tag, error));
+            }
+        }
+    }
+    (accounting_system, rejections)
+}
@@ -0,0 +1,327 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    io::{Read, Write},
+};
+
+use crate::models::{ClientId, Timestamp, TransactionId};
+
+use super::{
+    AccountingSystem, AccountingSystemConfig, ClientAccount, ClientAccountState, TransactionIdSet,
+    codec::{DecodeError, Decoder, Encoder},
+    transaction_id_set::PAGE_WORDS,
+};
+
+const SNAPSHOT_MAGIC: [u8; 8] = *b"RTMSNAP\0";
+
+/// Version of the snapshot format written by [`AccountingSystem::save_snapshot`].
+/// Only snapshots of this version can be loaded.
+pub const SNAPSHOT_VERSION: u16 = 1;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(std::io::Error),
+
+    /// Stream does not start with the snapshot header.
+    InvalidHeader,
+
+    /// Snapshot was written in a format version other than the supported one.
+    UnsupportedVersion {
+        found: u16,
+        supported: u16,
+    },
+
+    /// Snapshot content does not match its checksum.
+    ChecksumMismatch,
+
+    /// Snapshot content is inconsistent, even though its checksum matches.
+    Corrupted {
+        reason: &'static str,
+    },
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{err}"),
+            SnapshotError::InvalidHeader => write!(f, "not an rtm snapshot"),
+            SnapshotError::UnsupportedVersion { found, supported } => write!(
+                f,
+                "snapshot version {found} is not supported, the supported version is {supported}"
+            ),
+            SnapshotError::ChecksumMismatch => write!(f, "snapshot checksum mismatch"),
+            SnapshotError::Corrupted { reason } => write!(f, "corrupted snapshot: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+impl From<std::io::Error> for SnapshotError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DecodeError> for SnapshotError {
+    fn from(value: DecodeError) -> Self {
+        match value {
+            DecodeError::Io(err) => Self::Io(err),
+            DecodeError::Invalid(reason) => Self::Corrupted { reason },
+        }
+    }
+}
+
+impl AccountingSystem {
+    /// Writes the complete state: all client accounts, with their retained and disputed
+    /// transactions, and all processed transaction ids. Config is not part of the snapshot.
+    ///
+    /// # Errors
+    ///
+    /// [`SnapshotError::Io`] when writing to the stream fails.
+    pub fn save_snapshot(&self, stream: impl Write) -> Result<(), SnapshotError> {
+        self.write_snapshot(stream, 0)
+    }
+
+    /// Restores state written by [`AccountingSystem::save_snapshot`]. The stream is read
+    /// in small chunks, so it should be buffered.
+    ///
+    /// # Errors
+    ///
+    /// When the stream fails, or does not contain a valid snapshot of a supported version.
+    pub fn load_snapshot(config: AccountingSystemConfig, stream: impl Read) -> Result<Self, SnapshotError> {
+        Ok(Self::read_snapshot(config, stream)?.0)
+    }
+
+    /// Writes snapshot including all journal records up to `journal_sequence`.
+    pub(super) fn write_snapshot(&self, stream: impl Write, journal_sequence: u64) -> Result<(), SnapshotError> {
+        let mut encoder = Encoder::new(stream);
+        encoder.bytes(&SNAPSHOT_MAGIC)?;
+        encoder.u16(SNAPSHOT_VERSION)?;
+        encoder.u64(journal_sequence)?;
+
+        let pages = self.seen_transactions.pages().collect::<Vec<_>>();
+        encoder.len(pages.len())?;
+        for (index, bits) in pages {
+            encoder.u32(index)?;
+            match bits {
+                None => encoder.u8(1)?,
+                Some(bits) => {
+                    encoder.u8(0)?;
+                    for word in bits {
+                        encoder.u64(*word)?;
+                    }
+                }
+            }
+        }
+
+        encoder.len(self.client_accounts.len())?;
+        for account in self.client_accounts.values() {
+            encoder.u16(account.client_id.as_u16())?;
+            encoder.currency(account.currency)?;
+            encoder.amount(&account.available_balance)?;
+            encoder.amount(&account.held_balance)?;
+            encoder.u8(match account.state {
+                ClientAccountState::Normal => 0,
+                ClientAccountState::Locked => 1,
+                ClientAccountState::Frozen => 2,
+                ClientAccountState::Closed => 3,
+            })?;
+
+            let transactions = account.retained_transactions();
+            encoder.len(transactions.len())?;
+            for transaction in transactions {
+                encoder.transaction(transaction)?;
+            }
+
+            let mut disputed = account.disputed_transactions.iter().copied().collect::<Vec<_>>();
+            disputed.sort_unstable();
+            encoder.len(disputed.len())?;
+            for transaction_id in disputed {
+                encoder.u32(transaction_id.as_u32())?;
+            }
+
+            let mut partial_holds = account.partial_holds.iter().collect::<Vec<_>>();
+            partial_holds.sort_unstable_by_key(|(transaction_id, _)| **transaction_id);
+            encoder.len(partial_holds.len())?;
+            for (transaction_id, amount) in partial_holds {
+                encoder.u32(transaction_id.as_u32())?;
+                encoder.amount(amount)?;
+            }
+
+            let mut dispute_timestamps = account.dispute_timestamps.iter().collect::<Vec<_>>();
+            dispute_timestamps.sort_unstable_by_key(|(transaction_id, _)| **transaction_id);
+            encoder.len(dispute_timestamps.len())?;
+            for (transaction_id, timestamp) in dispute_timestamps {
+                encoder.u32(transaction_id.as_u32())?;
+                encoder.u64(timestamp.as_secs())?;
+            }
+
+            let mut charged_back = account.charged_back_transactions.iter().copied().collect::<Vec<_>>();
+            charged_back.sort_unstable();
+            encoder.len(charged_back.len())?;
+            for transaction_id in charged_back {
+                encoder.u32(transaction_id.as_u32())?;
+            }
+        }
+
+        encoder.finish()?.flush()?;
+        Ok(())
+    }
+
+    /// Reads snapshot together with its journal sequence.
+    pub(super) fn read_snapshot(
+        config: AccountingSystemConfig,
+        stream: impl Read,
+    ) -> Result<(Self, u64), SnapshotError> {
+        let mut decoder = Decoder::new(stream);
+        if decoder.bytes::<8>().ok() != Some(SNAPSHOT_MAGIC) {
+            return Err(SnapshotError::InvalidHeader);
+        }
+        let version = decoder.u16()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion {
+                found: version,
+                supported: SNAPSHOT_VERSION,
+            });
+        }
+        let journal_sequence = decoder.u64()?;
+
+        let mut seen_transactions = TransactionIdSet::new();
+        let mut bits = Box::new([0u64; PAGE_WORDS]);
+        for _ in 0..decoder.len()? {
+            let index = decoder.u32()?;
+            match decoder.u8()? {
+                0 => {
+                    for word in bits.iter_mut() {
+                        *word = decoder.u64()?;
+                    }
+                    seen_transactions.set_page(index, Some(&bits));
+                }
+                1 => seen_transactions.set_page(index, None),
+                _ => return Err(DecodeError::Invalid("invalid page kind").into()),
+            }
+        }
+
+        // Eviction order is only needed within a dispute window.
+        let ordered = config.dispute_window.is_some();
+        let mut client_accounts = BTreeMap::new();
+        for _ in 0..decoder.len()? {
+            let account = read_account(&mut decoder, &seen_transactions, ordered)?;
+            if client_accounts
+                .insert((account.client_id, account.currency), account)
+                .is_some()
+            {
+                return Err(DecodeError::Invalid("duplicate client account").into());
+            }
+        }
+
+        if !decoder.verify_checksum()? {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        let accounting_system = Self {
+            config,
+            client_accounts,
+            seen_transactions,
+        };
+        Ok((accounting_system, journal_sequence))
+    }
+}
+
+fn read_account(
+    decoder: &mut Decoder<impl Read>,
+    seen_transactions: &TransactionIdSet,
+    ordered: bool,
+) -> Result<ClientAccount, DecodeError> {
+    let mut account = ClientAccount::new(ClientId::from(decoder.u16()?));
+    account.currency = decoder.currency()?;
+    account.available_balance = decoder.amount()?;
+    account.held_balance = decoder.amount()?;
+    account.state = match decoder.u8()? {
+        0 => ClientAccountState::Normal,
+        1 => ClientAccountState::Locked,
+        2 => ClientAccountState::Frozen,
+        3 => ClientAccountState::Closed,
+        _ => return Err(DecodeError::Invalid("invalid account state")),
+    };
+
+    let count = decoder.len()?;
+    let mut transactions = HashMap::new();
+    let mut retention_order = VecDeque::new();
+    for _ in 0..count {
+        let transaction = decoder.transaction()?;
+        let transaction_id = transaction.id();
+        if transaction.client_id() != account.client_id {
+            return Err(DecodeError::Invalid("retained transaction of a different client"));
+        }
+        if transaction.currency() != account.currency {
+            return Err(DecodeError::Invalid("retained transaction in a different currency"));
+        }
+        if !seen_transactions.contains(transaction_id) {
+            return Err(DecodeError::Invalid("retained transaction was never processed"));
+        }
+        if transactions.insert(transaction_id, transaction).is_some() {
+            return Err(DecodeError::Invalid("duplicate retained transaction"));
+        }
+        if ordered {
+            retention_order.push_back(transaction_id);
+        }
+    }
+
+    let count = decoder.len()?;
+    let mut disputed_transactions = HashSet::new();
+    for _ in 0..count {
+        let transaction_id = TransactionId::from(decoder.u32()?);
+        if !transactions.contains_key(&transaction_id) {
+            return Err(DecodeError::Invalid("disputed transaction is not retained"));
+        }
+        disputed_transactions.insert(transaction_id);
+    }
+
+    let mut partial_holds = HashMap::new();
+    for _ in 0..decoder.len()? {
+        let transaction_id = TransactionId::from(decoder.u32()?);
+        if !disputed_transactions.contains(&transaction_id) {
+            return Err(DecodeError::Invalid("partially held transaction is not disputed"));
+        }
+        if partial_holds.insert(transaction_id, decoder.amount()?).is_some() {
+            return Err(DecodeError::Invalid("duplicate partial hold"));
+        }
+    }
+
+    let mut dispute_timestamps = HashMap::new();
+    for _ in 0..decoder.len()? {
+        let transaction_id = TransactionId::from(decoder.u32()?);
+        if !disputed_transactions.contains(&transaction_id) {
+            return Err(DecodeError::Invalid(
+                "dispute timestamp of a transaction that is not disputed",
+            ));
+        }
+        if dispute_timestamps
+            .insert(transaction_id, Timestamp::from(decoder.u64()?))
+            .is_some()
+        {
+            return Err(DecodeError::Invalid("duplicate dispute timestamp"));
+        }
+    }
+
+    let mut charged_back_transactions = HashSet::new();
+    for _ in 0..decoder.len()? {
+        let transaction_id = TransactionId::from(decoder.u32()?);
+        if !transactions.contains_key(&transaction_id) {
+            return Err(DecodeError::Invalid("charged back transaction is not retained"));
+        }
+        if disputed_transactions.contains(&transaction_id) {
+            return Err(DecodeError::Invalid("charged back transaction is disputed"));
+        }
+        charged_back_transactions.insert(transaction_id);
+    }
+
+    account.transactions = transactions;
+    account.retention_order = retention_order;
+    account.disputed_transactions = disputed_transactions;
+    account.partial_holds = partial_holds;
+    account.dispute_timestamps = dispute_timestamps;
+    account.charged_back_transactions = charged_back_transactions;
+    Ok(account)
+}
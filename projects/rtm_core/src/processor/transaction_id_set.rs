@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+
+use crate::models::TransactionId;
+
+const PAGE_BITS: u32 = 16;
+const PAGE_SIZE: u32 = 1 << PAGE_BITS;
+const WORD_BITS: u32 = u64::BITS;
+pub(super) const PAGE_WORDS: usize = (PAGE_SIZE / WORD_BITS) as usize;
+
+#[derive(Debug, Clone)]
+enum Page {
+    Partial { bits: Box<[u64; PAGE_WORDS]>, len: u32 },
+    Full,
+}
+
+/// Compact set of [`TransactionId`]s.
+///
+/// The u32 id space is split into pages of 65536 ids, each being a lazily allocated
+/// 8KB bitmap. Once a page is completely filled, its bitmap is dropped, so densely
+/// used id ranges (e.g. sequential ids) take constant memory, while the worst case
+/// is bounded by 512MB.
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct TransactionIdSet {
+    pages: HashMap<u32, Page>,
+    len: u64,
+}
+
+impl TransactionIdSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of ids in the set.
+    #[must_use]
+    pub const fn len(&self) -> u64 {
+        self.len
+    }
+
+    #[must_use]
+    pub fn contains(&self, id: TransactionId) -> bool {
+        let (page_index, word, mask) = split(id);
+        match self.pages.get(&page_index) {
+            None => false,
+            Some(Page::Full) => true,
+            Some(Page::Partial { bits, .. }) => bits[word] & mask != 0,
+        }
+    }
+
+    /// Adds id to the set. Returns `false` if it was already present.
+    pub fn insert(&mut self, id: TransactionId) -> bool {
+        let (page_index, word, mask) = split(id);
+        let page = self.pages.entry(page_index).or_insert_with(|| Page::Partial {
+            bits: Box::new([0; PAGE_WORDS]),
+            len: 0,
+        });
+        let Page::Partial { bits, len } = page else {
+            return false;
+        };
+        if bits[word] & mask != 0 {
+            return false;
+        }
+        bits[word] |= mask;
+        *len += 1;
+        if *len == PAGE_SIZE {
+            *page = Page::Full;
+        }
+        self.len += 1;
+        true
+    }
+}
+
+/// Raw content of a single page, `None` meaning that all its ids are present.
+pub(super) type PageBits<'a> = Option<&'a [u64; PAGE_WORDS]>;
+
+impl TransactionIdSet {
+    /// Iterates over non-empty pages, ordered by their index.
+    pub(super) fn pages(&self) -> impl Iterator<Item = (u32, PageBits<'_>)> {
+        let mut indexes = self.pages.keys().copied().collect::<Vec<_>>();
+        indexes.sort_unstable();
+        indexes.into_iter().map(|index| {
+            let bits = match &self.pages[&index] {
+                Page::Full => None,
+                Page::Partial { bits, .. } => Some(&**bits),
+            };
+            (index, bits)
+        })
+    }
+
+    /// Replaces page content, as returned by [`TransactionIdSet::pages`].
+    pub(super) fn set_page(&mut self, index: u32, bits: PageBits<'_>) {
+        let page = match bits {
+            None => Page::Full,
+            Some(bits) => {
+                let len = bits.iter().map(|word| word.count_ones()).sum();
+                if len == PAGE_SIZE {
+                    Page::Full
+                } else {
+                    Page::Partial {
+                        bits: Box::new(*bits),
+                        len,
+                    }
+                }
+            }
+        };
+        let added = match &page {
+            Page::Full => PAGE_SIZE,
+            Page::Partial { len, .. } => *len,
+        };
+        let removed = match self.pages.insert(index, page) {
+            None => 0,
+            Some(Page::Full) => PAGE_SIZE,
+            Some(Page::Partial { len, .. }) => len,
+        };
+        self.len = self.len + u64::from(added) - u64::from(removed);
+    }
+}
+
+fn split(id: TransactionId) -> (u32, usize, u64) {
+    let value = id.as_u32();
+    let offset = value & (PAGE_SIZE - 1);
+    (
+        value >> PAGE_BITS,
+        (offset / WORD_BITS) as usize,
+        1 << (offset % WORD_BITS),
+    )
+}
@@ -0,0 +1,179 @@
+use std::sync::Arc;
+
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    models::{AccountingOperation, AmountPolicies, ClientId, Currency},
+    processor::{AccountingSystem, ClientAccount, ClientAccountSnapshot, TransactionError},
+};
+
+use super::ServiceError;
+
+const QUEUE_SIZE: usize = 1024;
+
+enum Command {
+    Run {
+        operation: AccountingOperation,
+        reply: Option<oneshot::Sender<Result<(), TransactionError>>>,
+    },
+    Account {
+        client_id: ClientId,
+        currency: Currency,
+        reply: oneshot::Sender<Option<ClientAccountSnapshot>>,
+    },
+    Accounts {
+        reply: oneshot::Sender<Vec<ClientAccountSnapshot>>,
+    },
+}
+
+/// Runs an [`AccountingSystem`] in a dedicated tokio task.
+///
+/// All requests go through a single queue, so operations are applied one at a time and
+/// operations sent through the same [`AccountingHandle`] (or the same attached source)
+/// are applied in the order they were sent. Operations from different sources interleave.
+pub struct AccountingService;
+
+impl AccountingService {
+    /// Spawns the service task on the current tokio runtime. The task finishes, returning
+    /// the accounting system, once all handles and attached sources are dropped.
+    ///
+    /// # Panics
+    ///
+    /// When called outside of a tokio runtime.
+    pub fn spawn(accounting_system: AccountingSystem) -> (AccountingHandle, JoinHandle<AccountingSystem>) {
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        let amount_policies = Arc::new(accounting_system.config().amount_policies.clone());
+        let task = tokio::spawn(run(accounting_system, receiver));
+        (
+            AccountingHandle {
+                sender,
+                amount_policies,
+            },
+            task,
+        )
+    }
+}
+
+/// Cheaply clonable handle for sending requests to the [`AccountingService`].
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct AccountingHandle {
+    sender: mpsc::Sender<Command>,
+    amount_policies: Arc<AmountPolicies>,
+}
+
+impl AccountingHandle {
+    /// Amount policies of the accounting system, to parse amounts of operations sent to it.
+    pub fn amount_policies(&self) -> &AmountPolicies {
+        &self.amount_policies
+    }
+
+    /// Runs operation and waits for its result.
+    ///
+    /// # Errors
+    ///
+    /// [`ServiceError::Transaction`] if the operation was rejected, or [`ServiceError::Stopped`].
+    pub async fn run_operation(&self, operation: AccountingOperation) -> Result<(), ServiceError> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Run {
+            operation,
+            reply: Some(reply),
+        })
+        .await?;
+        response.await.map_err(|_| ServiceError::Stopped)??;
+        Ok(())
+    }
+
+    /// Queues operation without waiting for its result.
+    ///
+    /// # Errors
+    ///
+    /// [`ServiceError::Stopped`] if the service is no longer running.
+    pub async fn submit(&self, operation: AccountingOperation) -> Result<(), ServiceError> {
+        self.send(Command::Run { operation, reply: None }).await
+    }
+
+    /// Spawns a task queueing all operations received from `source`, without waiting for their
+    /// results. The task finishes once `source` is closed or the service is stopped.
+    ///
+    /// # Panics
+    ///
+    /// When called outside of a tokio runtime.
+    #[must_use = "dropping the handle does not stop the task"]
+    pub fn attach(&self, mut source: mpsc::Receiver<AccountingOperation>) -> JoinHandle<()> {
+        let handle = self.clone();
+        tokio::spawn(async move {
+            while let Some(operation) = source.recv().await {
+                if handle.submit(operation).await.is_err() {
+                    break;
+                }
+            }
+        })
+    }
+
+    /// Returns the current state of the given client account in the given currency, if it exists.
+    ///
+    /// # Errors
+    ///
+    /// [`ServiceError::Stopped`] if the service is no longer running.
+    pub async fn account(
+        &self,
+        client_id: ClientId,
+        currency: Currency,
+    ) -> Result<Option<ClientAccountSnapshot>, ServiceError> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Account {
+            client_id,
+            currency,
+            reply,
+        })
+        .await?;
+        response.await.map_err(|_| ServiceError::Stopped)
+    }
+
+    /// Returns the current state of all client accounts, ordered by client id and currency.
+    ///
+    /// # Errors
+    ///
+    /// [`ServiceError::Stopped`] if the service is no longer running.
+    pub async fn accounts(&self) -> Result<Vec<ClientAccountSnapshot>, ServiceError> {
+        let (reply, response) = oneshot::channel();
+        self.send(Command::Accounts { reply }).await?;
+        response.await.map_err(|_| ServiceError::Stopped)
+    }
+
+    async fn send(&self, command: Command) -> Result<(), ServiceError> {
+        self.sender.send(command).await.map_err(|_| ServiceError::Stopped)
+    }
+}
+
+async fn run(mut accounting_system: AccountingSystem, mut receiver: mpsc::Receiver<Command>) -> AccountingSystem {
+    while let Some(command) = receiver.recv().await {
+        match command {
+            Command::Run { operation, reply } => {
+                let result = accounting_system.run_operation(operation);
+                if let Some(reply) = reply {
+                    let _ = reply.send(result);
+                }
+            }
+            Command::Account {
+                client_id,
+                currency,
+                reply,
+            } => {
+                let _ = reply.send(
+                    accounting_system
+                        .account(client_id, currency)
+                        .map(ClientAccount::snapshot),
+                );
+            }
+            Command::Accounts { reply } => {
+                let _ = reply.send(accounting_system.iter_accounts().map(ClientAccount::snapshot).collect());
+            }
+        }
+    }
+    accounting_system
+}
@@ -0,0 +1,18 @@
+use crate::processor::TransactionError;
+
+/// Represents possible errors of the [`AccountingHandle`](super::AccountingHandle) requests.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[must_use]
+pub enum ServiceError {
+    /// Operation was rejected by the accounting system.
+    Transaction(TransactionError),
+
+    /// Accounting service is no longer running.
+    Stopped,
+}
+
+impl From<TransactionError> for ServiceError {
+    fn from(value: TransactionError) -> Self {
+        Self::Transaction(value)
+    }
+}
@@ -0,0 +1,5 @@
+mod errors;
+pub use errors::*;
+
+mod accounting_service;
+pub use accounting_service::*;
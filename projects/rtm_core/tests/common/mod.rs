@@ -0,0 +1,100 @@
+//! Helpers shared by the integration tests. Every test crate uses only some of them.
+#![allow(dead_code)]
+
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind},
+    processor::AccountingSystem,
+};
+
+pub fn amount(value: &str) -> Amount {
+    Amount::try_from(value).unwrap()
+}
+
+pub fn deposit(client_id: u16, id: u32, amount: impl Into<Amount>) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(
+            ClientId::from(client_id),
+            TransactionId::from(id),
+            amount.into(),
+            TransactionKind::Deposit,
+        ),
+    }
+}
+
+pub fn withdrawal(client_id: u16, id: u32, amount: impl Into<Amount>) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(
+            ClientId::from(client_id),
+            TransactionId::from(id),
+            amount.into(),
+            TransactionKind::Withdrawal,
+        ),
+    }
+}
+
+pub fn dispute(client_id: u16, id: u32) -> AccountingOperation {
+    AccountingOperation::Dispute {
+        client_id: ClientId::from(client_id),
+        ref_id: TransactionId::from(id),
+        currency: None,
+        timestamp: None,
+    }
+}
+
+pub fn resolve(client_id: u16, id: u32) -> AccountingOperation {
+    AccountingOperation::Resolve {
+        client_id: ClientId::from(client_id),
+        ref_id: TransactionId::from(id),
+        currency: None,
+        timestamp: None,
+    }
+}
+
+pub fn chargeback(client_id: u16, id: u32) -> AccountingOperation {
+    AccountingOperation::Chargeback {
+        client_id: ClientId::from(client_id),
+        ref_id: TransactionId::from(id),
+        currency: None,
+        timestamp: None,
+    }
+}
+
+/// Deterministic pseudo-random operations over `clients` clients and `ids` transaction ids,
+/// so that duplicates, cross-client references and locked accounts occur often.
+pub fn operations(count: usize, clients: u64, ids: u64, mut seed: u64) -> Vec<AccountingOperation> {
+    let mut next = move |bound: u64| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) % bound
+    };
+    (0..count)
+        .map(|_| {
+            let client_id = u16::try_from(next(clients)).unwrap();
+            let id = u32::try_from(next(ids)).unwrap();
+            let amount = amount(&format!("{}.{:04}", next(100), next(10_000)));
+            match next(10) {
+                0..=3 => deposit(client_id, id, amount),
+                4..=5 => withdrawal(client_id, id, amount),
+                6..=7 => dispute(client_id, id),
+                8 => resolve(client_id, id),
+                _ => chargeback(client_id, id),
+            }
+        })
+        .collect()
+}
+
+/// Balances and state of every account, for comparing two accounting systems.
+pub fn describe(accounting_system: &AccountingSystem) -> Vec<String> {
+    accounting_system
+        .iter_accounts()
+        .map(|account| {
+            format!(
+                "{},{},{},{},{:?}",
+                account.client_id.as_u16(),
+                account.currency,
+                account.available_balance,
+                account.held_balance,
+                account.state
+            )
+        })
+        .collect()
+}
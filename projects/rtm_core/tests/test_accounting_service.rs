@@ -0,0 +1,81 @@
+use rtm_core::{
+    models::{Amount, ClientId, Currency, TransactionId},
+    processor::{AccountingSystem, ClientAccountState, TransactionError},
+    service::{AccountingService, ServiceError},
+};
+use tokio::sync::mpsc;
+
+mod common;
+
+use common::deposit;
+
+#[tokio::test]
+async fn test_accounting_service_run_operation() {
+    let (handle, task) = AccountingService::spawn(AccountingSystem::new());
+    handle.run_operation(deposit(1, 1, 10)).await.unwrap();
+
+    let result = handle.run_operation(deposit(2, 1, 10)).await;
+    assert_eq!(
+        result,
+        Err(ServiceError::Transaction(TransactionError::DuplicateTransaction {
+            cause_id: TransactionId::from(1)
+        }))
+    );
+
+    let account = handle
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.available_balance, Amount::from(10));
+    assert_eq!(account.held_balance, Amount::zero());
+    assert_eq!(account.state, ClientAccountState::Normal);
+    assert_eq!(
+        handle.account(ClientId::from(3), Currency::UNSPECIFIED).await.unwrap(),
+        None
+    );
+
+    drop(handle);
+    let accounting_system = task.await.unwrap();
+    assert_eq!(accounting_system.iter_accounts().count(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_accounting_service_concurrent_sources() {
+    let (handle, task) = AccountingService::spawn(AccountingSystem::new());
+
+    let mut producers = Vec::new();
+    let mut sources = Vec::new();
+    for source in 0..50u32 {
+        let (sender, receiver) = mpsc::channel(8);
+        sources.push(handle.attach(receiver));
+        producers.push(tokio::spawn(async move {
+            for index in 0..100u32 {
+                let client_id = u16::try_from(index % 5).unwrap();
+                sender.send(deposit(client_id, source * 100 + index, 1)).await.unwrap();
+            }
+        }));
+    }
+    for producer in producers {
+        producer.await.unwrap();
+    }
+    for source in sources {
+        source.await.unwrap();
+    }
+
+    let accounts = handle.accounts().await.unwrap();
+    assert_eq!(
+        accounts
+            .iter()
+            .map(|account| account.client_id.as_u16())
+            .collect::<Vec<_>>(),
+        vec![0, 1, 2, 3, 4]
+    );
+    for account in &accounts {
+        assert_eq!(account.total_balance(), Amount::from(1000));
+    }
+
+    drop(handle);
+    let accounting_system = task.await.unwrap();
+    assert_eq!(accounting_system.iter_accounts().count(), 5);
+}
@@ -1,11 +1,178 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use rstest::rstest;
 use rtm_core::{
-    models::{AccountingOperation, Amount, ClientId, Transaction, TransactionId, TransactionKind},
-    processor::{AccountingSystem, ClientAccountState, TransactionError},
+    models::{
+        AccountingOperation, Amount, AmountPolicies, AmountPolicy, ClientId, Currency, Rounding, Timestamp,
+        Transaction, TransactionId, TransactionKind,
+    },
+    processor::{
+        AccountingEvent, AccountingSystem, AccountingSystemConfig, ClientAccountState, DisputePolicy, OverdraftPolicy,
+        TransactionError,
+    },
 };
 
+mod common;
+
+use common::{amount, chargeback, deposit, dispute, resolve, withdrawal};
+
+/// Largest amount that can be represented with all 4 decimal places.
+const MAX_AMOUNT: &str = "7922816251426433759354395.0335";
+
+fn large_transaction(id: u32, kind: TransactionKind) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(
+            ClientId::from(1),
+            TransactionId::from(id),
+            Amount::try_from(MAX_AMOUNT).unwrap(),
+            kind,
+        ),
+    }
+}
+
+fn currency_deposit(client_id: u16, id: u32, amount: &str, currency: &str) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::with_currency(
+            ClientId::from(client_id),
+            TransactionId::from(id),
+            Amount::try_from(amount).unwrap(),
+            Currency::try_from(currency).unwrap(),
+            TransactionKind::Deposit,
+        ),
+    }
+}
+
+fn currency_withdrawal(client_id: u16, id: u32, amount: &str, currency: &str) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::with_currency(
+            ClientId::from(client_id),
+            TransactionId::from(id),
+            Amount::try_from(amount).unwrap(),
+            Currency::try_from(currency).unwrap(),
+            TransactionKind::Withdrawal,
+        ),
+    }
+}
+
+fn currency_dispute(client_id: u16, id: u32, currency: &str) -> AccountingOperation {
+    AccountingOperation::Dispute {
+        client_id: ClientId::from(client_id),
+        ref_id: TransactionId::from(id),
+        currency: Some(Currency::try_from(currency).unwrap()),
+        timestamp: None,
+    }
+}
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn timed_deposit(id: u32, seconds: u64) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(
+            ClientId::from(1),
+            TransactionId::from(id),
+            Amount::from(1),
+            TransactionKind::Deposit,
+        )
+        .with_timestamp(Some(Timestamp::from(seconds))),
+    }
+}
+
+fn timed_dispute(id: u32, seconds: Option<u64>) -> AccountingOperation {
+    AccountingOperation::Dispute {
+        client_id: ClientId::from(1),
+        ref_id: TransactionId::from(id),
+        currency: None,
+        timestamp: seconds.map(Timestamp::from),
+    }
+}
+
+fn timed_chargeback(id: u32, seconds: u64) -> AccountingOperation {
+    AccountingOperation::Chargeback {
+        client_id: ClientId::from(1),
+        ref_id: TransactionId::from(id),
+        currency: None,
+        timestamp: Some(Timestamp::from(seconds)),
+    }
+}
+
+fn unlock(client_id: u16) -> AccountingOperation {
+    AccountingOperation::Unlock {
+        client_id: ClientId::from(client_id),
+        reason: "reviewed".to_string(),
+        timestamp: None,
+    }
+}
+
+fn freeze(client_id: u16) -> AccountingOperation {
+    AccountingOperation::Freeze {
+        client_id: ClientId::from(client_id),
+        reason: "suspected fraud".to_string(),
+        timestamp: None,
+    }
+}
+
+fn close(client_id: u16) -> AccountingOperation {
+    AccountingOperation::Close {
+        client_id: ClientId::from(client_id),
+        reason: "requested by client".to_string(),
+        timestamp: None,
+    }
+}
+
+fn with_dispute_policy(dispute_policy: DisputePolicy) -> AccountingSystem {
+    AccountingSystem::with_config(AccountingSystemConfig {
+        dispute_policy,
+        ..AccountingSystemConfig::default()
+    })
+}
+
+/// Client 1 deposited 10 and withdrew 8 of it.
+fn with_overdraft_policy(overdraft_policy: OverdraftPolicy) -> AccountingSystem {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        overdraft_policy,
+        ..AccountingSystemConfig::default()
+    });
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 8)).unwrap();
+    accounting_system
+}
+
+fn with_time_limits(dispute_days: u64, chargeback_days: u64) -> AccountingSystem {
+    AccountingSystem::with_config(AccountingSystemConfig {
+        dispute_time_limit: Some(Duration::from_secs(dispute_days * DAY)),
+        chargeback_time_limit: Some(Duration::from_secs(chargeback_days * DAY)),
+        ..AccountingSystemConfig::default()
+    })
+}
+
+/// Locked client with deposits 1 of 5 and 2 of 3, both disputed, the first one charged back.
+fn locked_with_open_dispute() -> AccountingSystem {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    accounting_system.run_operation(deposit(1, 2, 3)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Locked);
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::from(3)));
+    accounting_system
+}
+
+fn balances(accounting_system: &AccountingSystem, client_id: u16) -> (Amount, Amount) {
+    let account = accounting_system
+        .account(ClientId::from(client_id), Currency::UNSPECIFIED)
+        .unwrap();
+    (account.available_balance.clone(), account.held_balance.clone())
+}
+
+fn client_state(accounting_system: &AccountingSystem, client_id: u16) -> ClientAccountState {
+    accounting_system
+        .iter_client_accounts(ClientId::from(client_id))
+        .next()
+        .unwrap()
+        .state
+}
+
 #[test]
 fn test_accounting_system() {
     let mut accounting_system = AccountingSystem::new();
@@ -128,9 +295,26 @@ fn test_accounting_system_too_big_withdrawal() {
 }
 
 #[rstest]
-#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(2) })]
-#[case(AccountingOperation::Resolve { client_id: ClientId::from(1), ref_id: TransactionId::from(2) })]
-#[case(AccountingOperation::Chargeback { client_id: ClientId::from(1), ref_id: TransactionId::from(2) })]
+#[case(withdrawal(2, 2, 1))]
+#[case(dispute(2, 1))]
+#[case(currency_deposit(1, 2, "0", "EUR"))]
+#[case(deposit(2, 1, 1))]
+fn test_accounting_system_rejected_operation_creates_no_account(#[case] operation: AccountingOperation) {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 1)).unwrap();
+    assert!(accounting_system.run_operation(operation).is_err());
+
+    let accounts = accounting_system
+        .iter_accounts()
+        .map(|account| (account.client_id, account.currency))
+        .collect::<Vec<_>>();
+    assert_eq!(accounts, vec![(ClientId::from(1), Currency::UNSPECIFIED)]);
+}
+
+#[rstest]
+#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None })]
+#[case(AccountingOperation::Resolve { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None })]
+#[case(AccountingOperation::Chargeback { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None })]
 fn test_accounting_system_invalid_transaction(#[case] op: AccountingOperation) {
     let mut accounting_system = AccountingSystem::new();
     accounting_system
@@ -147,6 +331,8 @@ fn test_accounting_system_invalid_transaction(#[case] op: AccountingOperation) {
         .run_operation(AccountingOperation::Dispute {
             client_id: ClientId::from(1),
             ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
         })
         .unwrap();
     let result = accounting_system.run_operation(op);
@@ -154,8 +340,8 @@ fn test_accounting_system_invalid_transaction(#[case] op: AccountingOperation) {
 }
 
 #[rstest]
-#[case(AccountingOperation::Resolve { client_id: ClientId::from(1), ref_id: TransactionId::from(1) })]
-#[case(AccountingOperation::Chargeback { client_id: ClientId::from(1), ref_id: TransactionId::from(1) })]
+#[case(AccountingOperation::Resolve { client_id: ClientId::from(1), ref_id: TransactionId::from(1), currency: None, timestamp: None })]
+#[case(AccountingOperation::Chargeback { client_id: ClientId::from(1), ref_id: TransactionId::from(1), currency: None, timestamp: None })]
 fn test_accounting_system_not_dispuated(#[case] op: AccountingOperation) {
     let mut accounting_system = AccountingSystem::new();
     accounting_system
@@ -173,7 +359,7 @@ fn test_accounting_system_not_dispuated(#[case] op: AccountingOperation) {
 }
 
 #[rstest]
-#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(1) })]
+#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(1), currency: None, timestamp: None })]
 fn test_accounting_system_already_dispuated(#[case] op: AccountingOperation) {
     let mut accounting_system = AccountingSystem::new();
     accounting_system
@@ -190,6 +376,8 @@ fn test_accounting_system_already_dispuated(#[case] op: AccountingOperation) {
         .run_operation(AccountingOperation::Dispute {
             client_id: ClientId::from(1),
             ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
         })
         .unwrap();
     let result = accounting_system.run_operation(op);
@@ -198,3 +386,716 @@ fn test_accounting_system_already_dispuated(#[case] op: AccountingOperation) {
         Err(TransactionError::TransactionAlreadyDisputed { .. })
     ));
 }
+
+#[test]
+fn test_accounting_system_dispute_window() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        dispute_window: Some(2),
+        ..AccountingSystemConfig::default()
+    });
+    accounting_system.run_operation(deposit(1, 1, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(deposit(1, 2, 1)).unwrap();
+    accounting_system.run_operation(deposit(1, 3, 1)).unwrap();
+    accounting_system.run_operation(deposit(2, 4, 1)).unwrap();
+
+    // Transaction 1 is under dispute, so the oldest non-disputed one is dropped instead.
+    let result = accounting_system.run_operation(dispute(1, 2));
+    assert!(matches!(result, Err(TransactionError::TransactionDoesNotExist { .. })));
+    accounting_system.run_operation(dispute(1, 3)).unwrap();
+    accounting_system
+        .run_operation(AccountingOperation::Resolve {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        })
+        .unwrap();
+    accounting_system.run_operation(dispute(2, 4)).unwrap();
+}
+
+#[test]
+fn test_accounting_system_withdrawal_dispute_resolved() {
+    let mut accounting_system = with_dispute_policy(DisputePolicy::All);
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 3)).unwrap();
+
+    // Disputed withdrawal is held, but not available until charged back.
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(7), Amount::from(3)));
+
+    accounting_system.run_operation(resolve(1, 2)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(7), Amount::zero()));
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_withdrawal_dispute_charged_back() {
+    let mut accounting_system = with_dispute_policy(DisputePolicy::All);
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 3)).unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+
+    // Chargeback reverses the withdrawal, returning its amount to the client.
+    accounting_system.run_operation(chargeback(1, 2)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(10), Amount::zero()));
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert_eq!(account.state, ClientAccountState::Locked);
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_deposits_only_dispute_policy() {
+    let mut accounting_system = with_dispute_policy(DisputePolicy::DepositsOnly);
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 3)).unwrap();
+
+    // Withdrawal is not retained, but its id is still remembered.
+    let result = accounting_system.run_operation(dispute(1, 2));
+    assert_eq!(
+        result,
+        Err(TransactionError::TransactionDoesNotExist {
+            ref_id: TransactionId::from(2)
+        })
+    );
+    let result = accounting_system.run_operation(deposit(1, 2, 1));
+    assert!(matches!(result, Err(TransactionError::DuplicateTransaction { .. })));
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(7), Amount::zero()));
+
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(-3), Amount::from(10)));
+}
+
+#[test]
+fn test_accounting_system_no_dispute_policy() {
+    let mut accounting_system = with_dispute_policy(DisputePolicy::None);
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 3)).unwrap();
+
+    for id in [1, 2] {
+        let result = accounting_system.run_operation(dispute(1, id));
+        assert_eq!(
+            result,
+            Err(TransactionError::TransactionDoesNotExist {
+                ref_id: TransactionId::from(id)
+            })
+        );
+    }
+    // Nothing is retained, so there is nothing to resolve either.
+    let result = accounting_system.run_operation(resolve(1, 1));
+    assert!(matches!(result, Err(TransactionError::TransactionDoesNotExist { .. })));
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(7), Amount::zero()));
+}
+
+#[test]
+fn test_accounting_system_overdraft_allowed() {
+    let mut accounting_system = with_overdraft_policy(OverdraftPolicy::Allow);
+    let mut events = Vec::new();
+    accounting_system
+        .run_operation_with_sink(dispute(1, 1), &mut events)
+        .unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(-8), Amount::from(10)));
+    assert!(matches!(
+        events.last(),
+        Some(AccountingEvent::AccountOverdrawn { transaction_id, .. }) if *transaction_id == TransactionId::from(1)
+    ));
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert!(account.is_overdrawn());
+    assert!(account.snapshot().is_overdrawn());
+}
+
+#[test]
+fn test_accounting_system_overdraft_rejected() {
+    let mut accounting_system = with_overdraft_policy(OverdraftPolicy::Reject);
+    let result = accounting_system.run_operation(dispute(1, 1));
+    assert_eq!(
+        result,
+        Err(TransactionError::InsufficientFundsForDispute {
+            ref_id: TransactionId::from(1)
+        })
+    );
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(2), Amount::zero()));
+
+    // Deposits covered by the available funds can still be disputed.
+    accounting_system.run_operation(deposit(1, 3, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 3)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(2), Amount::from(1)));
+}
+
+#[test]
+fn test_accounting_system_overdraft_capped() {
+    let mut accounting_system = with_overdraft_policy(OverdraftPolicy::CapHold);
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::from(2)));
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert!(!account.is_overdrawn());
+
+    // Chargeback removes only the held part of the deposit.
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::zero()));
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_overdraft_capped_to_nothing() {
+    let mut accounting_system = with_overdraft_policy(OverdraftPolicy::CapHold);
+    accounting_system.run_operation(withdrawal(1, 3, 2)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::zero()));
+
+    // Dispute is recorded even though nothing is held.
+    let result = accounting_system.run_operation(dispute(1, 1));
+    assert!(matches!(
+        result,
+        Err(TransactionError::TransactionAlreadyDisputed { .. })
+    ));
+    accounting_system.run_operation(resolve(1, 1)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::zero()));
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_overdraft_capped_to_nothing_keeps_precision() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        overdraft_policy: OverdraftPolicy::CapHold,
+        amount_policies: AmountPolicy::new(2, Rounding::HalfUp).into(),
+        ..AccountingSystemConfig::default()
+    });
+    accounting_system.run_operation(deposit(1, 1, 10)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 2, 10)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    let (available, held) = balances(&accounting_system, 1);
+    assert_eq!(
+        (available.to_string(), held.to_string()),
+        ("0.00".to_string(), "0.00".to_string())
+    );
+}
+
+#[test]
+fn test_accounting_system_deposit_overflow() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(large_transaction(1, TransactionKind::Deposit))
+        .unwrap();
+
+    let result = accounting_system.run_operation(deposit(1, 2, 1));
+    assert_eq!(
+        result,
+        Err(TransactionError::ArithmeticOverflow {
+            cause_id: TransactionId::from(2)
+        })
+    );
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert_eq!(account.available_balance, Amount::try_from(MAX_AMOUNT).unwrap());
+
+    // Rejected transaction was not processed, so its id can still be used.
+    accounting_system.run_operation(withdrawal(1, 2, 1)).unwrap();
+}
+
+#[test]
+fn test_accounting_system_total_overflow() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(large_transaction(1, TransactionKind::Deposit))
+        .unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+
+    let result = accounting_system.run_operation(deposit(1, 2, 1));
+    assert_eq!(
+        result,
+        Err(TransactionError::ArithmeticOverflow {
+            cause_id: TransactionId::from(2)
+        })
+    );
+}
+
+#[test]
+fn test_accounting_system_dispute_overflow() {
+    let mut accounting_system = AccountingSystem::new();
+    for operation in [
+        large_transaction(1, TransactionKind::Deposit),
+        large_transaction(2, TransactionKind::Withdrawal),
+        large_transaction(3, TransactionKind::Deposit),
+    ] {
+        accounting_system.run_operation(operation).unwrap();
+    }
+
+    // Disputing the withdrawal would hold its amount on top of already full available funds.
+    let result = accounting_system.run_operation(dispute(1, 2));
+    assert_eq!(
+        result,
+        Err(TransactionError::ArithmeticOverflow {
+            cause_id: TransactionId::from(2)
+        })
+    );
+    let result = accounting_system.run_operation(resolve(1, 2));
+    assert!(matches!(result, Err(TransactionError::TransactionNotDisputed { .. })));
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[rstest]
+#[case(0, TransactionKind::Deposit)]
+#[case(-1, TransactionKind::Deposit)]
+#[case(0, TransactionKind::Withdrawal)]
+#[case(-1, TransactionKind::Withdrawal)]
+fn test_accounting_system_non_positive_amount(#[case] amount: i32, #[case] kind: TransactionKind) {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+
+    let result = accounting_system.run_operation(AccountingOperation::Transaction {
+        transaction: Transaction::new(ClientId::from(1), TransactionId::from(2), Amount::from(amount), kind),
+    });
+    assert_eq!(
+        result,
+        Err(TransactionError::NonPositiveAmount {
+            cause_id: TransactionId::from(2)
+        })
+    );
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert_eq!(account.available_balance, Amount::from(5));
+}
+
+#[test]
+fn test_accounting_system_amount_policy() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: AmountPolicy::new(2, Rounding::HalfUp).into(),
+        ..AccountingSystemConfig::default()
+    });
+    accounting_system.run_operation(deposit(1, 1, amount("1.005"))).unwrap();
+    accounting_system.run_operation(deposit(1, 2, amount("0.5"))).unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+    // Rounds to zero.
+    let result = accounting_system.run_operation(withdrawal(1, 3, amount("0.0049")));
+    assert!(matches!(result, Err(TransactionError::NonPositiveAmount { .. })));
+
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert_eq!(account.available_balance.to_string(), "1.01");
+    assert_eq!(account.held_balance.to_string(), "0.50");
+    assert_eq!(account.snapshot().total_balance().to_string(), "1.51");
+}
+
+#[test]
+fn test_accounting_system_disputes_keep_precision() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: AmountPolicy::new(2, Rounding::HalfUp).into(),
+        ..AccountingSystemConfig::default()
+    });
+    let balances = |accounting_system: &AccountingSystem, client_id| {
+        let (available, held) = balances(accounting_system, client_id);
+        (available.to_string(), held.to_string())
+    };
+    accounting_system.run_operation(deposit(1, 1, amount("10.5"))).unwrap();
+    accounting_system
+        .run_operation(withdrawal(1, 2, amount("1.25")))
+        .unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+    assert_eq!(
+        balances(&accounting_system, 1),
+        ("9.25".to_string(), "1.25".to_string())
+    );
+    accounting_system.run_operation(chargeback(1, 2)).unwrap();
+    assert_eq!(
+        balances(&accounting_system, 1),
+        ("10.50".to_string(), "0.00".to_string())
+    );
+
+    accounting_system.run_operation(deposit(2, 3, 5)).unwrap();
+    accounting_system.run_operation(dispute(2, 3)).unwrap();
+    accounting_system.run_operation(chargeback(2, 3)).unwrap();
+    assert_eq!(
+        balances(&accounting_system, 2),
+        ("0.00".to_string(), "0.00".to_string())
+    );
+}
+
+#[test]
+fn test_accounting_system_rejects_excessive_precision() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: AmountPolicy::new(2, Rounding::Reject).into(),
+        ..AccountingSystemConfig::default()
+    });
+    let result = accounting_system.run_operation(deposit(1, 1, amount("1.005")));
+    assert_eq!(
+        result,
+        Err(TransactionError::ExcessivePrecision {
+            cause_id: TransactionId::from(1)
+        })
+    );
+    accounting_system.run_operation(deposit(1, 1, 1)).unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    assert_eq!(account.available_balance.to_string(), "1.00");
+}
+
+#[test]
+fn test_accounting_system_balances_per_currency() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "5", "EUR"))
+        .unwrap();
+    accounting_system
+        .run_operation(currency_deposit(1, 2, "3", "USD"))
+        .unwrap();
+    accounting_system.run_operation(deposit(1, 3, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+
+    assert_eq!(
+        accounting_system.run_operation(currency_withdrawal(1, 4, "4", "USD")),
+        Err(TransactionError::InsufficientFunds {
+            cause_id: TransactionId::from(4)
+        })
+    );
+
+    let balances = accounting_system
+        .iter_client_accounts(ClientId::from(1))
+        .map(|account| {
+            (
+                account.currency.to_string(),
+                account.available_balance.clone(),
+                account.held_balance.clone(),
+            )
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(
+        balances,
+        [
+            ("EUR".to_string(), Amount::from(5), Amount::zero()),
+            ("USD".to_string(), Amount::zero(), Amount::from(3)),
+            ("XXX".to_string(), Amount::from(1), Amount::zero()),
+        ]
+    );
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_currency_mismatch() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "5", "EUR"))
+        .unwrap();
+    let result = accounting_system.run_operation(currency_dispute(1, 1, "USD"));
+    assert_eq!(
+        result,
+        Err(TransactionError::CurrencyMismatch {
+            ref_id: TransactionId::from(1),
+            expected: Currency::try_from("USD").unwrap(),
+            actual: Currency::try_from("EUR").unwrap(),
+        })
+    );
+    assert_eq!(result.unwrap_err().to_string(), "transaction 1 is in EUR, not in USD");
+
+    accounting_system.run_operation(currency_dispute(1, 1, "EUR")).unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::try_from("EUR").unwrap())
+        .unwrap();
+    assert_eq!(account.held_balance, Amount::from(5));
+}
+
+#[test]
+fn test_accounting_system_chargeback_locks_all_currencies() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "5", "EUR"))
+        .unwrap();
+    accounting_system
+        .run_operation(currency_deposit(1, 2, "3", "USD"))
+        .unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+
+    assert!(
+        accounting_system
+            .iter_client_accounts(ClientId::from(1))
+            .all(|account| account.state == ClientAccountState::Locked)
+    );
+    assert_eq!(
+        accounting_system.run_operation(currency_deposit(1, 3, "1", "GBP")),
+        Err(TransactionError::AccountLocked {
+            client_id: ClientId::from(1)
+        })
+    );
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_precision_per_currency() {
+    let mut amount_policies = AmountPolicies::from(AmountPolicy::new(2, Rounding::Reject));
+    amount_policies.currencies.insert(
+        Currency::try_from("JPY").unwrap(),
+        AmountPolicy::new(0, Rounding::Reject),
+    );
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies,
+        ..AccountingSystemConfig::default()
+    });
+
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "1.25", "EUR"))
+        .unwrap();
+    assert_eq!(
+        accounting_system.run_operation(currency_deposit(1, 2, "1.5", "JPY")),
+        Err(TransactionError::ExcessivePrecision {
+            cause_id: TransactionId::from(2)
+        })
+    );
+    accounting_system
+        .run_operation(currency_deposit(1, 3, "100", "JPY"))
+        .unwrap();
+
+    let balance = |currency: &str| {
+        accounting_system
+            .account(ClientId::from(1), Currency::try_from(currency).unwrap())
+            .unwrap()
+            .available_balance
+            .to_string()
+    };
+    assert_eq!(balance("EUR"), "1.25");
+    assert_eq!(balance("JPY"), "100");
+}
+
+#[test]
+fn test_accounting_system_dispute_time_limit() {
+    let mut accounting_system = with_time_limits(120, 30);
+    accounting_system.run_operation(timed_deposit(1, 0)).unwrap();
+    accounting_system.run_operation(timed_deposit(2, 0)).unwrap();
+
+    accounting_system
+        .run_operation(timed_dispute(1, Some(120 * DAY)))
+        .unwrap();
+    assert_eq!(
+        accounting_system.run_operation(timed_dispute(2, Some(120 * DAY + 1))),
+        Err(TransactionError::DisputeWindowExpired {
+            ref_id: TransactionId::from(2)
+        })
+    );
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(1), Amount::from(1)));
+}
+
+#[test]
+fn test_accounting_system_chargeback_time_limit() {
+    let mut accounting_system = with_time_limits(120, 30);
+    accounting_system.run_operation(timed_deposit(1, 0)).unwrap();
+    accounting_system.run_operation(timed_deposit(2, 0)).unwrap();
+    accounting_system.run_operation(timed_dispute(1, Some(DAY))).unwrap();
+    accounting_system.run_operation(timed_dispute(2, Some(DAY))).unwrap();
+
+    assert_eq!(
+        accounting_system.run_operation(timed_chargeback(1, 31 * DAY + 1)),
+        Err(TransactionError::DisputeWindowExpired {
+            ref_id: TransactionId::from(1)
+        })
+    );
+    accounting_system.run_operation(timed_chargeback(2, 31 * DAY)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(0), Amount::from(1)));
+}
+
+#[test]
+fn test_accounting_system_time_limits_need_timestamps() {
+    let mut accounting_system = with_time_limits(0, 0);
+    accounting_system.run_operation(deposit(1, 1, 1)).unwrap();
+    accounting_system.run_operation(timed_deposit(2, 0)).unwrap();
+
+    // Without both timestamps the elapsed time is unknown, so the limit is not enforced.
+    accounting_system
+        .run_operation(timed_dispute(1, Some(1000 * DAY)))
+        .unwrap();
+    accounting_system.run_operation(timed_dispute(2, None)).unwrap();
+    accounting_system
+        .run_operation(timed_chargeback(2, 1000 * DAY))
+        .unwrap();
+}
+
+#[test]
+fn test_accounting_system_unlock_after_chargeback() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    accounting_system.run_operation(deposit(1, 2, 3)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Locked);
+
+    accounting_system.run_operation(unlock(1)).unwrap();
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Normal);
+    accounting_system.run_operation(withdrawal(1, 3, 3)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::zero()));
+    assert_eq!(
+        accounting_system.run_operation(unlock(1)),
+        Err(TransactionError::AccountNotLocked {
+            client_id: ClientId::from(1)
+        })
+    );
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_freeze() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "5", "EUR"))
+        .unwrap();
+    accounting_system.run_operation(deposit(1, 2, 3)).unwrap();
+    accounting_system.run_operation(freeze(1)).unwrap();
+
+    assert!(
+        accounting_system
+            .iter_client_accounts(ClientId::from(1))
+            .all(|account| account.state == ClientAccountState::Frozen)
+    );
+    let frozen = Err(TransactionError::AccountFrozen {
+        client_id: ClientId::from(1),
+    });
+    assert_eq!(accounting_system.run_operation(withdrawal(1, 3, 1)), frozen);
+    assert_eq!(accounting_system.run_operation(dispute(1, 2)), frozen);
+    assert_eq!(accounting_system.run_operation(freeze(1)), frozen);
+
+    accounting_system.run_operation(unlock(1)).unwrap();
+    accounting_system.run_operation(withdrawal(1, 3, 1)).unwrap();
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_freeze_keeps_chargeback_lock() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+
+    assert_eq!(
+        accounting_system.run_operation(freeze(1)),
+        Err(TransactionError::AccountLocked {
+            client_id: ClientId::from(1)
+        })
+    );
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Locked);
+}
+
+#[test]
+fn test_accounting_system_close() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+
+    let not_settled = Err(TransactionError::AccountNotSettled {
+        client_id: ClientId::from(1),
+    });
+    assert_eq!(accounting_system.run_operation(close(1)), not_settled);
+    accounting_system.run_operation(resolve(1, 1)).unwrap();
+    assert_eq!(accounting_system.run_operation(close(1)), not_settled);
+
+    accounting_system.run_operation(withdrawal(1, 2, 5)).unwrap();
+    accounting_system.run_operation(close(1)).unwrap();
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Closed);
+
+    let closed = Err(TransactionError::AccountClosed {
+        client_id: ClientId::from(1),
+    });
+    assert_eq!(accounting_system.run_operation(deposit(1, 3, 1)), closed);
+    assert_eq!(accounting_system.run_operation(unlock(1)), closed);
+    assert_eq!(accounting_system.run_operation(freeze(1)), closed);
+    assert_eq!(accounting_system.run_operation(close(1)), closed);
+    assert_eq!(accounting_system.run_operation(dispute(1, 1)), closed);
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[rstest]
+#[case(unlock(7))]
+#[case(freeze(7))]
+#[case(close(7))]
+fn test_accounting_system_administrative_operation_of_unknown_client(#[case] operation: AccountingOperation) {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    assert_eq!(
+        accounting_system.run_operation(operation),
+        Err(TransactionError::ClientDoesNotExist {
+            client_id: ClientId::from(7)
+        })
+    );
+    assert!(
+        accounting_system
+            .iter_client_accounts(ClientId::from(7))
+            .next()
+            .is_none()
+    );
+}
+
+#[test]
+fn test_accounting_system_resolve_on_locked_account() {
+    let mut accounting_system = locked_with_open_dispute();
+    accounting_system.run_operation(resolve(1, 2)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::from(3), Amount::zero()));
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Locked);
+
+    let locked = Err(TransactionError::AccountLocked {
+        client_id: ClientId::from(1),
+    });
+    assert_eq!(accounting_system.run_operation(resolve(1, 2)), locked);
+    assert_eq!(accounting_system.run_operation(dispute(1, 2)), locked);
+    assert_eq!(accounting_system.run_operation(deposit(1, 3, 1)), locked);
+    assert_eq!(accounting_system.run_operation(withdrawal(1, 3, 1)), locked);
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_chargeback_on_locked_account() {
+    let mut accounting_system = locked_with_open_dispute();
+    accounting_system.run_operation(chargeback(1, 2)).unwrap();
+    assert_eq!(balances(&accounting_system, 1), (Amount::zero(), Amount::zero()));
+    assert_eq!(client_state(&accounting_system, 1), ClientAccountState::Locked);
+    assert_eq!(
+        accounting_system.run_operation(chargeback(1, 2)),
+        Err(TransactionError::AccountLocked {
+            client_id: ClientId::from(1)
+        })
+    );
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_open_dispute_in_other_currency_on_locked_account() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(currency_deposit(1, 1, "5", "EUR"))
+        .unwrap();
+    accounting_system
+        .run_operation(currency_deposit(1, 2, "3", "USD"))
+        .unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 2)).unwrap();
+    accounting_system.run_operation(chargeback(1, 1)).unwrap();
+
+    accounting_system.run_operation(resolve(1, 2)).unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::try_from("USD").unwrap())
+        .unwrap();
+    assert_eq!(account.available_balance, Amount::from(3));
+    assert_eq!(account.held_balance, Amount::zero());
+    assert_eq!(account.state, ClientAccountState::Locked);
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_accounting_system_open_dispute_on_frozen_account() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, 5)).unwrap();
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(freeze(1)).unwrap();
+
+    let frozen = Err(TransactionError::AccountFrozen {
+        client_id: ClientId::from(1),
+    });
+    assert_eq!(accounting_system.run_operation(resolve(1, 1)), frozen);
+    assert_eq!(accounting_system.run_operation(chargeback(1, 1)), frozen);
+}
@@ -0,0 +1,230 @@
+use rstest::rstest;
+use rtm_core::models::{
+    AccountingOperation, Amount, AmountPolicies, AmountPolicy, Currency, InvalidFieldReason,
+    InvalidNumericalStringError, InvalidOperationFieldError, MAX_REASON_LENGTH, OperationRecord, Rounding,
+};
+
+const MAX_AMOUNT: &str = "7922816251426433759354395.0335";
+
+fn amount(value: &str) -> Amount {
+    Amount::try_from(value).unwrap()
+}
+
+fn parse(
+    record_type: &str,
+    amount: &str,
+    currency: &str,
+    policies: &AmountPolicies,
+) -> Result<AccountingOperation, InvalidOperationFieldError> {
+    let record = OperationRecord {
+        record_type,
+        client: "1",
+        tx: "1",
+        amount,
+        currency,
+        ..OperationRecord::default()
+    };
+    AccountingOperation::parse_record(&record, policies)
+}
+
+#[rstest]
+#[case("1.5", "2.25", Some("3.75"))]
+#[case("-1", "0.0001", Some("-0.9999"))]
+#[case(MAX_AMOUNT, "0", Some(MAX_AMOUNT))]
+#[case(MAX_AMOUNT, "0.0001", None)]
+#[case(MAX_AMOUNT, MAX_AMOUNT, None)]
+fn test_checked_add(#[case] lhs: &str, #[case] rhs: &str, #[case] expected: Option<&str>) {
+    assert_eq!(amount(lhs).checked_add(&amount(rhs)), expected.map(amount));
+}
+
+#[rstest]
+#[case("1.5", "2.25", Some("-0.75"))]
+#[case(MAX_AMOUNT, MAX_AMOUNT, Some("0"))]
+#[case("-7922816251426433759354395.0335", "1", None)]
+fn test_checked_sub(#[case] lhs: &str, #[case] rhs: &str, #[case] expected: Option<&str>) {
+    assert_eq!(amount(lhs).checked_sub(&amount(rhs)), expected.map(amount));
+}
+
+#[rstest]
+#[case("1.5", "2", Some("3"))]
+#[case(MAX_AMOUNT, "1", Some(MAX_AMOUNT))]
+#[case(MAX_AMOUNT, "2", None)]
+fn test_checked_mul(#[case] lhs: &str, #[case] rhs: &str, #[case] expected: Option<&str>) {
+    assert_eq!(amount(lhs).checked_mul(&amount(rhs)), expected.map(amount));
+}
+
+#[rstest]
+#[case("3", "2", Some("1.5"))]
+#[case("1", "3", Some("0.3333"))]
+#[case("1", "0", None)]
+#[case(MAX_AMOUNT, "0.5", None)]
+fn test_checked_div(#[case] lhs: &str, #[case] rhs: &str, #[case] expected: Option<&str>) {
+    assert_eq!(amount(lhs).checked_div(&amount(rhs)), expected.map(amount));
+}
+
+#[rstest]
+#[case(MAX_AMOUNT, true)]
+#[case("7922816251426433759354395.0336", false)]
+#[case("79228162514264337593543950335", false)]
+fn test_parse_range(#[case] value: &str, #[case] valid: bool) {
+    assert_eq!(Amount::try_from(value).is_ok(), valid);
+}
+
+#[rstest]
+#[case("1.2345", Ok("1.2345"))]
+#[case(" -5 ", Err(InvalidNumericalStringError::Malformed))]
+#[case("-5", Ok("-5"))]
+#[case("1.50000000", Ok("1.5"))]
+#[case("1.23456", Ok("1.2346"))]
+#[case("1.23465", Ok("1.2346"))]
+#[case("0.00001", Ok("0"))]
+#[case("0.0000000000000000000000000000001", Ok("0"))]
+#[case("79228162514264337593543950336", Err(InvalidNumericalStringError::OutOfRange))]
+#[case("abc", Err(InvalidNumericalStringError::Malformed))]
+#[case("", Err(InvalidNumericalStringError::Malformed))]
+fn test_parse_errors(#[case] value: &str, #[case] expected: Result<&str, InvalidNumericalStringError>) {
+    assert_eq!(Amount::try_from(value), expected.map(amount));
+}
+
+#[rstest]
+#[case(2, Rounding::Reject, "1.5", Ok("1.50"))]
+#[case(2, Rounding::Reject, "1.25000", Ok("1.25"))]
+#[case(2, Rounding::Reject, "1.255", Err(InvalidNumericalStringError::TooPrecise))]
+#[case(2, Rounding::HalfEven, "1.255", Ok("1.26"))]
+#[case(2, Rounding::HalfEven, "1.245", Ok("1.24"))]
+#[case(2, Rounding::HalfUp, "1.245", Ok("1.25"))]
+#[case(2, Rounding::HalfUp, "-1.245", Ok("-1.25"))]
+#[case(2, Rounding::Truncate, "1.249", Ok("1.24"))]
+#[case(0, Rounding::HalfUp, "2.5", Ok("3"))]
+#[case(6, Rounding::Reject, "0.000001", Ok("0.000001"))]
+#[case(28, Rounding::Reject, "1", Ok("1.0000000000000000000000000000"))]
+#[case(28, Rounding::Reject, "10", Err(InvalidNumericalStringError::OutOfRange))]
+#[case(2, Rounding::HalfUp, "x", Err(InvalidNumericalStringError::Malformed))]
+fn test_parse_with_policy(
+    #[case] decimal_places: u32,
+    #[case] rounding: Rounding,
+    #[case] value: &str,
+    #[case] expected: Result<&str, InvalidNumericalStringError>,
+) {
+    let policy = AmountPolicy::new(decimal_places, rounding);
+    let result = policy.parse(value).map(|amount| amount.to_string());
+    assert_eq!(result, expected.map(str::to_string));
+}
+
+#[rstest]
+#[case(Rounding::Reject, Err(InvalidNumericalStringError::TooPrecise))]
+#[case(Rounding::HalfUp, Ok("1.24"))]
+#[case(Rounding::Truncate, Ok("1.23"))]
+fn test_apply_policy(#[case] rounding: Rounding, #[case] expected: Result<&str, InvalidNumericalStringError>) {
+    let policy = AmountPolicy::new(2, rounding);
+    let result = policy.apply(&amount("1.2350")).map(|amount| amount.to_string());
+    assert_eq!(result, expected.map(str::to_string));
+    assert_eq!(policy.apply(&amount("1.2000")).unwrap().to_string(), "1.20");
+}
+
+#[test]
+fn test_arithmetic_keeps_larger_precision() {
+    let policy = AmountPolicy::new(2, Rounding::Reject);
+    let sum = policy.zero() + policy.parse("1.5").unwrap();
+    assert_eq!(sum.to_string(), "1.50");
+    assert_eq!((sum.clone() - amount("0.0001")).to_string(), "1.4999");
+    assert_eq!((sum / Amount::from(3)).to_string(), "0.5000");
+}
+
+#[test]
+fn test_parse_operation_with_policy() {
+    let policies = AmountPolicies::from(AmountPolicy::new(2, Rounding::Truncate));
+    let operation = parse("deposit", "0.019", "", &policies).unwrap();
+    let AccountingOperation::Transaction { transaction } = operation else {
+        panic!("expected a transaction");
+    };
+    assert_eq!(transaction.amount().to_string(), "0.01");
+
+    let result = parse("deposit", "0.009", "", &policies);
+    assert_eq!(result.unwrap_err().reason, InvalidFieldReason::NotPositive);
+}
+
+#[test]
+fn test_parse_operation_with_currency_policy() {
+    let mut policies = AmountPolicies::default();
+    let jpy = Currency::try_from("jpy").unwrap();
+    policies.currencies.insert(jpy, AmountPolicy::new(0, Rounding::HalfUp));
+
+    let operation = parse("deposit", "2.5", "JPY", &policies).unwrap();
+    let AccountingOperation::Transaction { transaction } = operation else {
+        panic!("expected a transaction");
+    };
+    assert_eq!(transaction.currency(), jpy);
+    assert_eq!(transaction.amount().to_string(), "3");
+
+    let operation = parse("deposit", "2.5", "EUR", &policies).unwrap();
+    assert_eq!(operation.currency(), Some(Currency::try_from("EUR").unwrap()));
+
+    let result = parse("dispute", "", "EURO", &policies);
+    let error = result.unwrap_err();
+    assert_eq!(
+        (error.column, error.reason),
+        ("currency", InvalidFieldReason::Malformed)
+    );
+}
+
+#[rstest]
+#[case("eur", Ok("EUR"))]
+#[case(" Usd", Err(()))]
+#[case("EU", Err(()))]
+#[case("E1R", Err(()))]
+#[case("EURO", Err(()))]
+fn test_parse_currency(#[case] value: &str, #[case] expected: Result<&str, ()>) {
+    let currency = Currency::try_from(value).map(|currency| currency.to_string());
+    assert_eq!(currency.map_err(|_| ()), expected.map(str::to_string));
+}
+
+#[rstest]
+#[case("deposit", "1", Ok(()))]
+#[case("withdrawal", "0.0001", Ok(()))]
+#[case("deposit", "0", Err(InvalidFieldReason::NotPositive))]
+#[case("withdrawal", "-1", Err(InvalidFieldReason::NotPositive))]
+#[case("deposit", "1.00001", Ok(()))]
+#[case("deposit", "0.00001", Err(InvalidFieldReason::NotPositive))]
+#[case("deposit", "79228162514264337593543950335", Err(InvalidFieldReason::OutOfRange))]
+#[case("deposit", "x", Err(InvalidFieldReason::Malformed))]
+fn test_parse_operation_amount(
+    #[case] record_type: &str,
+    #[case] value: &str,
+    #[case] expected: Result<(), InvalidFieldReason>,
+) {
+    let result = parse(record_type, value, "", &AmountPolicies::default());
+    assert_eq!(result.map(|_| ()).map_err(|err| err.reason), expected);
+}
+
+#[test]
+fn test_parse_operation_error_message() {
+    let err = parse("deposit", "-2", "", &AmountPolicies::default()).unwrap_err();
+    assert_eq!(
+        err,
+        InvalidOperationFieldError {
+            column: "amount",
+            value: "-2".to_string(),
+            reason: InvalidFieldReason::NotPositive,
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "column `amount`: invalid value `-2`, amount is not positive"
+    );
+}
+
+#[rstest]
+#[case("", Err(InvalidFieldReason::Malformed))]
+#[case(&"x".repeat(MAX_REASON_LENGTH), Ok(()))]
+#[case(&"x".repeat(MAX_REASON_LENGTH + 1), Err(InvalidFieldReason::TooLong))]
+fn test_parse_operation_reason(#[case] reason: &str, #[case] expected: Result<(), InvalidFieldReason>) {
+    let record = OperationRecord {
+        record_type: "freeze",
+        client: "1",
+        reason,
+        ..OperationRecord::default()
+    };
+    let result = AccountingOperation::parse_record(&record, &AmountPolicies::default());
+    assert_eq!(result.map(|_| ()).map_err(|err| err.reason), expected);
+}
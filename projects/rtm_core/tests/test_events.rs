@@ -0,0 +1,324 @@
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Currency, Transaction, TransactionId, TransactionKind},
+    processor::{AccountingEvent, AccountingSystem, BalanceChange, Balances, EventSink, TransactionError},
+};
+
+fn transaction(id: u32, amount: i32, kind: TransactionKind) -> AccountingOperation {
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(ClientId::from(1), TransactionId::from(id), Amount::from(amount), kind),
+    }
+}
+
+fn balances(available: i32, held: i32) -> Balances {
+    Balances {
+        available: Amount::from(available),
+        held: Amount::from(held),
+    }
+}
+
+fn change(before: (i32, i32), after: (i32, i32)) -> BalanceChange {
+    BalanceChange {
+        before: balances(before.0, before.1),
+        after: balances(after.0, after.1),
+    }
+}
+
+#[test]
+fn test_events_of_all_operations() {
+    let client_id = ClientId::from(1);
+    let mut accounting_system = AccountingSystem::new();
+    let mut events = Vec::new();
+    for operation in [
+        transaction(1, 10, TransactionKind::Deposit),
+        transaction(2, 3, TransactionKind::Withdrawal),
+        AccountingOperation::Dispute {
+            client_id,
+            ref_id: TransactionId::from(2),
+            currency: None,
+            timestamp: None,
+        },
+        AccountingOperation::Resolve {
+            client_id,
+            ref_id: TransactionId::from(2),
+            currency: None,
+            timestamp: None,
+        },
+        AccountingOperation::Dispute {
+            client_id,
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        },
+        AccountingOperation::Chargeback {
+            client_id,
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        },
+    ] {
+        accounting_system
+            .run_operation_with_sink(operation, &mut events)
+            .unwrap();
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            AccountingEvent::Deposited {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                amount: Amount::from(10),
+                currency: Currency::UNSPECIFIED,
+                balances: change((0, 0), (10, 0)),
+            },
+            AccountingEvent::Withdrawn {
+                client_id,
+                transaction_id: TransactionId::from(2),
+                amount: Amount::from(3),
+                currency: Currency::UNSPECIFIED,
+                balances: change((10, 0), (7, 0)),
+            },
+            AccountingEvent::FundsHeld {
+                client_id,
+                transaction_id: TransactionId::from(2),
+                amount: Amount::from(3),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Withdrawal,
+                balances: change((7, 0), (7, 3)),
+            },
+            AccountingEvent::FundsReleased {
+                client_id,
+                transaction_id: TransactionId::from(2),
+                amount: Amount::from(3),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Withdrawal,
+                balances: change((7, 3), (7, 0)),
+            },
+            AccountingEvent::FundsHeld {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                amount: Amount::from(10),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Deposit,
+                balances: change((7, 0), (-3, 10)),
+            },
+            AccountingEvent::AccountOverdrawn {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                currency: Currency::UNSPECIFIED,
+            },
+            AccountingEvent::ChargedBack {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                amount: Amount::from(10),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Deposit,
+                balances: change((-3, 10), (-3, 0)),
+            },
+            AccountingEvent::AccountLocked {
+                client_id,
+                transaction_id: TransactionId::from(1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_events_of_chargebacks_on_locked_account() {
+    let client_id = ClientId::from(1);
+    let mut accounting_system = AccountingSystem::new();
+    for id in [1, 2] {
+        accounting_system
+            .run_operation(transaction(id, 1, TransactionKind::Deposit))
+            .unwrap();
+        accounting_system
+            .run_operation(AccountingOperation::Dispute {
+                client_id,
+                ref_id: TransactionId::from(id),
+                currency: None,
+                timestamp: None,
+            })
+            .unwrap();
+    }
+    let mut events = Vec::new();
+    for id in [1, 2] {
+        accounting_system
+            .run_operation_with_sink(
+                AccountingOperation::Chargeback {
+                    client_id,
+                    ref_id: TransactionId::from(id),
+                    currency: None,
+                    timestamp: None,
+                },
+                &mut events,
+            )
+            .unwrap();
+    }
+
+    // The client is only locked by the first chargeback.
+    assert_eq!(
+        events,
+        vec![
+            AccountingEvent::ChargedBack {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                amount: Amount::from(1),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Deposit,
+                balances: change((0, 2), (0, 1)),
+            },
+            AccountingEvent::AccountLocked {
+                client_id,
+                transaction_id: TransactionId::from(1),
+            },
+            AccountingEvent::ChargedBack {
+                client_id,
+                transaction_id: TransactionId::from(2),
+                amount: Amount::from(1),
+                currency: Currency::UNSPECIFIED,
+                kind: TransactionKind::Deposit,
+                balances: change((0, 1), (0, 0)),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_events_of_administrative_operations() {
+    let client_id = ClientId::from(1);
+    let mut accounting_system = AccountingSystem::new();
+    let mut events = Vec::new();
+    for operation in [
+        transaction(1, 2, TransactionKind::Deposit),
+        AccountingOperation::Freeze {
+            client_id,
+            reason: "suspected fraud".to_string(),
+            timestamp: None,
+        },
+        AccountingOperation::Unlock {
+            client_id,
+            reason: "reviewed".to_string(),
+            timestamp: None,
+        },
+        transaction(2, 2, TransactionKind::Withdrawal),
+        AccountingOperation::Close {
+            client_id,
+            reason: "requested by client".to_string(),
+            timestamp: None,
+        },
+    ] {
+        accounting_system
+            .run_operation_with_sink(operation, &mut events)
+            .unwrap();
+    }
+
+    assert_eq!(
+        events,
+        vec![
+            AccountingEvent::Deposited {
+                client_id,
+                transaction_id: TransactionId::from(1),
+                amount: Amount::from(2),
+                currency: Currency::UNSPECIFIED,
+                balances: change((0, 0), (2, 0)),
+            },
+            AccountingEvent::AccountFrozen {
+                client_id,
+                reason: "suspected fraud".to_string(),
+            },
+            AccountingEvent::AccountUnlocked {
+                client_id,
+                reason: "reviewed".to_string(),
+            },
+            AccountingEvent::Withdrawn {
+                client_id,
+                transaction_id: TransactionId::from(2),
+                amount: Amount::from(2),
+                currency: Currency::UNSPECIFIED,
+                balances: change((2, 0), (0, 0)),
+            },
+            AccountingEvent::AccountClosed {
+                client_id,
+                reason: "requested by client".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_rejected_operations_publish_no_events() {
+    let mut accounting_system = AccountingSystem::new();
+    let mut events = Vec::new();
+    accounting_system
+        .run_operation_with_sink(transaction(1, 1, TransactionKind::Deposit), &mut events)
+        .unwrap();
+    events.clear();
+
+    for (operation, error) in [
+        (
+            transaction(2, 5, TransactionKind::Withdrawal),
+            TransactionError::InsufficientFunds {
+                cause_id: TransactionId::from(2),
+            },
+        ),
+        (
+            transaction(1, 1, TransactionKind::Deposit),
+            TransactionError::DuplicateTransaction {
+                cause_id: TransactionId::from(1),
+            },
+        ),
+        (
+            AccountingOperation::Resolve {
+                client_id: ClientId::from(1),
+                ref_id: TransactionId::from(1),
+                currency: None,
+                timestamp: None,
+            },
+            TransactionError::TransactionNotDisputed {
+                ref_id: TransactionId::from(1),
+            },
+        ),
+    ] {
+        assert_eq!(
+            accounting_system.run_operation_with_sink(operation, &mut events),
+            Err(error)
+        );
+    }
+    assert!(events.is_empty());
+}
+
+#[test]
+fn test_closure_sink() {
+    struct Totals {
+        deposited: Amount,
+        published: usize,
+    }
+
+    let mut totals = Totals {
+        deposited: Amount::zero(),
+        published: 0,
+    };
+    let mut sink = |event: AccountingEvent| {
+        totals.published += 1;
+        if let AccountingEvent::Deposited { amount, .. } = event {
+            totals.deposited += amount;
+        }
+    };
+
+    let mut accounting_system = AccountingSystem::new();
+    for id in 1..=3 {
+        accounting_system
+            .run_operation_with_sink(transaction(id, 2, TransactionKind::Deposit), &mut sink)
+            .unwrap();
+    }
+    accounting_system
+        .run_operation_with_sink(transaction(4, 1, TransactionKind::Withdrawal), &mut sink)
+        .unwrap();
+    sink.publish(AccountingEvent::AccountLocked {
+        client_id: ClientId::from(1),
+        transaction_id: TransactionId::from(4),
+    });
+
+    assert_eq!(totals.published, 5);
+    assert_eq!(totals.deposited, Amount::from(6));
+}
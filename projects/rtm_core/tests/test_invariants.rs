@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use proptest::prelude::*;
+use rtm_core::{
+    models::{
+        AccountingOperation, Amount, AmountPolicies, AmountPolicy, ClientId, Currency, Rounding, Timestamp,
+        Transaction, TransactionId, TransactionKind,
+    },
+    processor::{
+        AccountingSystem, AccountingSystemConfig, DisputePolicy, InvariantViolation, OverdraftPolicy, TransactionError,
+    },
+};
+
+mod common;
+
+use common::{chargeback, deposit, dispute};
+
+fn currency() -> impl Strategy<Value = Currency> {
+    prop_oneof![
+        Just(Currency::UNSPECIFIED),
+        Just(Currency::try_from("EUR").unwrap()),
+        Just(Currency::try_from("USD").unwrap()),
+    ]
+}
+
+/// Operations over a few clients, currencies, transaction ids and timestamps, so that disputes often hit
+/// existing transactions, mixed with administrative operations.
+fn operation() -> impl Strategy<Value = AccountingOperation> {
+    let client_id = (1..=4u16).prop_map(ClientId::from);
+    let id = (1..=40u32).prop_map(TransactionId::from);
+    let amount = (0..1_000_000u32)
+        .prop_map(|value| Amount::try_from(format!("{}.{:04}", value / 10_000, value % 10_000).as_str()).unwrap());
+    let kind = prop_oneof![Just(TransactionKind::Deposit), Just(TransactionKind::Withdrawal)];
+    let timestamp = proptest::option::of((0..100u64).prop_map(Timestamp::from));
+    prop_oneof![
+        5 => (client_id.clone(), id.clone(), amount, currency(), kind, timestamp.clone()).prop_map(|(client_id, id, amount, currency, kind, timestamp)| {
+            AccountingOperation::Transaction {
+                transaction: Transaction::with_currency(client_id, id, amount, currency, kind).with_timestamp(timestamp),
+            }
+        }),
+        3 => (client_id.clone(), id.clone(), proptest::option::of(currency()), timestamp.clone())
+            .prop_map(|(client_id, ref_id, currency, timestamp)| AccountingOperation::Dispute { client_id, ref_id, currency, timestamp }),
+        2 => (client_id.clone(), id.clone(), proptest::option::of(currency()), timestamp.clone())
+            .prop_map(|(client_id, ref_id, currency, timestamp)| AccountingOperation::Resolve { client_id, ref_id, currency, timestamp }),
+        1 => (client_id.clone(), id, proptest::option::of(currency()), timestamp)
+            .prop_map(|(client_id, ref_id, currency, timestamp)| AccountingOperation::Chargeback { client_id, ref_id, currency, timestamp }),
+        1 => (client_id, 0..3u8).prop_map(|(client_id, kind)| {
+            let reason = "audit".to_string();
+            match kind {
+                0 => AccountingOperation::Unlock { client_id, reason, timestamp: None },
+                1 => AccountingOperation::Freeze { client_id, reason, timestamp: None },
+                _ => AccountingOperation::Close { client_id, reason, timestamp: None },
+            }
+        }),
+    ]
+}
+
+fn config() -> impl Strategy<Value = AccountingSystemConfig> {
+    let dispute_policy = prop_oneof![
+        Just(DisputePolicy::All),
+        Just(DisputePolicy::DepositsOnly),
+        Just(DisputePolicy::None)
+    ];
+    let overdraft_policy = prop_oneof![
+        Just(OverdraftPolicy::Allow),
+        Just(OverdraftPolicy::Reject),
+        Just(OverdraftPolicy::CapHold)
+    ];
+    let rounding = prop_oneof![
+        Just(Rounding::Reject),
+        Just(Rounding::HalfEven),
+        Just(Rounding::HalfUp),
+        Just(Rounding::Truncate)
+    ];
+    let amount_policy =
+        (0..=6u32, rounding).prop_map(|(decimal_places, rounding)| AmountPolicy::new(decimal_places, rounding));
+    let amount_policies = (amount_policy.clone(), proptest::option::of(amount_policy)).prop_map(|(default, eur)| {
+        let mut amount_policies = AmountPolicies::from(default);
+        if let Some(eur) = eur {
+            amount_policies
+                .currencies
+                .insert(Currency::try_from("EUR").unwrap(), eur);
+        }
+        amount_policies
+    });
+    let time_limit = proptest::option::of((0..50u64).prop_map(Duration::from_secs));
+    (
+        dispute_policy,
+        overdraft_policy,
+        proptest::option::of(1..5usize),
+        time_limit.clone(),
+        time_limit,
+        amount_policies,
+    )
+        .prop_map(
+            |(
+                dispute_policy,
+                overdraft_policy,
+                dispute_window,
+                dispute_time_limit,
+                chargeback_time_limit,
+                amount_policies,
+            )| AccountingSystemConfig {
+                dispute_policy,
+                overdraft_policy,
+                dispute_window,
+                dispute_time_limit,
+                chargeback_time_limit,
+                amount_policies,
+            },
+        )
+}
+
+proptest! {
+    #[test]
+    fn test_invariants_hold_after_every_operation(operations in prop::collection::vec(operation(), 1..200)) {
+        let mut accounting_system = AccountingSystem::new();
+        for operation in operations {
+            let _ = accounting_system.run_operation(operation);
+            prop_assert_eq!(accounting_system.verify_invariants(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_invariants_hold_with_any_config(
+        config in config(),
+        operations in prop::collection::vec(operation(), 1..200),
+    ) {
+        let mut accounting_system = AccountingSystem::with_config(config);
+        for operation in operations {
+            let _ = accounting_system.run_operation(operation);
+            prop_assert_eq!(accounting_system.verify_invariants(), Ok(()));
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_tampered_balances_are_reported() {
+    let account: rtm_core::processor::ClientAccount = serde_json::from_str(concat!(
+        r#"{"client":1,"available":"0","held":"7","total":"7","locked":false,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"5","type":"deposit"}],"disputed":[1]}"#
+    ))
+    .unwrap();
+
+    let tampered = AccountingSystem::with_accounts(AccountingSystemConfig::default(), [account]).unwrap();
+    assert_eq!(
+        tampered.verify_invariants(),
+        Err(vec![
+            InvariantViolation::HeldMismatch {
+                client_id: ClientId::from(1),
+                held: Amount::from(7),
+                disputed: Amount::from(5),
+            },
+            InvariantViolation::TotalMismatch {
+                client_id: ClientId::from(1),
+                total: Amount::from(7),
+                transactions: Amount::from(5),
+            },
+        ])
+    );
+}
+
+#[test]
+fn test_chargeback_keeps_reversed_transaction() {
+    let client_id = ClientId::from(1);
+    let mut accounting_system = AccountingSystem::new();
+    for operation in [deposit(1, 1, 5), deposit(1, 2, 3), dispute(1, 2), chargeback(1, 2)] {
+        accounting_system.run_operation(operation).unwrap();
+    }
+
+    let account = accounting_system.account(client_id, Currency::UNSPECIFIED).unwrap();
+    assert_eq!(account.snapshot().total_balance(), Amount::from(5));
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+
+    accounting_system
+        .run_operation(AccountingOperation::Unlock {
+            client_id,
+            reason: "reviewed".to_string(),
+            timestamp: None,
+        })
+        .unwrap();
+    assert_eq!(
+        accounting_system.run_operation(dispute(1, 2)),
+        Err(TransactionError::TransactionAlreadyDisputed {
+            ref_id: TransactionId::from(2)
+        })
+    );
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_invariants_of_largest_amounts() {
+    let max_amount = Amount::try_from("7922816251426433759354395.0335").unwrap();
+    let client_id = ClientId::from(1);
+    let mut accounting_system = AccountingSystem::new();
+    for (id, kind) in [
+        (1, TransactionKind::Deposit),
+        (3, TransactionKind::Withdrawal),
+        (2, TransactionKind::Deposit),
+    ] {
+        accounting_system
+            .run_operation(AccountingOperation::Transaction {
+                transaction: Transaction::new(client_id, TransactionId::from(id), max_amount.clone(), kind),
+            })
+            .unwrap();
+    }
+    accounting_system
+        .run_operation(AccountingOperation::Dispute {
+            client_id,
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        })
+        .unwrap();
+
+    // Retained and disputed transactions sum to the balances, even though adding them up
+    // in order of their ids would overflow.
+    assert_eq!(accounting_system.verify_invariants(), Ok(()));
+}
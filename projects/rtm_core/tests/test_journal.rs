@@ -0,0 +1,367 @@
+use std::{fs::OpenOptions, io::Write, num::NonZeroUsize, path::PathBuf};
+
+use rstest::rstest;
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Currency, Timestamp, Transaction, TransactionId, TransactionKind},
+    processor::{
+        AccountingSystem, AccountingSystemConfig, FsyncPolicy, JOURNAL_VERSION, JournalError,
+        JournaledAccountingSystem, RecoveryReport, TransactionError,
+    },
+};
+
+mod common;
+
+use common::{amount, deposit};
+
+/// Snapshot and journal paths in a fresh directory.
+struct Files {
+    directory: PathBuf,
+    snapshot: PathBuf,
+    journal: PathBuf,
+}
+
+impl Files {
+    fn new(name: &str) -> Self {
+        let directory = std::env::temp_dir().join(format!("rtm_journal_{}_{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&directory);
+        std::fs::create_dir_all(&directory).unwrap();
+        Self {
+            snapshot: directory.join("state.snapshot"),
+            journal: directory.join("state.journal"),
+            directory,
+        }
+    }
+
+    fn open(&self) -> Result<(JournaledAccountingSystem, RecoveryReport), JournalError> {
+        JournaledAccountingSystem::open(
+            AccountingSystemConfig::default(),
+            &self.snapshot,
+            &self.journal,
+            FsyncPolicy::Never,
+        )
+    }
+
+    fn journal_len(&self) -> u64 {
+        std::fs::metadata(&self.journal).unwrap().len()
+    }
+}
+
+impl Drop for Files {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.directory);
+    }
+}
+
+fn operations() -> Vec<AccountingOperation> {
+    vec![
+        deposit(1, 1, amount("10.5")),
+        deposit(2, 2, amount("3")),
+        AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(1),
+                TransactionId::from(3),
+                Amount::try_from("0.1234").unwrap(),
+                TransactionKind::Withdrawal,
+            )
+            .with_timestamp(Some(Timestamp::from(1_700_000_000))),
+        },
+        AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: Some(Currency::UNSPECIFIED),
+            timestamp: None,
+        },
+        AccountingOperation::Dispute {
+            client_id: ClientId::from(2),
+            ref_id: TransactionId::from(2),
+            currency: None,
+            timestamp: Some(Timestamp::from(1_700_000_060)),
+        },
+        AccountingOperation::Resolve {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        },
+        AccountingOperation::Chargeback {
+            client_id: ClientId::from(2),
+            ref_id: TransactionId::from(2),
+            currency: None,
+            timestamp: None,
+        },
+        AccountingOperation::Transaction {
+            transaction: Transaction::with_currency(
+                ClientId::from(3),
+                TransactionId::from(4),
+                Amount::from(1),
+                Currency::try_from("EUR").unwrap(),
+                TransactionKind::Deposit,
+            ),
+        },
+    ]
+}
+
+fn snapshot(accounting_system: &AccountingSystem) -> Vec<u8> {
+    let mut snapshot = Vec::new();
+    accounting_system.save_snapshot(&mut snapshot).unwrap();
+    snapshot
+}
+
+/// Snapshot of a plain system after running the first `count` operations.
+fn expected(count: usize) -> Vec<u8> {
+    let mut accounting_system = AccountingSystem::new();
+    for operation in operations().into_iter().take(count) {
+        accounting_system.run_operation(operation).unwrap();
+    }
+    snapshot(&accounting_system)
+}
+
+fn run(system: &mut JournaledAccountingSystem, operations: impl IntoIterator<Item = AccountingOperation>) {
+    for operation in operations {
+        system.run_operation(operation).unwrap();
+    }
+}
+
+#[rstest]
+#[case(FsyncPolicy::Always)]
+#[case(FsyncPolicy::Every(NonZeroUsize::new(3).unwrap()))]
+#[case(FsyncPolicy::Never)]
+fn test_journal_recovery(#[case] fsync: FsyncPolicy) {
+    let files = Files::new(&format!("recovery_{fsync:?}"));
+    let config = AccountingSystemConfig::default();
+    let (mut system, report) =
+        JournaledAccountingSystem::open(config.clone(), &files.snapshot, &files.journal, fsync).unwrap();
+    assert_eq!(report, RecoveryReport::default());
+    run(&mut system, operations());
+    drop(system);
+
+    let (system, report) = JournaledAccountingSystem::open(config, &files.snapshot, &files.journal, fsync).unwrap();
+    assert_eq!(report.replayed, 8);
+    assert_eq!(report.discarded_bytes, 0);
+    assert_eq!(system.sequence(), 8);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+}
+
+#[test]
+fn test_journal_skips_rejected_operations() {
+    let files = Files::new("rejected");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(2));
+    assert!(matches!(
+        system.run_operation(deposit(3, 1, amount("1"))),
+        Err(JournalError::Transaction(TransactionError::DuplicateTransaction { .. }))
+    ));
+    drop(system);
+
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 2);
+    assert_eq!(snapshot(system.accounting_system()), expected(2));
+}
+
+#[test]
+fn test_journal_rejects_too_large_records() {
+    let files = Files::new("too_large");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(2));
+    let freeze = AccountingOperation::Freeze {
+        client_id: ClientId::from(1),
+        reason: "x".repeat(2000),
+        timestamp: None,
+    };
+    assert!(matches!(
+        system.run_operation(freeze),
+        Err(JournalError::RecordTooLarge { .. })
+    ));
+    assert_eq!(snapshot(system.accounting_system()), expected(2));
+    run(&mut system, operations().into_iter().skip(2));
+    drop(system);
+
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 8);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+}
+
+#[test]
+fn test_journal_administrative_operations() {
+    let files = Files::new("administrative");
+    let (mut system, _) = files.open().unwrap();
+    let administrative = [
+        AccountingOperation::Unlock {
+            client_id: ClientId::from(2),
+            reason: "chargeback reviewed".to_string(),
+            timestamp: Some(Timestamp::from(1_700_000_120)),
+        },
+        AccountingOperation::Freeze {
+            client_id: ClientId::from(3),
+            reason: "suspected fraud, see ticket".to_string(),
+            timestamp: None,
+        },
+    ];
+    run(&mut system, operations().into_iter().chain(administrative.clone()));
+    drop(system);
+
+    let mut expected = AccountingSystem::new();
+    for operation in operations().into_iter().chain(administrative) {
+        expected.run_operation(operation).unwrap();
+    }
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 10);
+    assert_eq!(snapshot(system.accounting_system()), snapshot(&expected));
+}
+
+#[test]
+fn test_journal_checkpoint() {
+    let files = Files::new("checkpoint");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(5));
+    system.checkpoint().unwrap();
+    let journal_len = files.journal_len();
+    run(&mut system, operations().into_iter().skip(5));
+    drop(system);
+
+    let (mut system, report) = files.open().unwrap();
+    assert_eq!(report.snapshot_sequence, 5);
+    assert_eq!(report.replayed, 3);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+
+    system.checkpoint().unwrap();
+    assert_eq!(files.journal_len(), journal_len);
+    drop(system);
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.snapshot_sequence, 8);
+    assert_eq!(report.replayed, 0);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+}
+
+#[rstest]
+#[case(1)]
+#[case(5)]
+#[case(12)]
+fn test_journal_truncated_tail(#[case] cut: u64) {
+    let files = Files::new(&format!("truncated_{cut}"));
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(7));
+    let complete_len = files.journal_len();
+    run(&mut system, operations().into_iter().skip(7));
+    let full_len = files.journal_len();
+    drop(system);
+
+    let file = OpenOptions::new().write(true).open(&files.journal).unwrap();
+    file.set_len(full_len - cut).unwrap();
+    drop(file);
+
+    let (mut system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 7);
+    assert_eq!(report.discarded_bytes, full_len - cut - complete_len);
+    assert_eq!(files.journal_len(), complete_len);
+    assert_eq!(snapshot(system.accounting_system()), expected(7));
+
+    // Journal keeps working after the tail was cut off.
+    run(&mut system, operations().into_iter().skip(7));
+    drop(system);
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 8);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+}
+
+#[test]
+fn test_journal_corrupted_tail() {
+    let files = Files::new("corrupted");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(7));
+    let complete_len = files.journal_len();
+    run(&mut system, operations().into_iter().skip(7));
+    drop(system);
+
+    // Flip a bit in the last record and append some garbage.
+    let mut content = std::fs::read(&files.journal).unwrap();
+    let position = usize::try_from(complete_len).unwrap() + 6;
+    content[position] ^= 0x10;
+    content.extend_from_slice(&[0xFF; 7]);
+    std::fs::write(&files.journal, &content).unwrap();
+
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 7);
+    assert_eq!(report.discarded_bytes, content.len() as u64 - complete_len);
+    assert_eq!(snapshot(system.accounting_system()), expected(7));
+}
+
+#[rstest]
+#[case(6)]
+#[case(0)]
+fn test_journal_corrupted_middle_record(#[case] offset: usize) {
+    let files = Files::new(&format!("corrupted_middle_{offset}"));
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(6));
+    let complete_len = files.journal_len();
+    run(&mut system, operations().into_iter().skip(6));
+    drop(system);
+
+    // Flip a bit in the seventh record, either in its payload or in its length.
+    let mut content = std::fs::read(&files.journal).unwrap();
+    content[usize::try_from(complete_len).unwrap() + offset] ^= 0x10;
+    std::fs::write(&files.journal, &content).unwrap();
+
+    assert!(matches!(files.open(), Err(JournalError::Corrupted { sequence: 7 })));
+    assert_eq!(std::fs::read(&files.journal).unwrap(), content);
+}
+
+#[test]
+fn test_journal_interrupted_checkpoint() {
+    let files = Files::new("interrupted");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(4));
+    let old_journal = std::fs::read(&files.journal).unwrap();
+    system.checkpoint().unwrap();
+    drop(system);
+
+    // Crash after the snapshot was replaced, but before the journal was.
+    std::fs::write(&files.journal, &old_journal).unwrap();
+    let (mut system, report) = files.open().unwrap();
+    assert_eq!(report.snapshot_sequence, 4);
+    assert_eq!(report.replayed, 0);
+    assert_eq!(snapshot(system.accounting_system()), expected(4));
+
+    run(&mut system, operations().into_iter().skip(4));
+    drop(system);
+    let (system, report) = files.open().unwrap();
+    assert_eq!(report.replayed, 4);
+    assert_eq!(snapshot(system.accounting_system()), expected(8));
+}
+
+#[test]
+fn test_journal_newer_than_snapshot() {
+    let files = Files::new("missing");
+    let (mut system, _) = files.open().unwrap();
+    run(&mut system, operations().into_iter().take(4));
+    system.checkpoint().unwrap();
+    drop(system);
+
+    std::fs::remove_file(&files.snapshot).unwrap();
+    assert!(matches!(
+        files.open(),
+        Err(JournalError::MissingRecords { expected: 1, found: 5 })
+    ));
+}
+
+#[test]
+fn test_journal_unsupported_version() {
+    let files = Files::new("version");
+    drop(files.open().unwrap());
+
+    let mut content = std::fs::read(&files.journal).unwrap();
+    for version in [0, JOURNAL_VERSION + 1] {
+        content[8..10].copy_from_slice(&version.to_le_bytes());
+        std::fs::write(&files.journal, &content).unwrap();
+        assert!(matches!(
+            files.open(),
+            Err(JournalError::UnsupportedVersion { found, supported: JOURNAL_VERSION }) if found == version
+        ));
+    }
+
+    std::fs::File::create(&files.journal)
+        .unwrap()
+        .write_all(b"type,client,tx,amount\n")
+        .unwrap();
+    assert!(matches!(files.open(), Err(JournalError::InvalidHeader)));
+}
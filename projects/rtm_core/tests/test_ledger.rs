@@ -0,0 +1,137 @@
+use rstest::rstest;
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Currency, Transaction, TransactionId, TransactionKind},
+    processor::{AccountingSystem, Ledger, LedgerAccount, LedgerDiscrepancy},
+};
+
+mod common;
+
+use common::{amount, deposit, operations, withdrawal};
+
+#[rstest]
+#[case(1)]
+#[case(2)]
+#[case(3)]
+fn test_ledger_matches_accounting_system(#[case] seed: u64) {
+    let mut accounting_system = AccountingSystem::new();
+    let mut ledger = Ledger::new();
+    for operation in operations(2_000, 4, 100, seed) {
+        let _ = accounting_system.run_operation_with_sink(operation, &mut ledger);
+        assert_eq!(ledger.verify(&accounting_system), Ok(()));
+    }
+    assert!(ledger.entries() > 0);
+
+    let bank = ledger.balance(LedgerAccount::ExternalBank(Currency::UNSPECIFIED));
+    let clients = accounting_system
+        .iter_accounts()
+        .fold(Amount::zero(), |total, account| {
+            total + account.available_balance.clone() + account.held_balance.clone()
+        });
+    assert_eq!(-bank, clients);
+}
+
+#[test]
+fn test_ledger_with_opening_balances() {
+    let mut accounting_system = AccountingSystem::new();
+    for operation in operations(500, 4, 100, 4) {
+        let _ = accounting_system.run_operation(operation);
+    }
+
+    let mut ledger = Ledger::with_opening_balances(&accounting_system);
+    assert_eq!(ledger.verify(&accounting_system), Ok(()));
+    for operation in operations(500, 4, 100, 5) {
+        let _ = accounting_system.run_operation_with_sink(operation, &mut ledger);
+    }
+    assert_eq!(ledger.verify(&accounting_system), Ok(()));
+}
+
+#[test]
+fn test_ledger_reports_discrepancies() {
+    let mut accounting_system = AccountingSystem::new();
+    let mut ledger = Ledger::new();
+    accounting_system
+        .run_operation_with_sink(deposit(1, 1, 5), &mut ledger)
+        .unwrap();
+    accounting_system.run_operation(deposit(1, 2, 3)).unwrap();
+    accounting_system.run_operation(deposit(2, 3, 1)).unwrap();
+
+    assert_eq!(
+        ledger.verify(&accounting_system),
+        Err(vec![
+            LedgerDiscrepancy::BalanceMismatch {
+                account: LedgerAccount::Available(ClientId::from(1), Currency::UNSPECIFIED),
+                ledger: Amount::from(5),
+                accounting_system: Amount::from(8),
+            },
+            LedgerDiscrepancy::BalanceMismatch {
+                account: LedgerAccount::Available(ClientId::from(2), Currency::UNSPECIFIED),
+                ledger: Amount::zero(),
+                accounting_system: Amount::from(1),
+            },
+        ])
+    );
+    assert_eq!(
+        Ledger::new().verify(&AccountingSystem::new()),
+        Ok(()),
+        "empty ledger matches empty accounting system"
+    );
+}
+
+#[test]
+fn test_ledger_reports_overflow() {
+    let max_amount = amount("7922816251426433759354395.0335");
+    let mut accounting_system = AccountingSystem::new();
+    let mut ledger = Ledger::new();
+    for operation in [
+        deposit(1, 1, max_amount.clone()),
+        deposit(2, 2, max_amount),
+        withdrawal(1, 3, 1),
+    ] {
+        accounting_system
+            .run_operation_with_sink(operation, &mut ledger)
+            .unwrap();
+    }
+
+    assert_eq!(ledger.entries(), 3);
+    assert_eq!(
+        ledger.verify(&accounting_system),
+        Err(vec![LedgerDiscrepancy::Overflow {
+            account: LedgerAccount::ExternalBank(Currency::UNSPECIFIED),
+        }])
+    );
+}
+
+#[test]
+fn test_ledger_accounts_per_currency() {
+    let eur = Currency::try_from("EUR").unwrap();
+    let mut accounting_system = AccountingSystem::new();
+    let mut ledger = Ledger::new();
+    let transaction = Transaction::with_currency(
+        ClientId::from(1),
+        TransactionId::from(1),
+        Amount::from(5),
+        eur,
+        TransactionKind::Deposit,
+    );
+    accounting_system
+        .run_operation_with_sink(AccountingOperation::Transaction { transaction }, &mut ledger)
+        .unwrap();
+    accounting_system
+        .run_operation_with_sink(deposit(1, 2, 3), &mut ledger)
+        .unwrap();
+
+    assert_eq!(ledger.verify(&accounting_system), Ok(()));
+    assert_eq!(ledger.balance(LedgerAccount::ExternalBank(eur)), Amount::from(-5));
+    assert_eq!(
+        ledger.balance(LedgerAccount::ExternalBank(Currency::UNSPECIFIED)),
+        Amount::from(-3)
+    );
+    assert_eq!(
+        LedgerAccount::Available(ClientId::from(1), eur).to_string(),
+        "client:1:available:EUR"
+    );
+    assert_eq!(
+        LedgerAccount::Held(ClientId::from(1), Currency::UNSPECIFIED).to_string(),
+        "client:1:held"
+    );
+}
@@ -0,0 +1,417 @@
+use std::fmt::Debug;
+
+use rstest::rstest;
+use rtm_core::{
+    models::{
+        AccountingOperation, Amount, AmountPolicy, ClientId, Currency, MAX_REASON_LENGTH, Rounding, Timestamp,
+        Transaction, TransactionId, TransactionKind,
+    },
+    processor::{
+        AccountingEvent, AccountingSystem, AccountingSystemConfig, ClientAccount, ClientAccountSnapshot,
+        ClientAccountState, OverdraftPolicy, TransactionError,
+    },
+};
+use serde::{Serialize, de::DeserializeOwned};
+
+mod common;
+
+use common::{amount, chargeback, deposit, dispute};
+
+fn round_trip<T: Serialize + DeserializeOwned>(value: &T, expected_json: &str) -> T {
+    let json = serde_json::to_string(value).unwrap();
+    assert_eq!(json, expected_json);
+    serde_json::from_str(&json).unwrap()
+}
+
+fn assert_round_trip<T: Serialize + DeserializeOwned + PartialEq + Debug>(value: &T, expected_json: &str) {
+    assert_eq!(&round_trip(value, expected_json), value);
+}
+
+#[test]
+fn test_serde_primitives() {
+    assert_round_trip(&Amount::try_from("1.5").unwrap(), r#""1.5000""#);
+    assert_round_trip(&Amount::try_from("-0.0001").unwrap(), r#""-0.0001""#);
+    assert_round_trip(&ClientId::from(7), "7");
+    assert_round_trip(&TransactionId::from(u32::MAX), "4294967295");
+    assert_round_trip(&TransactionKind::Withdrawal, r#""withdrawal""#);
+    assert_round_trip(&ClientAccountState::Locked, r#""locked""#);
+    assert_round_trip(&ClientAccountState::Frozen, r#""frozen""#);
+    assert_round_trip(&ClientAccountState::Closed, r#""closed""#);
+}
+
+#[rstest]
+#[case("1.5")]
+#[case("abc")]
+fn test_serde_amount_requires_decimal_string(#[case] value: &str) {
+    assert!(serde_json::from_str::<Amount>(value).is_err());
+}
+
+#[rstest]
+#[case(
+    deposit(1, 2, amount("3.25")),
+    r#"{"type":"deposit","client":1,"tx":2,"amount":"3.2500"}"#
+)]
+#[case(
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(ClientId::from(1), TransactionId::from(3), Amount::from(2), TransactionKind::Withdrawal),
+    },
+    r#"{"type":"withdrawal","client":1,"tx":3,"amount":"2.0000"}"#
+)]
+#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None }, r#"{"type":"dispute","client":1,"tx":2}"#)]
+#[case(AccountingOperation::Resolve { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None }, r#"{"type":"resolve","client":1,"tx":2}"#)]
+#[case(AccountingOperation::Chargeback { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: None }, r#"{"type":"chargeback","client":1,"tx":2}"#)]
+#[case(
+    AccountingOperation::Transaction {
+        transaction: Transaction::new(ClientId::from(1), TransactionId::from(2), Amount::from(1), TransactionKind::Deposit)
+            .with_timestamp(Some(Timestamp::from(1_700_000_000))),
+    },
+    r#"{"type":"deposit","client":1,"tx":2,"amount":"1.0000","timestamp":1700000000}"#
+)]
+#[case(AccountingOperation::Dispute { client_id: ClientId::from(1), ref_id: TransactionId::from(2), currency: None, timestamp: Some(Timestamp::from(1_700_000_060)) }, r#"{"type":"dispute","client":1,"tx":2,"timestamp":1700000060}"#)]
+#[case(AccountingOperation::Unlock { client_id: ClientId::from(1), reason: "reviewed".to_string(), timestamp: None }, r#"{"type":"unlock","client":1,"reason":"reviewed"}"#)]
+#[case(AccountingOperation::Freeze { client_id: ClientId::from(1), reason: "fraud".to_string(), timestamp: Some(Timestamp::from(1)) }, r#"{"type":"freeze","client":1,"reason":"fraud","timestamp":1}"#)]
+#[case(AccountingOperation::Close { client_id: ClientId::from(1), reason: "closed".to_string(), timestamp: None }, r#"{"type":"close","client":1,"reason":"closed"}"#)]
+fn test_serde_accounting_operation(#[case] operation: AccountingOperation, #[case] expected_json: &str) {
+    let result = round_trip(&operation, expected_json);
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[rstest]
+#[case(r#"{"type":"deposit","client":1,"tx":2,"amount":"0"}"#)]
+#[case(r#"{"type":"withdrawal","client":1,"tx":2,"amount":"-1.5"}"#)]
+#[case(r#"{"type":"deposit","client":1,"tx":2,"amount":"1.2.3"}"#)]
+#[case(r#"{"type":"freeze","client":1,"reason":" "}"#)]
+#[case(r#"{"type":"close","client":1}"#)]
+fn test_serde_accounting_operation_invalid(#[case] json: &str) {
+    assert!(serde_json::from_str::<AccountingOperation>(json).is_err());
+}
+
+#[test]
+fn test_serde_accounting_operation_reason_too_long() {
+    let json = format!(
+        r#"{{"type":"freeze","client":1,"reason":"{}"}}"#,
+        "x".repeat(MAX_REASON_LENGTH)
+    );
+    assert!(serde_json::from_str::<AccountingOperation>(&json).is_ok());
+
+    let json = format!(
+        r#"{{"type":"freeze","client":1,"reason":"{}"}}"#,
+        "x".repeat(MAX_REASON_LENGTH + 1)
+    );
+    assert!(serde_json::from_str::<AccountingOperation>(&json).is_err());
+}
+
+#[test]
+fn test_serde_transaction() {
+    let AccountingOperation::Transaction { transaction } = deposit(1, 2, amount("3")) else {
+        unreachable!();
+    };
+    let result = round_trip(
+        &transaction,
+        r#"{"client":1,"tx":2,"amount":"3.0000","type":"deposit"}"#,
+    );
+    assert_eq!(result.client_id(), transaction.client_id());
+    assert_eq!(result.id(), transaction.id());
+    assert_eq!(result.amount(), transaction.amount());
+    assert_eq!(result.kind(), transaction.kind());
+}
+
+#[rstest]
+#[case(TransactionError::AccountLocked { client_id: ClientId::from(1) }, r#"{"error":"AccountLocked","client_id":1}"#)]
+#[case(TransactionError::InsufficientFunds { cause_id: TransactionId::from(4) }, r#"{"error":"InsufficientFunds","cause_id":4}"#)]
+#[case(TransactionError::TransactionNotDisputed { ref_id: TransactionId::from(4) }, r#"{"error":"TransactionNotDisputed","ref_id":4}"#)]
+#[case(TransactionError::CrossClientTransaction, r#"{"error":"CrossClientTransaction"}"#)]
+fn test_serde_transaction_error(#[case] error: TransactionError, #[case] expected_json: &str) {
+    assert_round_trip(&error, expected_json);
+}
+
+#[test]
+fn test_serde_client_account() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 2, amount("1"))).unwrap();
+    accounting_system.run_operation(deposit(1, 1, amount("2"))).unwrap();
+    accounting_system
+        .run_operation(AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        })
+        .unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+
+    assert_round_trip(
+        &account.snapshot(),
+        r#"{"client":1,"available":"1.0000","held":"2.0000","total":"3.0000","locked":false}"#,
+    );
+
+    let expected_json = concat!(
+        r#"{"client":1,"available":"1.0000","held":"2.0000","total":"3.0000","locked":false,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"2.0000","type":"deposit"},"#,
+        r#"{"client":1,"tx":2,"amount":"1.0000","type":"deposit"}],"disputed":[1]}"#
+    );
+    let result: ClientAccount = round_trip(account, expected_json);
+    assert_eq!(result.snapshot(), account.snapshot());
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[test]
+fn test_serde_client_account_precision() {
+    let amount_policy = AmountPolicy::new(8, Rounding::Reject);
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: amount_policy.into(),
+        ..AccountingSystemConfig::default()
+    });
+    let amount = amount_policy.parse("1.12345678").unwrap();
+    accounting_system.run_operation(deposit(1, 1, amount)).unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+
+    assert_round_trip(
+        &account.snapshot(),
+        r#"{"client":1,"available":"1.12345678","held":"0.00000000","total":"1.12345678","locked":false}"#,
+    );
+    let expected_json = concat!(
+        r#"{"client":1,"available":"1.12345678","held":"0.00000000","total":"1.12345678","locked":false,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"1.12345678","type":"deposit"}],"disputed":[]}"#
+    );
+    let result: ClientAccount = round_trip(account, expected_json);
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[test]
+fn test_serde_client_account_partial_hold() {
+    let mut accounting_system = AccountingSystem::with_config(AccountingSystemConfig {
+        overdraft_policy: OverdraftPolicy::CapHold,
+        ..AccountingSystemConfig::default()
+    });
+    accounting_system.run_operation(deposit(1, 1, amount("2"))).unwrap();
+    accounting_system
+        .run_operation(AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(1),
+                TransactionId::from(2),
+                Amount::try_from("1.5").unwrap(),
+                TransactionKind::Withdrawal,
+            ),
+        })
+        .unwrap();
+    accounting_system
+        .run_operation(AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        })
+        .unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+
+    let expected_json = concat!(
+        r#"{"client":1,"available":"0.0000","held":"0.5000","total":"0.5000","locked":false,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"2.0000","type":"deposit"},"#,
+        r#"{"client":1,"tx":2,"amount":"1.5000","type":"withdrawal"}],"disputed":[1],"#,
+        r#""partially_held":[{"tx":1,"held":"0.5000"}]}"#
+    );
+    let result: ClientAccount = round_trip(account, expected_json);
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[test]
+fn test_serde_client_account_dispute_timestamp() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation(AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(1),
+                TransactionId::from(1),
+                Amount::from(1),
+                TransactionKind::Deposit,
+            )
+            .with_timestamp(Some(Timestamp::from(100))),
+        })
+        .unwrap();
+    accounting_system
+        .run_operation(AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: Some(Timestamp::from(160)),
+        })
+        .unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+
+    let expected_json = concat!(
+        r#"{"client":1,"available":"0.0000","held":"1.0000","total":"1.0000","locked":false,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"1.0000","type":"deposit","timestamp":100}],"disputed":[1],"#,
+        r#""dispute_timestamps":[{"tx":1,"timestamp":160}]}"#
+    );
+    let result: ClientAccount = round_trip(account, expected_json);
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[test]
+fn test_serde_client_account_charged_back() {
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system.run_operation(deposit(1, 1, amount("2"))).unwrap();
+    for operation in [dispute(1, 1), chargeback(1, 1)] {
+        accounting_system.run_operation(operation).unwrap();
+    }
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+
+    let expected_json = concat!(
+        r#"{"client":1,"available":"0.0000","held":"0.0000","total":"0.0000","locked":true,"#,
+        r#""transactions":[{"client":1,"tx":1,"amount":"2.0000","type":"deposit"}],"disputed":[],"charged_back":[1]}"#
+    );
+    let result: ClientAccount = round_trip(account, expected_json);
+    assert_eq!(serde_json::to_string(&result).unwrap(), expected_json);
+}
+
+#[test]
+fn test_serde_client_account_dispute_window() {
+    let config = AccountingSystemConfig {
+        dispute_window: Some(2),
+        ..AccountingSystemConfig::default()
+    };
+    let mut accounting_system = AccountingSystem::with_config(config.clone());
+    accounting_system.run_operation(deposit(1, 2, amount("1"))).unwrap();
+    accounting_system.run_operation(deposit(1, 1, amount("1"))).unwrap();
+    let account = accounting_system
+        .account(ClientId::from(1), Currency::UNSPECIFIED)
+        .unwrap();
+    let json = serde_json::to_string(account).unwrap();
+    let account: ClientAccount = serde_json::from_str(&json).unwrap();
+
+    // Restored transactions are dropped from the oldest, not before the new one.
+    let mut accounting_system = AccountingSystem::with_accounts(config, [account]).unwrap();
+    accounting_system.run_operation(deposit(1, 3, amount("1"))).unwrap();
+    let result = accounting_system.run_operation(dispute(1, 2));
+    assert!(matches!(result, Err(TransactionError::TransactionDoesNotExist { .. })));
+    accounting_system.run_operation(dispute(1, 1)).unwrap();
+    accounting_system.run_operation(dispute(1, 3)).unwrap();
+}
+
+#[rstest]
+#[case(
+    r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[],"disputed":[]}"#,
+    r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[],"disputed":[]}"#
+)]
+#[case(
+    r#"{"client":1,"available":"1","held":"0","total":"1","locked":false,"transactions":[{"client":1,"tx":1,"amount":"1","type":"deposit"}],"disputed":[]}"#,
+    r#"{"client":2,"available":"1","held":"0","total":"1","locked":false,"transactions":[{"client":2,"tx":1,"amount":"1","type":"deposit"}],"disputed":[]}"#
+)]
+fn test_serde_client_accounts_conflicting(#[case] first: &str, #[case] second: &str) {
+    let accounts = [first, second].map(|json| serde_json::from_str::<ClientAccount>(json).unwrap());
+    assert!(AccountingSystem::with_accounts(AccountingSystemConfig::default(), accounts).is_err());
+}
+
+#[rstest]
+#[case(r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[],"disputed":[1]}"#)]
+#[case(
+    r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[{"client":1,"tx":1,"amount":"1","type":"deposit"}],"disputed":[],"dispute_timestamps":[{"tx":1,"timestamp":0}]}"#
+)]
+#[case(
+    r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[{"client":1,"tx":1,"amount":"1","type":"deposit"}],"disputed":[],"partially_held":[{"tx":1,"held":"0"}]}"#
+)]
+#[case(
+    r#"{"client":1,"available":"0","held":"0","total":"0","locked":false,"transactions":[{"client":2,"tx":1,"amount":"1","type":"deposit"}],"disputed":[]}"#
+)]
+#[case(
+    r#"{"client":1,"currency":"EUR","available":"0","held":"0","total":"0","locked":false,"transactions":[{"client":1,"tx":1,"amount":"1","type":"deposit"}],"disputed":[]}"#
+)]
+#[case(r#"{"client":1,"available":"0","held":"0","total":"0","locked":true,"transactions":[],"disputed":[],"charged_back":[1]}"#)]
+#[case(
+    r#"{"client":1,"available":"0","held":"1","total":"1","locked":true,"transactions":[{"client":1,"tx":1,"amount":"1","type":"deposit"}],"disputed":[1],"charged_back":[1]}"#
+)]
+fn test_serde_client_account_inconsistent(#[case] json: &str) {
+    assert!(serde_json::from_str::<ClientAccount>(json).is_err());
+}
+
+#[test]
+fn test_serde_snapshot_fields() {
+    let snapshot = ClientAccountSnapshot {
+        client_id: ClientId::from(3),
+        currency: Currency::UNSPECIFIED,
+        available_balance: Amount::from(1),
+        held_balance: Amount::zero(),
+        state: ClientAccountState::Locked,
+    };
+    assert_round_trip(
+        &snapshot,
+        r#"{"client":3,"available":"1.0000","held":"0.0000","total":"1.0000","locked":true}"#,
+    );
+    let snapshot = ClientAccountSnapshot {
+        currency: Currency::try_from("EUR").unwrap(),
+        ..snapshot
+    };
+    assert_round_trip(
+        &snapshot,
+        r#"{"client":3,"available":"1.0000","held":"0.0000","total":"1.0000","locked":true,"currency":"EUR"}"#,
+    );
+    let snapshot = ClientAccountSnapshot {
+        currency: Currency::UNSPECIFIED,
+        available_balance: Amount::from(-1),
+        held_balance: Amount::from(2),
+        state: ClientAccountState::Frozen,
+        ..snapshot
+    };
+    assert_round_trip(
+        &snapshot,
+        r#"{"client":3,"available":"-1.0000","held":"2.0000","total":"1.0000","locked":true,"overdrawn":true,"state":"frozen"}"#,
+    );
+}
+
+#[rstest]
+#[case(r#"{"client":1,"available":"1","held":"1","total":"1","locked":false}"#)]
+#[case(r#"{"client":1,"available":"1","held":"0","total":"1","locked":false,"state":"frozen"}"#)]
+#[case(r#"{"client":1,"available":"-1","held":"1","total":"0","locked":false}"#)]
+#[case(r#"{"client":1,"available":"1","held":"0","locked":false}"#)]
+fn test_serde_snapshot_inconsistent(#[case] json: &str) {
+    assert!(serde_json::from_str::<ClientAccountSnapshot>(json).is_err());
+}
+
+#[test]
+fn test_serde_accounting_event() {
+    let mut events = Vec::new();
+    let mut accounting_system = AccountingSystem::new();
+    accounting_system
+        .run_operation_with_sink(deposit(1, 2, amount("1.5")), &mut events)
+        .unwrap();
+    assert_round_trip(
+        &events[0],
+        concat!(
+            r#"{"event":"Deposited","client":1,"tx":2,"amount":"1.5000","balances":{"#,
+            r#""before":{"available":"0.0000","held":"0.0000"},"after":{"available":"1.5000","held":"0.0000"}}}"#
+        ),
+    );
+    assert_round_trip(
+        &AccountingEvent::AccountOverdrawn {
+            client_id: ClientId::from(1),
+            transaction_id: TransactionId::from(2),
+            currency: Currency::UNSPECIFIED,
+        },
+        r#"{"event":"AccountOverdrawn","client":1,"tx":2}"#,
+    );
+    assert_round_trip(
+        &AccountingEvent::AccountLocked {
+            client_id: ClientId::from(1),
+            transaction_id: TransactionId::from(2),
+        },
+        r#"{"event":"AccountLocked","client":1,"tx":2}"#,
+    );
+    assert_round_trip(
+        &AccountingEvent::AccountFrozen {
+            client_id: ClientId::from(1),
+            reason: "suspected fraud".to_string(),
+        },
+        r#"{"event":"AccountFrozen","client":1,"reason":"suspected fraud"}"#,
+    );
+}
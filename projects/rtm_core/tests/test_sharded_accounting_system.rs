@@ -0,0 +1,87 @@
+use std::num::NonZeroUsize;
+
+use rstest::rstest;
+use rtm_core::{
+    models::TransactionId,
+    processor::{AccountingSystem, AccountingSystemConfig, ShardError, ShardedAccountingSystem, TransactionError},
+};
+
+mod common;
+
+use common::{deposit, describe, operations};
+
+#[rstest]
+#[case(1, 1)]
+#[case(2, 2)]
+#[case(4, 3)]
+#[case(7, 4)]
+fn test_sharded_matches_sequential(#[case] shards: usize, #[case] seed: u64) {
+    let operations = operations(20_000, 16, 200, seed);
+
+    let mut sequential = AccountingSystem::new();
+    let mut expected_rejections = Vec::new();
+    for (index, operation) in operations.iter().enumerate() {
+        if let Err(error) = sequential.run_operation(operation.clone()) {
+            expected_rejections.push((index, error));
+        }
+    }
+
+    let mut sharded =
+        ShardedAccountingSystem::new(AccountingSystemConfig::default(), NonZeroUsize::new(shards).unwrap());
+    for (index, operation) in operations.into_iter().enumerate() {
+        sharded.run_operation(index, operation).unwrap();
+    }
+    let (merged, rejections) = sharded.finish().unwrap();
+
+    assert_eq!(describe(&merged), describe(&sequential));
+    assert_eq!(rejections, expected_rejections);
+}
+
+#[test]
+fn test_sharded_duplicate_across_shards() {
+    let mut sharded = ShardedAccountingSystem::new(AccountingSystemConfig::default(), NonZeroUsize::new(2).unwrap());
+    sharded.run_operation("first", deposit(1, 1, 1)).unwrap();
+    sharded.run_operation("second", deposit(2, 1, 1)).unwrap();
+    let (_, rejections) = sharded.finish().unwrap();
+
+    assert_eq!(
+        rejections,
+        vec![(
+            "second",
+            TransactionError::DuplicateTransaction {
+                cause_id: TransactionId::from(1)
+            }
+        )]
+    );
+}
+
+/// Tag panicking when dropped, i.e. once the worker is done with an applied operation.
+struct PanickingTag(bool);
+
+impl Drop for PanickingTag {
+    fn drop(&mut self) {
+        assert!(!self.0, "tag dropped");
+    }
+}
+
+#[test]
+fn test_sharded_worker_panic_while_waiting() {
+    let mut sharded = ShardedAccountingSystem::new(AccountingSystemConfig::default(), NonZeroUsize::new(2).unwrap());
+    // Worker of client 0 panics after the first deposit, so the second one never completes.
+    sharded.run_operation(PanickingTag(true), deposit(0, 1, 1)).unwrap();
+    sharded.run_operation(PanickingTag(false), deposit(0, 2, 1)).unwrap();
+
+    assert_eq!(
+        sharded.run_operation(PanickingTag(false), deposit(1, 2, 1)),
+        Err(ShardError::WorkerPanicked { shard: 0 })
+    );
+}
+
+#[test]
+fn test_sharded_worker_panic_on_finish() {
+    let mut sharded = ShardedAccountingSystem::new(AccountingSystemConfig::default(), NonZeroUsize::new(2).unwrap());
+    sharded.run_operation(PanickingTag(false), deposit(1, 1, 1)).unwrap();
+    sharded.run_operation(PanickingTag(true), deposit(0, 2, 1)).unwrap();
+
+    assert_eq!(sharded.finish().err(), Some(ShardError::WorkerPanicked { shard: 0 }));
+}
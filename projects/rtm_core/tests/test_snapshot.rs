@@ -0,0 +1,388 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use rstest::rstest;
+use rtm_core::{
+    models::{AccountingOperation, Amount, ClientId, Currency, Timestamp, Transaction, TransactionId, TransactionKind},
+    processor::{
+        AccountingSystem, AccountingSystemConfig, ClientAccountState, DisputePolicy, OverdraftPolicy, SNAPSHOT_VERSION,
+        ShardedAccountingSystem, SnapshotError, TransactionError,
+    },
+};
+
+mod common;
+
+use common::{chargeback, deposit, describe, dispute, withdrawal};
+
+fn eur() -> Currency {
+    Currency::try_from("EUR").unwrap()
+}
+
+/// Client 1 with a disputed deposit, client 2 with a regular one and a disputed one in euro,
+/// and locked client 3.
+fn yesterday() -> AccountingSystem {
+    let mut accounting_system = AccountingSystem::new();
+    for operation in [
+        deposit(1, 1, 10),
+        deposit(1, 2, 5),
+        dispute(1, 2),
+        deposit(2, 3, 7),
+        deposit(3, 4, 1),
+        dispute(3, 4),
+        chargeback(3, 4),
+        AccountingOperation::Transaction {
+            transaction: Transaction::with_currency(
+                ClientId::from(2),
+                TransactionId::from(5),
+                Amount::from(2),
+                eur(),
+                TransactionKind::Deposit,
+            ),
+        },
+        AccountingOperation::Dispute {
+            client_id: ClientId::from(2),
+            ref_id: TransactionId::from(5),
+            currency: Some(eur()),
+            timestamp: None,
+        },
+    ] {
+        accounting_system.run_operation(operation).unwrap();
+    }
+    accounting_system
+}
+
+fn save(accounting_system: &AccountingSystem) -> Vec<u8> {
+    let mut snapshot = Vec::new();
+    accounting_system.save_snapshot(&mut snapshot).unwrap();
+    snapshot
+}
+
+fn load(snapshot: &[u8]) -> Result<AccountingSystem, SnapshotError> {
+    AccountingSystem::load_snapshot(AccountingSystemConfig::default(), snapshot)
+}
+
+#[test]
+fn test_snapshot_round_trip() {
+    let original = yesterday();
+    let snapshot = save(&original);
+    let mut restored = load(&snapshot).unwrap();
+    assert_eq!(describe(&restored), describe(&original));
+    assert_eq!(save(&restored), snapshot);
+
+    assert_eq!(
+        restored.run_operation(deposit(2, 1, 1)),
+        Err(TransactionError::DuplicateTransaction {
+            cause_id: TransactionId::from(1)
+        })
+    );
+    restored.run_operation(dispute(2, 3)).unwrap();
+    restored.run_operation(chargeback(1, 2)).unwrap();
+    assert_eq!(
+        restored.run_operation(deposit(3, 5, 1)),
+        Err(TransactionError::AccountLocked {
+            client_id: ClientId::from(3)
+        })
+    );
+    restored
+        .run_operation(AccountingOperation::Unlock {
+            client_id: ClientId::from(3),
+            reason: "reviewed".to_string(),
+            timestamp: None,
+        })
+        .unwrap();
+    assert_eq!(
+        restored.run_operation(dispute(3, 4)),
+        Err(TransactionError::TransactionAlreadyDisputed {
+            ref_id: TransactionId::from(4)
+        })
+    );
+
+    let first = restored.account(ClientId::from(1), Currency::UNSPECIFIED).unwrap();
+    assert_eq!(first.available_balance, Amount::from(10));
+    assert_eq!(first.held_balance, Amount::zero());
+    assert_eq!(first.state, ClientAccountState::Locked);
+    let second = restored.account(ClientId::from(2), Currency::UNSPECIFIED).unwrap();
+    assert_eq!(second.held_balance, Amount::from(7));
+    let second = restored.account(ClientId::from(2), eur()).unwrap();
+    assert_eq!(second.held_balance, Amount::from(2));
+    assert_eq!(restored.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_snapshot_partial_hold() {
+    let config = AccountingSystemConfig {
+        overdraft_policy: OverdraftPolicy::CapHold,
+        ..AccountingSystemConfig::default()
+    };
+    let mut original = AccountingSystem::with_config(config.clone());
+    original.run_operation(deposit(1, 1, 10)).unwrap();
+    original
+        .run_operation(AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(1),
+                TransactionId::from(2),
+                Amount::from(8),
+                TransactionKind::Withdrawal,
+            ),
+        })
+        .unwrap();
+    original.run_operation(dispute(1, 1)).unwrap();
+
+    let snapshot = save(&original);
+    let mut restored = AccountingSystem::load_snapshot(config, snapshot.as_slice()).unwrap();
+    assert_eq!(save(&restored), snapshot);
+
+    // Only the held part of the deposit is released.
+    restored
+        .run_operation(AccountingOperation::Resolve {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: None,
+        })
+        .unwrap();
+    let account = restored.account(ClientId::from(1), Currency::UNSPECIFIED).unwrap();
+    assert_eq!(account.available_balance, Amount::from(2));
+    assert_eq!(account.held_balance, Amount::zero());
+    assert_eq!(restored.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_snapshot_timestamps() {
+    let config = AccountingSystemConfig {
+        dispute_time_limit: Some(Duration::from_secs(10)),
+        chargeback_time_limit: Some(Duration::from_secs(5)),
+        ..AccountingSystemConfig::default()
+    };
+    let timed_deposit = |id: u32| AccountingOperation::Transaction {
+        transaction: Transaction::new(
+            ClientId::from(1),
+            TransactionId::from(id),
+            Amount::from(1),
+            TransactionKind::Deposit,
+        )
+        .with_timestamp(Some(Timestamp::from(100))),
+    };
+    let mut original = AccountingSystem::with_config(config.clone());
+    original.run_operation(timed_deposit(1)).unwrap();
+    original.run_operation(timed_deposit(2)).unwrap();
+    original
+        .run_operation(AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: Some(Timestamp::from(102)),
+        })
+        .unwrap();
+
+    let snapshot = save(&original);
+    let mut restored = AccountingSystem::load_snapshot(config, snapshot.as_slice()).unwrap();
+    assert_eq!(save(&restored), snapshot);
+
+    // Both the transaction and the dispute timestamps survive the snapshot.
+    assert_eq!(
+        restored.run_operation(AccountingOperation::Dispute {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(2),
+            currency: None,
+            timestamp: Some(Timestamp::from(111)),
+        }),
+        Err(TransactionError::DisputeWindowExpired {
+            ref_id: TransactionId::from(2)
+        })
+    );
+    assert_eq!(
+        restored.run_operation(AccountingOperation::Chargeback {
+            client_id: ClientId::from(1),
+            ref_id: TransactionId::from(1),
+            currency: None,
+            timestamp: Some(Timestamp::from(108)),
+        }),
+        Err(TransactionError::DisputeWindowExpired {
+            ref_id: TransactionId::from(1)
+        })
+    );
+    assert_eq!(restored.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_snapshot_administrative_states() {
+    let mut original = AccountingSystem::new();
+    for operation in [
+        deposit(1, 1, 10),
+        AccountingOperation::Freeze {
+            client_id: ClientId::from(1),
+            reason: "suspected fraud".to_string(),
+            timestamp: None,
+        },
+        deposit(2, 2, 1),
+        AccountingOperation::Transaction {
+            transaction: Transaction::new(
+                ClientId::from(2),
+                TransactionId::from(3),
+                Amount::from(1),
+                TransactionKind::Withdrawal,
+            ),
+        },
+        AccountingOperation::Close {
+            client_id: ClientId::from(2),
+            reason: "requested by client".to_string(),
+            timestamp: None,
+        },
+    ] {
+        original.run_operation(operation).unwrap();
+    }
+
+    let snapshot = save(&original);
+    let mut restored = load(&snapshot).unwrap();
+    assert_eq!(describe(&restored), describe(&original));
+    assert_eq!(save(&restored), snapshot);
+    assert_eq!(
+        restored
+            .account(ClientId::from(1), Currency::UNSPECIFIED)
+            .unwrap()
+            .state,
+        ClientAccountState::Frozen
+    );
+    assert_eq!(
+        restored
+            .account(ClientId::from(2), Currency::UNSPECIFIED)
+            .unwrap()
+            .state,
+        ClientAccountState::Closed
+    );
+    restored
+        .run_operation(AccountingOperation::Unlock {
+            client_id: ClientId::from(1),
+            reason: "reviewed".to_string(),
+            timestamp: None,
+        })
+        .unwrap();
+    restored.run_operation(dispute(1, 1)).unwrap();
+    assert_eq!(restored.verify_invariants(), Ok(()));
+}
+
+#[test]
+fn test_snapshot_of_full_page() {
+    let mut accounting_system = AccountingSystem::new();
+    for id in 0..70_000 {
+        accounting_system.run_operation(deposit(1, id, 1)).unwrap();
+    }
+    let mut restored = load(&save(&accounting_system)).unwrap();
+    for id in [0, 65_535, 65_536, 69_999] {
+        assert_eq!(
+            restored.run_operation(deposit(2, id, 1)),
+            Err(TransactionError::DuplicateTransaction {
+                cause_id: TransactionId::from(id)
+            })
+        );
+    }
+    restored.run_operation(deposit(2, 70_000, 1)).unwrap();
+}
+
+#[test]
+fn test_snapshot_restored_with_stricter_dispute_policy() {
+    let mut original = AccountingSystem::new();
+    original.run_operation(deposit(1, 1, 10)).unwrap();
+    original.run_operation(withdrawal(1, 2, 3)).unwrap();
+
+    let config = AccountingSystemConfig {
+        dispute_policy: DisputePolicy::DepositsOnly,
+        ..AccountingSystemConfig::default()
+    };
+    let mut restored = AccountingSystem::load_snapshot(config, save(&original).as_slice()).unwrap();
+    assert_eq!(
+        restored.run_operation(dispute(1, 2)),
+        Err(TransactionError::TransactionNotDisputable {
+            ref_id: TransactionId::from(2)
+        })
+    );
+    restored.run_operation(dispute(1, 1)).unwrap();
+}
+
+#[test]
+fn test_snapshot_keeps_dispute_window_order() {
+    let config = AccountingSystemConfig {
+        dispute_window: Some(3),
+        ..AccountingSystemConfig::default()
+    };
+    let mut original = AccountingSystem::with_config(config.clone());
+    for id in [3, 1, 2] {
+        original.run_operation(deposit(1, id, 1)).unwrap();
+    }
+    let mut restored = AccountingSystem::load_snapshot(config, save(&original).as_slice()).unwrap();
+
+    // The oldest transaction is dropped, not the one with the lowest id.
+    restored.run_operation(deposit(1, 4, 1)).unwrap();
+    assert_eq!(
+        restored.run_operation(dispute(1, 3)),
+        Err(TransactionError::TransactionDoesNotExist {
+            ref_id: TransactionId::from(3)
+        })
+    );
+    restored.run_operation(dispute(1, 1)).unwrap();
+}
+
+#[test]
+fn test_snapshot_continued_by_sharded_system() {
+    let snapshot = save(&yesterday());
+    let today = [
+        deposit(4, 1, 3),
+        deposit(4, 10, 3),
+        dispute(1, 1),
+        chargeback(2, 3),
+        dispute(2, 3),
+    ];
+
+    let mut sequential = load(&snapshot).unwrap();
+    let expected_rejections = today
+        .iter()
+        .enumerate()
+        .filter_map(|(index, operation)| {
+            sequential
+                .run_operation(operation.clone())
+                .err()
+                .map(|err| (index, err))
+        })
+        .collect::<Vec<_>>();
+
+    let mut sharded = ShardedAccountingSystem::from_system(load(&snapshot).unwrap(), NonZeroUsize::new(3).unwrap());
+    for (index, operation) in today.into_iter().enumerate() {
+        sharded.run_operation(index, operation).unwrap();
+    }
+    let (sharded, rejections) = sharded.finish().unwrap();
+
+    assert_eq!(rejections, expected_rejections);
+    assert_eq!(describe(&sharded), describe(&sequential));
+    assert_eq!(save(&sharded), save(&sequential));
+}
+
+#[rstest]
+#[case(0)]
+#[case(SNAPSHOT_VERSION + 1)]
+fn test_snapshot_unsupported_version(#[case] version: u16) {
+    let mut snapshot = save(&yesterday());
+    snapshot[8..10].copy_from_slice(&version.to_le_bytes());
+    assert!(matches!(
+        load(&snapshot),
+        Err(SnapshotError::UnsupportedVersion { found, supported: SNAPSHOT_VERSION }) if found == version
+    ));
+}
+
+#[rstest]
+#[case(b"".as_slice())]
+#[case(b"transaction,client,tx,amount\n".as_slice())]
+fn test_snapshot_invalid_header(#[case] snapshot: &[u8]) {
+    assert!(matches!(load(snapshot), Err(SnapshotError::InvalidHeader)));
+}
+
+#[test]
+fn test_snapshot_corrupted() {
+    let snapshot = save(&yesterday());
+
+    let mut corrupted = snapshot.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert!(matches!(load(&corrupted), Err(SnapshotError::ChecksumMismatch)));
+
+    let truncated = &snapshot[..snapshot.len() - 1];
+    assert!(matches!(load(truncated), Err(SnapshotError::Io(_))));
+}
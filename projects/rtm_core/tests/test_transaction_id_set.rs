@@ -0,0 +1,31 @@
+use rtm_core::{models::TransactionId, processor::TransactionIdSet};
+
+#[test]
+fn test_transaction_id_set() {
+    let mut set = TransactionIdSet::new();
+    assert_eq!(set.len(), 0);
+    for value in [0, 1, 63, 64, 65535, 65536, u32::MAX] {
+        let id = TransactionId::from(value);
+        assert!(!set.contains(id));
+        assert!(set.insert(id));
+        assert!(set.contains(id));
+        assert!(!set.insert(id));
+    }
+    assert_eq!(set.len(), 7);
+    assert!(!set.contains(TransactionId::from(2)));
+    assert!(!set.contains(TransactionId::from(u32::MAX - 1)));
+}
+
+#[test]
+fn test_transaction_id_set_full_page() {
+    let mut set = TransactionIdSet::new();
+    for value in 0..(1 << 17) {
+        assert!(set.insert(TransactionId::from(value)));
+    }
+    assert_eq!(set.len(), 1 << 17);
+    for value in 0..(1 << 17) {
+        assert!(set.contains(TransactionId::from(value)));
+        assert!(!set.insert(TransactionId::from(value)));
+    }
+    assert!(!set.contains(TransactionId::from(1 << 17)));
+}
@@ -0,0 +1,172 @@
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use rtm_core::{
+    models::{AccountingOperation, ClientId, Currency},
+    processor::{ClientAccountSnapshot, TransactionError},
+    service::{AccountingHandle, ServiceError},
+};
+use serde::Serialize;
+use tokio::net::TcpListener;
+
+/// Body of every non-successful response, e.g. `{"error": "InsufficientFunds", "message": "..."}`.
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: &'static str,
+    message: String,
+}
+
+#[derive(Debug)]
+struct ErrorResponse {
+    status: StatusCode,
+    body: ErrorBody,
+}
+
+impl ErrorResponse {
+    fn invalid_request(message: String) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            body: ErrorBody {
+                error: "InvalidRequest",
+                message,
+            },
+        }
+    }
+}
+
+impl From<ServiceError> for ErrorResponse {
+    fn from(error: ServiceError) -> Self {
+        let error = match error {
+            ServiceError::Transaction(error) => error,
+            ServiceError::Stopped => {
+                return Self {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    body: ErrorBody {
+                        error: "ServiceStopped",
+                        message: "accounting service is not running".to_string(),
+                    },
+                };
+            }
+        };
+
+        let status = match error {
+            TransactionError::AccountLocked { .. }
+            | TransactionError::AccountFrozen { .. }
+            | TransactionError::AccountClosed { .. } => StatusCode::LOCKED,
+            TransactionError::TransactionDoesNotExist { .. } | TransactionError::ClientDoesNotExist { .. } => {
+                StatusCode::NOT_FOUND
+            }
+            TransactionError::DuplicateTransaction { .. }
+            | TransactionError::TransactionAlreadyDisputed { .. }
+            | TransactionError::TransactionNotDisputed { .. }
+            | TransactionError::AccountNotLocked { .. }
+            | TransactionError::AccountNotSettled { .. } => StatusCode::CONFLICT,
+            TransactionError::InsufficientFunds { .. }
+            | TransactionError::CrossClientTransaction
+            | TransactionError::ArithmeticOverflow { .. }
+            | TransactionError::NonPositiveAmount { .. }
+            | TransactionError::ExcessivePrecision { .. }
+            | TransactionError::CurrencyMismatch { .. }
+            | TransactionError::TransactionNotDisputable { .. }
+            | TransactionError::InsufficientFundsForDispute { .. }
+            | TransactionError::DisputeWindowExpired { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        };
+        Self {
+            status,
+            body: ErrorBody {
+                error: error.name(),
+                message: error.to_string(),
+            },
+        }
+    }
+}
+
+impl IntoResponse for ErrorResponse {
+    fn into_response(self) -> Response {
+        (self.status, Json(self.body)).into_response()
+    }
+}
+
+/// Builds the REST API:
+///
+/// * `POST /operations` applies a single json encoded [`AccountingOperation`], e.g.
+///   `{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}`, responding with `204 No Content`,
+/// * `GET /accounts/{client_id}` returns a single account without a currency,
+/// * `GET /accounts/{client_id}/{currency}` returns a single account in the given currency,
+/// * `GET /accounts` returns all accounts, ordered by client id and currency.
+///
+/// Accounts are returned in the json representation of [`ClientAccountSnapshot`], e.g.
+/// `{"client": 1, "available": "1.5000", "held": "0.0000", "total": "1.5000", "locked": false}`.
+/// Rejected operations are mapped to 4xx responses with [`TransactionError::name`] as the `error` field.
+pub fn router(handle: AccountingHandle) -> Router {
+    Router::new()
+        .route("/operations", post(post_operation))
+        .route("/accounts", get(get_accounts))
+        .route("/accounts/{client_id}", get(get_account))
+        .route("/accounts/{client_id}/{currency}", get(get_currency_account))
+        .with_state(handle)
+}
+
+/// Serves the [`router`] until the listener fails.
+///
+/// # Errors
+///
+/// When accepting a new connection fails.
+pub async fn serve(listener: TcpListener, handle: AccountingHandle) -> std::io::Result<()> {
+    axum::serve(listener, router(handle)).await
+}
+
+async fn post_operation(State(handle): State<AccountingHandle>, body: Bytes) -> Result<StatusCode, ErrorResponse> {
+    let operation = serde_json::from_slice::<AccountingOperation>(&body)
+        .map_err(|err| ErrorResponse::invalid_request(err.to_string()))?;
+    handle.run_operation(operation).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_account(
+    State(handle): State<AccountingHandle>,
+    Path(client_id): Path<String>,
+) -> Result<Json<ClientAccountSnapshot>, ErrorResponse> {
+    find_account(&handle, &client_id, Currency::UNSPECIFIED).await
+}
+
+async fn get_currency_account(
+    State(handle): State<AccountingHandle>,
+    Path((client_id, currency)): Path<(String, String)>,
+) -> Result<Json<ClientAccountSnapshot>, ErrorResponse> {
+    let Ok(currency) = Currency::try_from(currency.as_str()) else {
+        return Err(ErrorResponse::invalid_request(format!("invalid currency `{currency}`")));
+    };
+    find_account(&handle, &client_id, currency).await
+}
+
+async fn find_account(
+    handle: &AccountingHandle,
+    client_id: &str,
+    currency: Currency,
+) -> Result<Json<ClientAccountSnapshot>, ErrorResponse> {
+    let Ok(client_id) = ClientId::try_from(client_id) else {
+        return Err(ErrorResponse::invalid_request(format!("invalid client `{client_id}`")));
+    };
+    match handle.account(client_id, currency).await? {
+        Some(account) => Ok(Json(account)),
+        None => Err(ErrorResponse {
+            status: StatusCode::NOT_FOUND,
+            body: ErrorBody {
+                error: "AccountNotFound",
+                message: format!("account {} does not exist", client_id.as_u16()),
+            },
+        }),
+    }
+}
+
+async fn get_accounts(
+    State(handle): State<AccountingHandle>,
+) -> Result<Json<Vec<ClientAccountSnapshot>>, ErrorResponse> {
+    Ok(Json(handle.accounts().await?))
+}
@@ -0,0 +1,49 @@
+#![deny(warnings)]
+#![warn(clippy::all, clippy::pedantic)]
+#![allow(
+    clippy::needless_return,
+    clippy::redundant_field_names,
+    clippy::unreadable_literal,
+    clippy::inline_always,
+    clippy::module_name_repetitions,
+    clippy::len_without_is_empty,
+    clippy::should_implement_trait
+)]
+
+use std::net::SocketAddr;
+
+use clap::Parser;
+use rtm_core::{processor::AccountingSystem, service::AccountingService};
+use tokio::net::TcpListener;
+
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+struct Cli {
+    /// Address to listen on.
+    #[arg(long, default_value = "127.0.0.1:7878")]
+    bind: SocketAddr,
+
+    /// Address to serve the HTTP/JSON API on. The API is disabled if not set.
+    #[arg(long)]
+    http: Option<SocketAddr>,
+}
+
+#[tokio::main]
+async fn main() -> std::io::Result<()> {
+    let cli = Cli::parse();
+    let listener = TcpListener::bind(cli.bind).await?;
+    eprintln!("Listening on {}", listener.local_addr()?);
+
+    let (handle, _) = AccountingService::spawn(AccountingSystem::new());
+
+    let Some(http) = cli.http else {
+        return rtm_server::server::serve(listener, handle).await;
+    };
+    let http_listener = TcpListener::bind(http).await?;
+    eprintln!("Serving HTTP on {}", http_listener.local_addr()?);
+    tokio::try_join!(
+        rtm_server::server::serve(listener, handle.clone()),
+        rtm_server::http::serve(http_listener, handle),
+    )?;
+    Ok(())
+}
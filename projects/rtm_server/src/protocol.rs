@@ -0,0 +1,93 @@
+use rtm_core::{
+    models::{AccountingOperation, AmountPolicies, ClientId, Currency, OperationRecord},
+    processor::ClientAccountSnapshot,
+};
+
+/// Header of the rows describing client accounts, as returned by `DUMP`.
+/// Rows of accounts in a currency have the currency as an additional last field.
+pub const ACCOUNT_HEADER: &str = "client,available,held,total,locked";
+
+/// Single line sent by a client.
+#[derive(Debug)]
+#[must_use]
+pub enum Request {
+    /// Csv row with `type, client, tx, amount` and optional `currency`, `timestamp` and
+    /// `reason` fields, in this order. The reason takes the rest of the line, commas included.
+    Operation(AccountingOperation),
+
+    /// `BALANCE <client> [<currency>]` command.
+    Balance(ClientId, Currency),
+
+    /// `DUMP` command.
+    Dump,
+}
+
+impl Request {
+    /// Parses a line, with amounts of operations parsed by `amount_policies`.
+    ///
+    /// # Errors
+    ///
+    /// Description of why the line is malformed.
+    pub fn parse(line: &str, amount_policies: &AmountPolicies) -> Result<Self, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+
+        if command.eq_ignore_ascii_case("DUMP") {
+            if words.next().is_some() {
+                return Err("DUMP takes no arguments".to_string());
+            }
+            return Ok(Request::Dump);
+        }
+
+        if command.eq_ignore_ascii_case("BALANCE") {
+            let (Some(client), currency, None) = (words.next(), words.next(), words.next()) else {
+                return Err("BALANCE takes one or two arguments".to_string());
+            };
+            let Ok(client_id) = ClientId::try_from(client) else {
+                return Err(format!("invalid client `{client}`"));
+            };
+            return match currency.map_or(Ok(Currency::UNSPECIFIED), Currency::try_from) {
+                Ok(currency) => Ok(Request::Balance(client_id, currency)),
+                Err(_) => Err(format!("invalid currency `{}`", currency.unwrap_or_default())),
+            };
+        }
+
+        let fields = line.splitn(7, ',').collect::<Vec<_>>();
+        let [record_type, client, tx, amount, optional @ ..] = fields.as_slice() else {
+            return Err(format!("expected 4 to 7 fields, found {}", fields.len()));
+        };
+        let [currency, timestamp, reason] =
+            std::array::from_fn(|index| optional.get(index).copied().unwrap_or_default());
+        let record = OperationRecord {
+            record_type,
+            client,
+            tx,
+            amount,
+            currency,
+            timestamp,
+            reason,
+        };
+        match AccountingOperation::parse_record(&record, amount_policies) {
+            Ok(operation) => Ok(Request::Operation(operation)),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+/// Formats account as a row matching [`ACCOUNT_HEADER`].
+#[must_use]
+pub fn format_account(account: &ClientAccountSnapshot) -> String {
+    let row = format!(
+        "{},{},{},{},{}",
+        account.client_id.as_u16(),
+        account.available_balance,
+        account.held_balance,
+        account.total_balance(),
+        account.state.is_locked()
+    );
+    if account.currency.is_unspecified() {
+        row
+    } else {
+        format!("{row},{}", account.currency)
+    }
+}
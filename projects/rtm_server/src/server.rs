@@ -0,0 +1,111 @@
+use rtm_core::service::{AccountingHandle, ServiceError};
+use tokio::{
+    io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::protocol::{ACCOUNT_HEADER, Request, format_account};
+
+/// Longest accepted line in bytes, so that a client can not make the server buffer unbounded input.
+pub const MAX_LINE_LENGTH: usize = 1024;
+
+/// Accepts connections until the listener fails, serving each of them on a separate task.
+///
+/// Every non-empty line sent by a client gets a response:
+///
+/// * csv operation row: `OK` or `REJECTED <reason>`,
+/// * `BALANCE <client> [<currency>]`: account row (see [`ACCOUNT_HEADER`]) or `NOT_FOUND`,
+/// * `DUMP`: [`ACCOUNT_HEADER`], row for every account and `END`,
+/// * anything malformed: `INVALID <reason>`, also lines longer than [`MAX_LINE_LENGTH`].
+///
+/// # Errors
+///
+/// When accepting a new connection fails.
+pub async fn serve(listener: TcpListener, handle: AccountingHandle) -> std::io::Result<()> {
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let handle = handle.clone();
+        tokio::spawn(async move {
+            let _ = handle_connection(stream, &handle).await;
+        });
+    }
+}
+
+async fn handle_connection(stream: TcpStream, handle: &AccountingHandle) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut buffer = Vec::new();
+    while let Some(line) = read_line(&mut reader, &mut buffer).await? {
+        let Some(line) = line else {
+            writer.write_all(b"INVALID line is too long\n").await?;
+            continue;
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let response = match respond(line, handle).await {
+            Ok(response) => response,
+            Err(ServiceError::Transaction(err)) => format!("REJECTED {err}"),
+            Err(ServiceError::Stopped) => {
+                writer.write_all(b"ERROR service stopped\n").await?;
+                return Ok(());
+            }
+        };
+        writer.write_all(response.as_bytes()).await?;
+        writer.write_all(b"\n").await?;
+    }
+    Ok(())
+}
+
+/// Reads the next line into `buffer`, returning `None` at the end of the stream. Lines longer than
+/// [`MAX_LINE_LENGTH`] are skipped up to their end without being buffered, and returned as `Some(None)`.
+async fn read_line<'a>(
+    reader: &mut (impl AsyncBufRead + Unpin),
+    buffer: &'a mut Vec<u8>,
+) -> std::io::Result<Option<Option<&'a str>>> {
+    buffer.clear();
+    let limit = MAX_LINE_LENGTH as u64 + 1;
+    if reader.take(limit).read_until(b'\n', buffer).await? == 0 {
+        return Ok(None);
+    }
+    if buffer.len() > MAX_LINE_LENGTH && buffer.last() != Some(&b'\n') {
+        loop {
+            buffer.clear();
+            if reader.take(limit).read_until(b'\n', buffer).await? == 0 || buffer.last() == Some(&b'\n') {
+                return Ok(Some(None));
+            }
+        }
+    }
+    std::str::from_utf8(buffer)
+        .map(|line| Some(Some(line)))
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+async fn respond(line: &str, handle: &AccountingHandle) -> Result<String, ServiceError> {
+    let request = match Request::parse(line, handle.amount_policies()) {
+        Ok(request) => request,
+        Err(reason) => return Ok(format!("INVALID {reason}")),
+    };
+
+    match request {
+        Request::Operation(operation) => {
+            handle.run_operation(operation).await?;
+            Ok("OK".to_string())
+        }
+        Request::Balance(client_id, currency) => match handle.account(client_id, currency).await? {
+            Some(account) => Ok(format_account(&account)),
+            None => Ok("NOT_FOUND".to_string()),
+        },
+        Request::Dump => {
+            let mut response = ACCOUNT_HEADER.to_string();
+            for account in handle.accounts().await? {
+                response.push('\n');
+                response.push_str(&format_account(&account));
+            }
+            response.push_str("\nEND");
+            Ok(response)
+        }
+    }
+}
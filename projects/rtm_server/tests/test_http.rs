@@ -0,0 +1,202 @@
+use rtm_core::{
+    models::{AmountPolicy, Rounding},
+    processor::{AccountingSystem, AccountingSystemConfig},
+    service::AccountingService,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+async fn start_server() -> std::net::SocketAddr {
+    start_server_with(AccountingSystem::new()).await
+}
+
+async fn start_server_with(accounting_system: AccountingSystem) -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (handle, _) = AccountingService::spawn(accounting_system);
+    tokio::spawn(rtm_server::http::serve(listener, handle));
+    address
+}
+
+fn with_precision(decimal_places: u32) -> AccountingSystem {
+    AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: AmountPolicy::new(decimal_places, Rounding::Reject).into(),
+        ..AccountingSystemConfig::default()
+    })
+}
+
+/// Sends a single HTTP/1.1 request and returns the status code and body of the response.
+async fn request(address: std::net::SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+    let mut stream = TcpStream::connect(address).await.unwrap();
+    let request = format!(
+        "{method} {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split_once("\r\n\r\n").unwrap().1.to_string();
+    (status, body)
+}
+
+#[tokio::test]
+async fn test_http_operations_and_accounts() {
+    let address = start_server().await;
+
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}"#;
+    assert_eq!(
+        request(address, "POST", "/operations", deposit).await,
+        (204, String::new())
+    );
+    let deposit = r#"{"type": "deposit", "client": 2, "tx": 2, "amount": "3"}"#;
+    assert_eq!(request(address, "POST", "/operations", deposit).await.0, 204);
+    let dispute = r#"{"type": "dispute", "client": 2, "tx": 2}"#;
+    assert_eq!(request(address, "POST", "/operations", dispute).await.0, 204);
+
+    assert_eq!(
+        request(address, "GET", "/accounts/1", "").await,
+        (
+            200,
+            r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false}"#.to_string()
+        )
+    );
+    assert_eq!(
+        request(address, "GET", "/accounts", "").await,
+        (
+            200,
+            r#"[{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false},{"client":2,"available":"0.0000","held":"3.0000","total":"3.0000","locked":false}]"#
+                .to_string()
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_http_errors() {
+    let address = start_server().await;
+
+    let withdrawal = r#"{"type": "withdrawal", "client": 1, "tx": 1, "amount": "1"}"#;
+    assert_eq!(
+        request(address, "POST", "/operations", withdrawal).await,
+        (
+            422,
+            r#"{"error":"InsufficientFunds","message":"insufficient funds for transaction 1"}"#.to_string()
+        )
+    );
+
+    let resolve = r#"{"type": "resolve", "client": 1, "tx": 7}"#;
+    let (status, body) = request(address, "POST", "/operations", resolve).await;
+    assert_eq!(status, 404);
+    assert!(body.contains(r#""error":"TransactionDoesNotExist""#));
+
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 2, "amount": "1"}"#;
+    assert_eq!(request(address, "POST", "/operations", deposit).await.0, 204);
+    let (status, body) = request(address, "POST", "/operations", deposit).await;
+    assert_eq!(status, 409);
+    assert!(body.contains(r#""error":"DuplicateTransaction""#));
+
+    let invalid = r#"{"type": "deposit", "client": 1, "tx": 3, "amount": "abc"}"#;
+    let (status, body) = request(address, "POST", "/operations", invalid).await;
+    assert_eq!(status, 400);
+    assert!(body.contains(r#""error":"InvalidRequest""#));
+
+    let (status, body) = request(address, "POST", "/operations", "{").await;
+    assert_eq!(status, 400);
+    assert!(body.contains(r#""error":"InvalidRequest""#));
+
+    let (status, body) = request(address, "GET", "/accounts/9", "").await;
+    assert_eq!(status, 404);
+    assert!(body.contains(r#""error":"AccountNotFound""#));
+
+    assert_eq!(request(address, "GET", "/accounts/x", "").await.0, 400);
+}
+
+#[tokio::test]
+async fn test_http_currencies() {
+    let address = start_server().await;
+
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5", "currency": "EUR"}"#;
+    assert_eq!(request(address, "POST", "/operations", deposit).await.0, 204);
+    let dispute = r#"{"type": "dispute", "client": 1, "tx": 1, "currency": "USD"}"#;
+    assert_eq!(
+        request(address, "POST", "/operations", dispute).await,
+        (
+            422,
+            r#"{"error":"CurrencyMismatch","message":"transaction 1 is in EUR, not in USD"}"#.to_string()
+        )
+    );
+
+    assert_eq!(
+        request(address, "GET", "/accounts/1/eur", "").await,
+        (
+            200,
+            r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":false,"currency":"EUR"}"#
+                .to_string()
+        )
+    );
+    assert_eq!(request(address, "GET", "/accounts/1", "").await.0, 404);
+    assert_eq!(request(address, "GET", "/accounts/1/EURO", "").await.0, 400);
+}
+
+#[tokio::test]
+async fn test_http_administrative_operations() {
+    let address = start_server().await;
+
+    let freeze = r#"{"type": "freeze", "client": 1, "reason": "suspected fraud"}"#;
+    let (status, body) = request(address, "POST", "/operations", freeze).await;
+    assert_eq!(status, 404);
+    assert!(body.contains(r#""error":"ClientDoesNotExist""#));
+
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.5"}"#;
+    assert_eq!(request(address, "POST", "/operations", deposit).await.0, 204);
+    assert_eq!(request(address, "POST", "/operations", freeze).await.0, 204);
+    assert_eq!(
+        request(address, "GET", "/accounts/1", "").await,
+        (
+            200,
+            r#"{"client":1,"available":"1.5000","held":"0.0000","total":"1.5000","locked":true,"state":"frozen"}"#
+                .to_string()
+        )
+    );
+
+    let withdrawal = r#"{"type": "withdrawal", "client": 1, "tx": 2, "amount": "1.5"}"#;
+    assert_eq!(
+        request(address, "POST", "/operations", withdrawal).await,
+        (
+            423,
+            r#"{"error":"AccountFrozen","message":"account 1 is frozen"}"#.to_string()
+        )
+    );
+
+    let unlock = r#"{"type": "unlock", "client": 1, "reason": "reviewed"}"#;
+    assert_eq!(request(address, "POST", "/operations", unlock).await.0, 204);
+    let (status, body) = request(address, "POST", "/operations", unlock).await;
+    assert_eq!(status, 409);
+    assert!(body.contains(r#""error":"AccountNotLocked""#));
+    assert_eq!(request(address, "POST", "/operations", withdrawal).await.0, 204);
+}
+
+#[tokio::test]
+async fn test_http_amount_policy() {
+    let address = start_server_with(with_precision(8)).await;
+
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 1, "amount": "1.12345678"}"#;
+    assert_eq!(request(address, "POST", "/operations", deposit).await.0, 204);
+    let deposit = r#"{"type": "deposit", "client": 1, "tx": 2, "amount": "0.000000001"}"#;
+    let (status, body) = request(address, "POST", "/operations", deposit).await;
+    assert_eq!(status, 422);
+    assert!(body.contains(r#""error":"ExcessivePrecision""#));
+
+    assert_eq!(
+        request(address, "GET", "/accounts/1", "").await,
+        (
+            200,
+            r#"{"client":1,"available":"1.12345678","held":"0.00000000","total":"1.12345678","locked":false}"#
+                .to_string()
+        )
+    );
+}
@@ -0,0 +1,186 @@
+use rtm_core::{
+    models::{AmountPolicy, Rounding},
+    processor::{AccountingSystem, AccountingSystemConfig},
+    service::AccountingService,
+};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines},
+    net::{TcpListener, TcpStream, tcp::OwnedReadHalf, tcp::OwnedWriteHalf},
+};
+
+struct Connection {
+    lines: Lines<BufReader<OwnedReadHalf>>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    async fn request(&mut self, line: &str) -> String {
+        self.writer.write_all(format!("{line}\n").as_bytes()).await.unwrap();
+        self.lines.next_line().await.unwrap().unwrap()
+    }
+}
+
+async fn start_server() -> std::net::SocketAddr {
+    start_server_with(AccountingSystem::new()).await
+}
+
+async fn start_server_with(accounting_system: AccountingSystem) -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    let (handle, _) = AccountingService::spawn(accounting_system);
+    tokio::spawn(rtm_server::server::serve(listener, handle));
+    address
+}
+
+fn with_precision(decimal_places: u32) -> AccountingSystem {
+    AccountingSystem::with_config(AccountingSystemConfig {
+        amount_policies: AmountPolicy::new(decimal_places, Rounding::Reject).into(),
+        ..AccountingSystemConfig::default()
+    })
+}
+
+async fn connect(address: std::net::SocketAddr) -> Connection {
+    let (reader, writer) = TcpStream::connect(address).await.unwrap().into_split();
+    Connection {
+        lines: BufReader::new(reader).lines(),
+        writer,
+    }
+}
+
+#[tokio::test]
+async fn test_server_operations_and_queries() {
+    let address = start_server().await;
+    let mut connection = connect(address).await;
+
+    assert_eq!(connection.request("deposit, 1, 1, 1.5").await, "OK");
+    assert_eq!(connection.request("deposit, 2, 2, 2.0").await, "OK");
+    assert_eq!(connection.request("Withdrawal, 1, 3, 1.0").await, "OK");
+    assert_eq!(
+        connection.request("withdrawal, 1, 4, 5.0").await,
+        "REJECTED insufficient funds for transaction 4"
+    );
+    assert_eq!(connection.request("dispute, 2, 2,").await, "OK");
+
+    assert_eq!(connection.request("BALANCE 1").await, "1,0.5000,0.0000,0.5000,false");
+    assert_eq!(connection.request("balance 3").await, "NOT_FOUND");
+
+    assert_eq!(connection.request("DUMP").await, "client,available,held,total,locked");
+    assert_eq!(
+        connection.lines.next_line().await.unwrap().unwrap(),
+        "1,0.5000,0.0000,0.5000,false"
+    );
+    assert_eq!(
+        connection.lines.next_line().await.unwrap().unwrap(),
+        "2,0.0000,2.0000,2.0000,false"
+    );
+    assert_eq!(connection.lines.next_line().await.unwrap().unwrap(), "END");
+}
+
+#[tokio::test]
+async fn test_server_invalid_requests() {
+    let address = start_server().await;
+    let mut connection = connect(address).await;
+
+    assert_eq!(
+        connection.request("adjustment, 1, 1, 1.0").await,
+        "INVALID column `type`: invalid value `adjustment`"
+    );
+    assert_eq!(
+        connection.request("deposit, 1, 1").await,
+        "INVALID expected 4 to 7 fields, found 3"
+    );
+    assert_eq!(connection.request("BALANCE x").await, "INVALID invalid client `x`");
+    assert_eq!(connection.request("DUMP all").await, "INVALID DUMP takes no arguments");
+    assert_eq!(
+        connection.request("BALANCE 1 EURO").await,
+        "INVALID invalid currency `EURO`"
+    );
+}
+
+#[tokio::test]
+async fn test_server_line_too_long() {
+    let address = start_server().await;
+    let mut connection = connect(address).await;
+
+    let reason = "x".repeat(4 * rtm_server::server::MAX_LINE_LENGTH);
+    assert_eq!(
+        connection.request(&format!("freeze, 1, , , , , {reason}")).await,
+        "INVALID line is too long"
+    );
+    assert_eq!(connection.request("deposit, 1, 1, 1.0").await, "OK");
+}
+
+#[tokio::test]
+async fn test_server_shares_state_between_connections() {
+    let address = start_server().await;
+    let mut first = connect(address).await;
+    let mut second = connect(address).await;
+
+    assert_eq!(first.request("deposit, 1, 1, 1.0").await, "OK");
+    assert_eq!(
+        second.request("deposit, 2, 1, 1.0").await,
+        "REJECTED transaction 1 was already processed"
+    );
+    assert_eq!(second.request("BALANCE 1").await, "1,1.0000,0.0000,1.0000,false");
+}
+
+#[tokio::test]
+async fn test_server_currencies() {
+    let address = start_server().await;
+    let mut connection = connect(address).await;
+
+    assert_eq!(connection.request("deposit, 1, 1, 1.5, EUR").await, "OK");
+    assert_eq!(connection.request("deposit, 1, 2, 2.0").await, "OK");
+    assert_eq!(
+        connection.request("dispute, 1, 1, , USD").await,
+        "REJECTED transaction 1 is in EUR, not in USD"
+    );
+
+    assert_eq!(
+        connection.request("BALANCE 1 eur").await,
+        "1,1.5000,0.0000,1.5000,false,EUR"
+    );
+    assert_eq!(connection.request("BALANCE 1").await, "1,2.0000,0.0000,2.0000,false");
+    assert_eq!(connection.request("BALANCE 1 USD").await, "NOT_FOUND");
+}
+
+#[tokio::test]
+async fn test_server_administrative_operations() {
+    let address = start_server().await;
+    let mut connection = connect(address).await;
+
+    assert_eq!(connection.request("deposit, 1, 1, 1.5").await, "OK");
+    assert_eq!(
+        connection
+            .request("freeze, 1, , , , , suspected fraud, card stolen")
+            .await,
+        "OK"
+    );
+    assert_eq!(connection.request("BALANCE 1").await, "1,1.5000,0.0000,1.5000,true");
+    assert_eq!(
+        connection.request("deposit, 1, 2, 1.0").await,
+        "REJECTED account 1 is frozen"
+    );
+    assert_eq!(
+        connection.request("unlock, 1, , ").await,
+        "INVALID column `reason`: invalid value ``"
+    );
+    assert_eq!(connection.request("unlock, 1, , , , , reviewed").await, "OK");
+    assert_eq!(connection.request("deposit, 1, 2, 1.0").await, "OK");
+}
+
+#[tokio::test]
+async fn test_server_amount_policy() {
+    let address = start_server_with(with_precision(8)).await;
+    let mut connection = connect(address).await;
+
+    assert_eq!(connection.request("deposit, 1, 1, 1.12345678").await, "OK");
+    assert_eq!(
+        connection.request("deposit, 1, 2, 0.000000001").await,
+        "INVALID column `amount`: invalid value `0.000000001`, amount has too many decimal places"
+    );
+    assert_eq!(
+        connection.request("BALANCE 1").await,
+        "1,1.12345678,0.00000000,1.12345678,false"
+    );
+}